@@ -0,0 +1,891 @@
+use crate::config::{self, CargoConfig};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::toolchain_file::{self, ToolchainFileInfo};
+use crate::{explain, history, merge, mirrors, presets, profiles, rustup, undo};
+use serde::Serialize;
+use std::io::Write;
+
+const USAGE: &str = "\
+用法: cargo-assistant [--config <path>] [--json] <command> [args]
+      cargo-assistant --help | --version
+
+命令:
+  config path                     显示配置文件路径
+  config show                     输出完整配置
+  config get <key>                读取键值，如 build.jobs
+  config set <key> <value>        写入键值，value 按 TOML 字面量解析，失败时视为字符串
+  config unset <key>              删除键
+  config export <file> [--format <fmt>] [--secrets true]
+                                  导出为 toml / dotenv / shell / github_actions / dockerfile，
+                                  未指定格式时按扩展名推断，默认排除 token 等敏感键
+  config import <file> [--format <fmt>] [--strategy <s>]
+                                  合并导入 toml / json / yaml / dotenv，--format env 读取当前环境变量；
+                                  策略 keep_existing（默认）/ prefer_imported
+  config explain [key] [--project <dir>]
+                                  显示生效值及其来源，! 表示覆盖了本程序编辑的配置
+  mirror list                     列出内置 crates.io 镜像
+  mirror get                      显示当前使用的镜像
+  mirror set <id>                 切换镜像，official 恢复官方源
+  rustup-env get                  显示 RUSTUP_DIST_SERVER / RUSTUP_UPDATE_ROOT
+  rustup-env set <id>             按内置 rustup 镜像写入环境变量
+  rustup-env set --dist <url> [--root <url>]
+                                  写入前校验地址并探测 dist 服务器，--probe false 跳过；
+                                  未指定 --root 时使用 <dist>/rustup
+  rustup-env list                 列出内置 rustup 镜像及对应的 crates.io 镜像
+  rustup-env probe [id|url]       探测 dist 服务器，默认探测当前配置
+  rustup-env clear                清除 rustup 镜像环境变量
+  rustup-settings get             显示 rustup settings.toml
+  rustup-settings set <key> <value>
+                                  key 为 default-host / default-toolchain / profile /
+                                  auto-self-update / auto-install
+  toolchain list [--cwd <dir>]    列出工具链，* 为默认，> 为当前生效
+  toolchain install <name> [--profile minimal|default|complete]
+  toolchain uninstall <name>      卸载工具链
+  toolchain default <name>        设置默认工具链
+  toolchain update [name]         使用已配置的 rustup 镜像更新
+  toolchain file [dir]            检查项目的 rust-toolchain.toml 是否已满足
+  toolchain sync [dir]            安装 rust-toolchain.toml 要求的工具链、组件和目标
+  override list                   列出目录覆盖，! 表示目录已不存在
+  override set <toolchain> [dir]  为目录设置覆盖工具链
+  override unset [dir]            移除目录覆盖，--nonexistent true 清理已删除目录
+  override show [dir]             显示目录实际使用的工具链及来源
+  component list [--toolchain <name>]
+  component add <name>... [--toolchain <name>]
+  component remove <name>... [--toolchain <name>]
+  target list [--toolchain <name>] [--all true]
+                                  列出目标平台，默认只显示有预编译标准库的目标
+  target add <name> [--toolchain <name>]
+  target remove <name> [--toolchain <name>]
+  profile list                    列出配置方案，* 表示当前生效
+  profile diff <name>             显示应用方案会修改的键
+  profile apply <name>            备份后应用方案
+  preset list                     列出预设，! 表示不适用于当前平台
+  preset preview <id>             显示应用预设会修改的键
+  preset apply <id>               备份后合并预设
+  preset import <file> [--replace true]
+  backup list                     列出备份
+  backup create [--label <name>]  创建备份
+  backup restore <name>           从备份恢复
+  backup delete <name>            删除备份
+
+退出码: 0 成功, 1 其他错误, 2 参数错误, 3 不存在, 4 冲突或被占用,
+        5 解析失败, 6 权限不足, 7 外部命令失败";
+
+const COMMANDS: &[&str] = &[
+    "help",
+    "config",
+    "mirror",
+    "rustup-env",
+    "rustup-settings",
+    "toolchain",
+    "override",
+    "component",
+    "target",
+    "profile",
+    "preset",
+    "backup",
+];
+
+/// 命令执行结果：`json` 用于 `--json` 输出，`text` 用于终端显示
+pub struct CliOutput {
+    pub json: serde_json::Value,
+    pub text: String,
+}
+
+fn output(value: impl Serialize, text: impl Into<String>) -> AppResult<CliOutput> {
+    Ok(CliOutput {
+        json: serde_json::to_value(value).map_err(AppError::serialize)?,
+        text: text.into(),
+    })
+}
+
+fn usage_error(message: &str) -> AppError {
+    AppError::invalid(message.to_string()).with_detail(USAGE)
+}
+
+pub fn exit_code(code: ErrorCode) -> i32 {
+    match code {
+        ErrorCode::InvalidInput => 2,
+        ErrorCode::NotFound => 3,
+        ErrorCode::Conflict | ErrorCode::Locked | ErrorCode::AlreadyExists => 4,
+        ErrorCode::Parse => 5,
+        ErrorCode::PermissionDenied => 6,
+        ErrorCode::Command => 7,
+        ErrorCode::Serialize | ErrorCode::Io | ErrorCode::Internal => 1,
+    }
+}
+
+struct Args {
+    config: Option<String>,
+    json: bool,
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> AppResult<Args> {
+        let mut args = Args {
+            config: None,
+            json: false,
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--json" => args.json = true,
+                "--config" | "-c" => {
+                    let value = iter.next().ok_or_else(|| usage_error("--config 缺少路径"))?;
+                    args.config = Some(value.clone());
+                }
+                flag if flag.starts_with("--") && flag.len() > 2 => {
+                    let (name, value) = match flag[2..].split_once('=') {
+                        Some((name, value)) => (name.to_string(), value.to_string()),
+                        None => {
+                            let value = iter
+                                .next()
+                                .ok_or_else(|| usage_error(&format!("{} 缺少参数值", flag)))?;
+                            (flag[2..].to_string(), value.clone())
+                        }
+                    };
+                    args.options.push((name, value));
+                }
+                _ => args.positional.push(arg.clone()),
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn arg(&self, index: usize, what: &str) -> AppResult<&str> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| usage_error(&format!("缺少参数: {}", what)))
+    }
+
+    fn path(&self) -> Option<&str> {
+        self.config.as_deref()
+    }
+}
+
+// 与 GUI 保存走同一条路径：带修订号校验写入，并记录撤销和 git 历史
+fn write_config(args: &Args, config: &CargoConfig, revision: &str) -> AppResult<String> {
+    let before = undo::read_before(args.path());
+    let revision = config::save_config_checked(config, args.path(), Some(revision))?;
+    let _ = undo::record_change(args.path(), &before, "cli");
+    history::record_after_write(args.path());
+    Ok(revision)
+}
+
+fn value_text(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Table(table) => toml::to_string_pretty(table).unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+// value 优先按 TOML 字面量解析（数字、布尔、数组、内联表），否则作为字符串
+fn parse_cli_value(raw: &str) -> toml::Value {
+    format!("value = {}", raw)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn lookup<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    config::split_key_path(key)
+        .iter()
+        .try_fold(value, |current, segment| current.get(segment.as_str()))
+}
+
+// 按 serde 名称解析枚举类型的选项，如 `--format dotenv`
+fn parse_enum_option<T: serde::de::DeserializeOwned>(args: &Args, name: &str) -> AppResult<Option<T>> {
+    match args.option(name) {
+        Some(value) => serde_json::from_value(serde_json::Value::from(value))
+            .map(Some)
+            .map_err(|_| usage_error(&format!("--{} 的取值无效: {}", name, value))),
+        None => Ok(None),
+    }
+}
+
+fn config_command(args: &Args) -> AppResult<CliOutput> {
+    match args.arg(1, "config 子命令")? {
+        "path" => {
+            let path = config::resolve_config_path(args.path());
+            let path = path.to_string_lossy().to_string();
+            output(&path, path.clone())
+        }
+        "show" => {
+            let loaded = config::load_config_with_revision(args.path())?;
+            let text = config::serialize_config(&loaded.config)?;
+            output(&loaded, text)
+        }
+        "get" => {
+            let key = args.arg(2, "key")?;
+            let loaded = config::load_config_with_revision(args.path())?;
+            let value = config::config_to_value(&loaded.config)?;
+            let found = lookup(&value, key)
+                .ok_or_else(|| AppError::not_found("配置项不存在").with_key(key.to_string()))?;
+            output(found, value_text(found))
+        }
+        "export" => {
+            let file = args.arg(2, "导出文件")?;
+            let format = parse_enum_option(args, "format")?;
+            let include_secrets = args.option("secrets") == Some("true");
+            let config = config::load_config(args.path())?;
+            let result = config::export_config_to_path(&config, file, format, include_secrets)?;
+            let mut lines = vec![format!("已导出 {} 项到 {}", result.exported, result.path)];
+            lines.extend(result.skipped.iter().map(|s| format!("跳过 {}: {}", s.key, s.reason)));
+            output(&result, lines.join("\n"))
+        }
+        "import" => {
+            let format: Option<config::ImportFormat> = parse_enum_option(args, "format")?;
+            let strategy = parse_enum_option(args, "strategy")?
+                .unwrap_or(merge::ImportStrategy::KeepExisting);
+            let imported = match (format, args.positional.get(2)) {
+                (Some(config::ImportFormat::Env), _) => config::import_config_from_env(),
+                (_, Some(file)) => config::import_config_from_path(file, format)?,
+                (_, None) => return Err(usage_error("缺少参数: 导入文件")),
+            };
+            let loaded = config::load_config_with_revision(args.path())?;
+            let result = merge::merge_import(&loaded.config, imported, strategy, &Default::default())?;
+            write_config(args, &result.merged, &loaded.revision)?;
+            let mut lines = vec![format!(
+                "新增 {} 项，覆盖 {} 项，跳过 {} 项",
+                result.added.len(),
+                result.overridden.len(),
+                result.skipped.len()
+            )];
+            lines.extend(result.overridden.iter().map(|k| format!("覆盖 {}", k.key)));
+            lines.extend(result.skipped.iter().map(|k| format!("跳过 {}", k.key)));
+            output(&result, lines.join("\n"))
+        }
+        "explain" => {
+            let key = args.positional.get(2).map(String::as_str);
+            let report = explain::explain_config(args.path(), args.option("project"), key)?;
+            let mut lines: Vec<String> = report
+                .settings
+                .iter()
+                .map(|s| {
+                    let mark = if s.overrides_app_config { "!" } else { " " };
+                    format!("{} {} = {}    <- {}", mark, s.key, s.value, s.origin.source)
+                })
+                .collect();
+            if !report.app_config_loaded {
+                lines.push(format!("注意: cargo 不会读取 {}", report.config_path));
+            }
+            output(&report, lines.join("\n"))
+        }
+        "set" | "unset" => {
+            let key = args.arg(2, "key")?;
+            let loaded = config::load_config_with_revision(args.path())?;
+            let mut table = match config::config_to_value(&loaded.config)? {
+                toml::Value::Table(table) => table,
+                _ => toml::Table::new(),
+            };
+            if args.positional[1] == "set" {
+                let value = parse_cli_value(args.arg(3, "value")?);
+                if !config::insert_key_path(&mut table, key, value) {
+                    return Err(AppError::new(ErrorCode::Conflict, "父级键不是表，无法写入")
+                        .with_key(key.to_string()));
+                }
+            } else if !config::remove_key_path(&mut table, key) {
+                return Err(AppError::not_found("配置项不存在").with_key(key.to_string()));
+            }
+            let config: CargoConfig = toml::Value::Table(table).try_into().map_err(|e| {
+                AppError::invalid("值的类型与配置项不符")
+                    .with_key(key.to_string())
+                    .with_detail(e)
+            })?;
+            let revision = write_config(args, &config, &loaded.revision)?;
+            output(serde_json::json!({ "key": key, "revision": revision }), format!("已更新 {}", key))
+        }
+        other => Err(usage_error(&format!("未知的 config 子命令: {}", other))),
+    }
+}
+
+fn mirror_command(args: &Args) -> AppResult<CliOutput> {
+    match args.arg(1, "mirror 子命令")? {
+        "list" => {
+            let text = mirrors::CRATES_MIRRORS
+                .iter()
+                .map(|m| format!("{:<10} {:<24} {}", m.id, m.name, m.registry))
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(mirrors::CRATES_MIRRORS, text)
+        }
+        "get" => {
+            let config = config::load_config(args.path())?;
+            let id = mirrors::current_crates_mirror(&config);
+            output(serde_json::json!({ "id": id }), id)
+        }
+        "set" => {
+            let id = args.arg(2, "镜像 id")?;
+            let loaded = config::load_config_with_revision(args.path())?;
+            let mut config = loaded.config;
+            mirrors::apply_crates_mirror(&mut config, id)?;
+            let revision = write_config(args, &config, &loaded.revision)?;
+            output(
+                serde_json::json!({ "id": id, "revision": revision }),
+                format!("已切换到 {}", id),
+            )
+        }
+        other => Err(usage_error(&format!("未知的 mirror 子命令: {}", other))),
+    }
+}
+
+// 镜像站通常把 rustup-init 放在 dist 地址下的 rustup 目录
+fn default_update_root(dist: &str) -> String {
+    format!("{}/rustup", dist.trim_end_matches('/'))
+}
+
+fn rustup_env_command(args: &Args) -> AppResult<CliOutput> {
+    let (dist, root) = match args.arg(1, "rustup-env 子命令")? {
+        "get" => {
+            let status = config::get_rustup_env_status();
+            let (dist, root) = config::get_rustup_env_effective();
+            let text = format!(
+                "RUSTUP_DIST_SERVER={}\nRUSTUP_UPDATE_ROOT={}",
+                dist.unwrap_or_default(),
+                root.unwrap_or_default()
+            );
+            return output(&status, text);
+        }
+        "set" => match (args.positional.get(2), args.option("dist")) {
+            (Some(id), _) => {
+                let mirror = mirrors::find_rustup_mirror(id)
+                    .ok_or_else(|| AppError::not_found("未知的 rustup 镜像").with_key(id.clone()))?;
+                (mirror.dist.to_string(), mirror.root.to_string())
+            }
+            (None, Some(dist)) => {
+                let root = args
+                    .option("root")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| default_update_root(dist));
+                (dist.to_string(), root)
+            }
+            (None, None) => return Err(usage_error("请指定镜像 id 或 --dist")),
+        },
+        "clear" => (String::new(), String::new()),
+        "list" => {
+            let text = mirrors::RUSTUP_MIRRORS
+                .iter()
+                .map(|m| format!("{:<10} {:<16} {:<10} {}", m.id, m.name, m.crates, m.dist))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return output(mirrors::RUSTUP_MIRRORS, text);
+        }
+        "probe" => {
+            let dist = match args.positional.get(2) {
+                Some(id) => match mirrors::find_rustup_mirror(id) {
+                    Some(mirror) => mirror.dist.to_string(),
+                    None => id.clone(),
+                },
+                None => config::get_rustup_env_effective().0.unwrap_or_default(),
+            };
+            if dist.is_empty() {
+                return Err(usage_error("未设置 RUSTUP_DIST_SERVER，请指定镜像 id 或地址"));
+            }
+            let probe = mirrors::probe_rustup_dist(&dist)?;
+            let text = format!("{} ({} ms)\n{}", probe.url, probe.elapsed_ms, probe.checksum);
+            return output(&probe, text);
+        }
+        other => return Err(usage_error(&format!("未知的 rustup-env 子命令: {}", other))),
+    };
+
+    let probe = args.option("probe") != Some("false");
+    let check = mirrors::check_rustup_env(Some(&dist), Some(&root), probe)?;
+    let mut result = config::set_rustup_env(check.dist, check.root);
+    result.warning = check.warning;
+    let failures: Vec<String> = [("user", &result.user), ("system", &result.system)]
+        .iter()
+        .filter(|(_, outcome)| !outcome.ok && !outcome.skipped)
+        .map(|(scope, outcome)| format!("{}: {}", scope, outcome.error.clone().unwrap_or_default()))
+        .collect();
+    if !result.user.ok && !result.user.skipped {
+        return Err(AppError::new(ErrorCode::Io, "写入 Rustup 环境变量失败")
+            .with_detail(failures.join("; ")));
+    }
+    let mut text = "已更新 Rustup 环境变量".to_string();
+    for failure in result.warning.iter().chain(&failures) {
+        text.push_str(&format!("\n警告: {}", failure));
+    }
+    output(&result, text)
+}
+
+fn rustup_settings_command(args: &Args) -> AppResult<CliOutput> {
+    let settings = match args.arg(1, "rustup-settings 子命令")? {
+        "get" => rustup::read_rustup_settings()?,
+        "set" => {
+            let value = Some(args.arg(3, "设置值")?.to_string());
+            let mut update = rustup::RustupSettingsUpdate::default();
+            match args.arg(2, "设置项")? {
+                "default-host" => update.default_host_triple = value,
+                "default-toolchain" => update.default_toolchain = value,
+                "profile" => update.profile = value,
+                "auto-self-update" => update.auto_self_update = value,
+                "auto-install" => update.auto_install = value,
+                other => return Err(usage_error(&format!("未知的 rustup 设置项: {}", other))),
+            }
+            rustup::update_rustup_settings(&update)?
+        }
+        other => return Err(usage_error(&format!("未知的 rustup-settings 子命令: {}", other))),
+    };
+    let text = [
+        ("default-host", &settings.default_host_triple),
+        ("default-toolchain", &settings.default_toolchain),
+        ("profile", &settings.profile),
+        ("auto-self-update", &settings.auto_self_update),
+        ("auto-install", &settings.auto_install),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{:<18} {}", key, value.as_deref().unwrap_or("-")))
+    .collect::<Vec<_>>()
+    .join("\n");
+    output(&settings, text)
+}
+
+fn toolchain_command(args: &Args) -> AppResult<CliOutput> {
+    let result = match args.arg(1, "toolchain 子命令")? {
+        "list" => {
+            let list = rustup::list_toolchains(args.option("cwd"))?;
+            let text = list
+                .toolchains
+                .iter()
+                .map(|t| {
+                    let default = if t.is_default { "*" } else { " " };
+                    let active = if t.is_active { ">" } else { " " };
+                    format!("{}{} {}", default, active, t.name)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return output(&list, text);
+        }
+        "install" => rustup::install_toolchain(args.arg(2, "工具链")?, args.option("profile"))?,
+        "uninstall" => rustup::uninstall_toolchain(args.arg(2, "工具链")?)?,
+        "default" => rustup::set_default_toolchain(args.arg(2, "工具链")?)?,
+        "update" => rustup::update_toolchains(args.positional.get(2).map(String::as_str))?,
+        sub @ ("file" | "sync") => {
+            let dir = args.positional.get(2).map(String::as_str).unwrap_or(".");
+            let info = if sub == "sync" {
+                toolchain_file::install_toolchain_file(dir)?
+            } else {
+                toolchain_file::read_toolchain_file(dir)?
+            };
+            return output(&info, toolchain_file_text(&info));
+        }
+        other => return Err(usage_error(&format!("未知的 toolchain 子命令: {}", other))),
+    };
+    let text = if result.updates.is_empty() {
+        result.output.clone()
+    } else {
+        result
+            .updates
+            .iter()
+            .map(|u| format!("{:<10} {} - {}", u.status, u.toolchain, u.version))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    output(&result, text)
+}
+
+fn toolchain_file_text(info: &ToolchainFileInfo) -> String {
+    if !info.exists {
+        return format!("{} 不存在", info.path);
+    }
+    let spec = &info.spec;
+    let mut lines = vec![info.path.clone()];
+    let toolchain = spec.channel.as_deref().or(spec.path.as_deref()).unwrap_or_default();
+    let mark = if info.status.toolchain_installed { "*" } else { " " };
+    lines.push(format!("{} toolchain  {}", mark, toolchain));
+    if let Some(profile) = &spec.profile {
+        lines.push(format!("  profile    {}", profile));
+    }
+    for (kind, wanted, missing) in [
+        ("component", &spec.components, &info.status.missing_components),
+        ("target", &spec.targets, &info.status.missing_targets),
+    ] {
+        for name in wanted {
+            let mark = if missing.contains(name) { " " } else { "*" };
+            lines.push(format!("{} {:<10} {}", mark, kind, name));
+        }
+    }
+    for issue in &info.status.issues {
+        lines.push(format!("! {}", issue));
+    }
+    lines.join("\n")
+}
+
+fn override_command(args: &Args) -> AppResult<CliOutput> {
+    let dir = |index: usize| args.positional.get(index).map(String::as_str).unwrap_or(".");
+    let overrides = match args.arg(1, "override 子命令")? {
+        "list" => rustup::list_overrides()?,
+        "set" => rustup::set_override(dir(3), args.arg(2, "工具链")?)?,
+        "unset" if args.option("nonexistent") == Some("true") => rustup::unset_override(None)?,
+        "unset" => rustup::unset_override(Some(dir(2)))?,
+        "show" => {
+            let resolved = rustup::resolve_toolchain_for(dir(2))?;
+            let toolchain = resolved.toolchain.as_deref().unwrap_or("-");
+            let mut text = format!("{} ({})", toolchain, resolved.source);
+            if let Some(origin) = &resolved.origin {
+                text.push_str(&format!("\n来源: {}", origin));
+            }
+            if let Some(error) = &resolved.error {
+                text.push_str(&format!("\n错误: {}", error));
+            }
+            return output(&resolved, text);
+        }
+        other => return Err(usage_error(&format!("未知的 override 子命令: {}", other))),
+    };
+    let text = overrides
+        .iter()
+        .map(|o| format!("{} {:<40} {}", if o.exists { " " } else { "!" }, o.path, o.toolchain))
+        .collect::<Vec<_>>()
+        .join("\n");
+    output(&overrides, text)
+}
+
+fn component_command(args: &Args) -> AppResult<CliOutput> {
+    let toolchain = args.option("toolchain");
+    let names = args.positional.get(2..).unwrap_or_default().to_vec();
+    let list = match args.arg(1, "component 子命令")? {
+        "list" => rustup::list_components(toolchain)?,
+        "add" => rustup::add_components(toolchain, &names)?.list,
+        "remove" => rustup::remove_components(toolchain, &names)?.list,
+        other => return Err(usage_error(&format!("未知的 component 子命令: {}", other))),
+    };
+    let text = list
+        .components
+        .iter()
+        .map(|c| format!("{} {}", if c.installed { "*" } else { " " }, c.name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    output(&list, text)
+}
+
+fn target_command(args: &Args) -> AppResult<CliOutput> {
+    let toolchain = args.option("toolchain");
+    match args.arg(1, "target 子命令")? {
+        "list" => {
+            let mut list = rustup::list_targets(toolchain)?;
+            if args.option("all") != Some("true") {
+                list.targets.retain(|t| t.prebuilt);
+            }
+            let text = list
+                .targets
+                .iter()
+                .map(|t| format!("{} T{} {}", if t.installed { "*" } else { " " }, t.tier, t.name))
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(&list, text)
+        }
+        "add" => {
+            let target = args.arg(2, "目标平台")?;
+            rustup::install_target(toolchain, target)?;
+            output(target, format!("已安装 {}", target))
+        }
+        "remove" => {
+            let target = args.arg(2, "目标平台")?;
+            rustup::remove_target(toolchain, target)?;
+            output(target, format!("已移除 {}", target))
+        }
+        other => Err(usage_error(&format!("未知的 target 子命令: {}", other))),
+    }
+}
+
+fn profile_command(args: &Args) -> AppResult<CliOutput> {
+    match args.arg(1, "profile 子命令")? {
+        "list" => {
+            let items = profiles::list_profiles(args.path())?;
+            let text = items
+                .iter()
+                .map(|p| {
+                    let mark = if p.active { "*" } else { " " };
+                    let description = p.profile.description.as_deref().unwrap_or_default();
+                    format!("{} {:<20} {}", mark, p.profile.name, description)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(&items, text)
+        }
+        "diff" => {
+            let diff = profiles::diff_profile(args.path(), args.arg(2, "方案名称")?)?;
+            let mut lines: Vec<String> = diff
+                .changes
+                .iter()
+                .map(|c| format!("{:<8} {}", c.kind, c.key))
+                .collect();
+            if diff.rustup_changed {
+                lines.push("changed  rustup-env".to_string());
+            }
+            output(&diff, lines.join("\n"))
+        }
+        "apply" => {
+            let name = args.arg(2, "方案名称")?;
+            let before = undo::read_before(args.path());
+            let result = profiles::apply_profile(args.path(), name)?;
+            let _ = undo::record_change(args.path(), &before, "profile");
+            history::record_after_write(args.path());
+            let text = format!("已应用 {}，修改 {} 项", name, result.changes.len());
+            output(&result, text)
+        }
+        other => Err(usage_error(&format!("未知的 profile 子命令: {}", other))),
+    }
+}
+
+fn changes_text(changes: &[config::KeyChange]) -> String {
+    changes
+        .iter()
+        .map(|c| format!("{:<8} {}", c.kind, c.key))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn preset_command(args: &Args) -> AppResult<CliOutput> {
+    match args.arg(1, "preset 子命令")? {
+        "list" => {
+            let items = presets::list_presets();
+            let text = items
+                .iter()
+                .map(|p| {
+                    let mark = if p.supported { " " } else { "!" };
+                    format!("{} {:<20} {}", mark, p.preset.id, p.preset.name)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(&items, text)
+        }
+        "preview" => {
+            let preview = presets::preview_preset(args.path(), args.arg(2, "预设 id")?)?;
+            let text = changes_text(&preview.changes);
+            output(&preview, text)
+        }
+        "apply" => {
+            let id = args.arg(2, "预设 id")?;
+            let before = undo::read_before(args.path());
+            let result = presets::apply_preset(args.path(), id)?;
+            let _ = undo::record_change(args.path(), &before, "preset");
+            history::record_after_write(args.path());
+            let text = format!("已应用 {}，修改 {} 项", id, result.changes.len());
+            output(&result, text)
+        }
+        "import" => {
+            let replace = args.option("replace") == Some("true");
+            let preset = presets::import_preset(args.arg(2, "预设文件")?, replace)?;
+            let text = format!("已导入 {}", preset.id);
+            output(&preset, text)
+        }
+        other => Err(usage_error(&format!("未知的 preset 子命令: {}", other))),
+    }
+}
+
+fn backup_command(args: &Args) -> AppResult<CliOutput> {
+    match args.arg(1, "backup 子命令")? {
+        "list" => {
+            let items = config::list_backups(args.path())?;
+            let text = items
+                .iter()
+                .map(|b| format!("{:<48} {:>8} {}", b.name, b.size, b.kind))
+                .collect::<Vec<_>>()
+                .join("\n");
+            output(&items, text)
+        }
+        "create" => {
+            let label = args.option("label").map(str::to_string);
+            let entry = config::create_backup(args.path(), label)?;
+            let text = format!("已创建备份 {}", entry.name);
+            output(&entry, text)
+        }
+        "restore" => {
+            let name = args.arg(2, "备份名称")?;
+            let before = undo::read_before(args.path());
+            config::restore_backup(args.path(), name.to_string())?;
+            let _ = undo::record_change(args.path(), &before, "restore_backup");
+            history::record_after_write(args.path());
+            output(serde_json::json!({ "name": name }), format!("已从 {} 恢复", name))
+        }
+        "delete" => {
+            let name = args.arg(2, "备份名称")?;
+            config::delete_backup(args.path(), name.to_string())?;
+            output(serde_json::json!({ "name": name }), format!("已删除 {}", name))
+        }
+        other => Err(usage_error(&format!("未知的 backup 子命令: {}", other))),
+    }
+}
+
+/// 解析并执行一条命令，不做任何输出，便于脚本和测试直接调用
+pub fn execute(raw: &[String]) -> (bool, AppResult<CliOutput>) {
+    let args = match Args::parse(raw) {
+        Ok(args) => args,
+        Err(err) => return (raw.iter().any(|arg| arg == "--json"), Err(err)),
+    };
+    let result = match args.positional.first().map(String::as_str) {
+        None | Some("help") => output(USAGE, USAGE),
+        Some("config") => config_command(&args),
+        Some("mirror") => mirror_command(&args),
+        Some("rustup-env") => rustup_env_command(&args),
+        Some("rustup-settings") => rustup_settings_command(&args),
+        Some("toolchain") => toolchain_command(&args),
+        Some("override") => override_command(&args),
+        Some("component") => component_command(&args),
+        Some("target") => target_command(&args),
+        Some("profile") => profile_command(&args),
+        Some("preset") => preset_command(&args),
+        Some("backup") => backup_command(&args),
+        Some(other) => Err(usage_error(&format!("未知命令: {}", other))),
+    };
+    (args.json, result)
+}
+
+/// 是否按命令行工具运行：只认已知子命令和 `--help` / `--version`，
+/// 其他参数（如 macOS 的 `-psn_*`、文件关联传入的路径）仍启动 GUI
+pub fn is_cli_invocation(raw: &[String]) -> bool {
+    if raw.first().map(String::as_str) == Some("assistant") {
+        return true;
+    }
+    if raw
+        .iter()
+        .any(|arg| matches!(arg.as_str(), "--help" | "-h" | "--version" | "-V"))
+    {
+        return true;
+    }
+    // 参数不完整时也按命令行处理，由 execute 报告用法错误
+    let command = match Args::parse(raw) {
+        Ok(args) => args.positional.first().cloned(),
+        Err(_) => raw.iter().find(|arg| !arg.starts_with('-')).cloned(),
+    };
+    command.is_some_and(|command| COMMANDS.contains(&command.as_str()))
+}
+
+/// 命令行入口，返回进程退出码；通过 `cargo assistant` 调用时会多出一个 `assistant` 参数
+pub fn run(mut raw: Vec<String>) -> i32 {
+    if raw.first().map(String::as_str) == Some("assistant") {
+        raw.remove(0);
+    }
+    if raw.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return 0;
+    }
+    if raw.iter().any(|arg| arg == "--version" || arg == "-V") {
+        println!("cargo-assistant {}", env!("CARGO_PKG_VERSION"));
+        return 0;
+    }
+
+    history::set_warning_listener(|err| eprintln!("警告: 历史记录提交失败: {}", err));
+    let (json, result) = execute(&raw);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match result {
+        Ok(result) => {
+            if json {
+                let _ = writeln!(out, "{}", result.json);
+            } else if !result.text.is_empty() {
+                let _ = writeln!(out, "{}", result.text);
+            }
+            0
+        }
+        Err(err) => {
+            if json {
+                let _ = writeln!(out, "{}", serde_json::json!({ "error": err }));
+            } else {
+                eprintln!("错误: {}", err);
+            }
+            exit_code(err.code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_support::TestDir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_config(dir: &TestDir, content: &str) -> PathBuf {
+        let path = dir.path().join("config.toml");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn run_args(path: &std::path::Path, args: &[&str]) -> AppResult<CliOutput> {
+        let mut raw = vec!["--config".to_string(), path.to_string_lossy().to_string()];
+        raw.extend(args.iter().map(|arg| arg.to_string()));
+        execute(&raw).1
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn only_known_commands_start_the_cli() {
+        assert!(is_cli_invocation(&strings(&["config", "show"])));
+        assert!(is_cli_invocation(&strings(&["--json", "-c", "x.toml", "mirror", "get"])));
+        assert!(is_cli_invocation(&strings(&["assistant"])));
+        assert!(is_cli_invocation(&strings(&["--version"])));
+        assert!(is_cli_invocation(&strings(&["rustup-env", "set", "--dist"])));
+        assert!(!is_cli_invocation(&[]));
+        assert!(!is_cli_invocation(&strings(&["-psn_0_12345"])));
+        assert!(!is_cli_invocation(&strings(&["/home/user/.cargo/config.toml"])));
+    }
+
+    #[test]
+    fn config_set_get_and_unset_round_trip() {
+        let dir = TestDir::new("roundtrip");
+        let path = temp_config(&dir, "[build]\njobs = 2\n");
+        let got = run_args(&path, &["config", "get", "build.jobs"]).unwrap();
+        assert_eq!(got.text, "2");
+
+        run_args(&path, &["config", "set", "http.timeout", "30"]).unwrap();
+        run_args(&path, &["config", "set", "build.target-dir", "out"]).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("timeout = 30"), "{}", content);
+        assert!(content.contains("target-dir = \"out\""), "{}", content);
+
+        run_args(&path, &["config", "unset", "build.jobs"]).unwrap();
+        let err = run_args(&path, &["config", "get", "build.jobs"]).err().unwrap();
+        assert_eq!(exit_code(err.code), 3);
+    }
+
+    #[test]
+    fn config_errors_map_to_exit_codes() {
+        let dir = TestDir::new("errors");
+        let path = temp_config(&dir, "[build]\njobs = 2\n");
+        let err = run_args(&path, &["config", "unset", "http.timeout"]).err().unwrap();
+        assert_eq!(exit_code(err.code), 3);
+        let err = run_args(&path, &["config", "set", "build.jobs.x", "1"]).err().unwrap();
+        assert_eq!(exit_code(err.code), 4);
+        let err = run_args(&path, &["config", "bogus"]).err().unwrap();
+        assert_eq!(exit_code(err.code), 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[build]\njobs = 2\n");
+    }
+
+    #[test]
+    fn json_flag_is_reported() {
+        let dir = TestDir::new("json");
+        let path = temp_config(&dir, "[http]\ntimeout = 3\n");
+        let raw = strings(&["--json", "--config", &path.to_string_lossy(), "config", "get", "http.timeout"]);
+        let (json, result) = execute(&raw);
+        assert!(json);
+        assert_eq!(result.unwrap().json, serde_json::json!(3));
+    }
+
+    #[test]
+    fn update_root_defaults_to_rustup_dir() {
+        assert_eq!(default_update_root("https://rsproxy.cn"), "https://rsproxy.cn/rustup");
+        assert_eq!(
+            default_update_root("https://mirrors.ustc.edu.cn/rust-static/"),
+            "https://mirrors.ustc.edu.cn/rust-static/rustup"
+        );
+    }
+}
@@ -1,14 +1,19 @@
+use crate::error::{AppError, AppResult, ErrorCode};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
 const RUSTUP_DIST_SERVER: &str = "RUSTUP_DIST_SERVER";
 const RUSTUP_UPDATE_ROOT: &str = "RUSTUP_UPDATE_ROOT";
+const BACKUP_MANIFEST: &str = "checksums.json";
+const BACKUP_QUARANTINE_DIR: &str = "quarantine";
 #[cfg(not(target_os = "windows"))]
 const RUSTUP_MANAGED_START: &str = "# >>> quickchange rustup env >>>";
 #[cfg(not(target_os = "windows"))]
@@ -20,6 +25,77 @@ pub struct BackupEntry {
     pub path: String,
     pub modified: u64,
     pub size: u64,
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct BackupChecksum {
+    hash: String,
+    size: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupIssue {
+    pub name: String,
+    pub kind: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Default, Clone)]
+pub struct BackupVerifyReport {
+    pub checked: usize,
+    pub ok: usize,
+    pub unverified: usize,
+    pub issues: Vec<BackupIssue>,
+    pub quarantined: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupSettings {
+    pub root: String,
+    pub custom: bool,
+    pub dir: String,
+    pub migrated: usize,
+    /// 迁移时目标中已存在、因而保留在原目录的项（如另一台机器同步来的 history 仓库）
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LoadedConfig {
+    pub config: CargoConfig,
+    pub revision: String,
+    pub path: String,
+    pub skipped: Vec<SkippedSection>,
+}
+
+/// 恢复模式下因解析失败而跳过的表，`content` 为原文，保存前需由用户处理
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedSection {
+    pub header: String,
+    pub line: usize,
+    pub content: String,
+    pub error: AppError,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyChange {
+    pub key: String,
+    pub kind: String,
+    pub old: Option<toml::Value>,
+    pub new: Option<toml::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AppSettings {
+    pub backup_root: Option<String>,
+    #[serde(default)]
+    pub git_history: bool,
+    #[serde(default)]
+    pub watch_enabled: bool,
+    pub watch_path: Option<String>,
+    pub backup_interval_minutes: Option<u64>,
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -71,6 +147,9 @@ pub struct RustupEnvWriteOutcome {
 pub struct RustupEnvWriteResult {
     pub user: RustupEnvWriteOutcome,
     pub system: RustupEnvWriteOutcome,
+    /// 写入前检查跳过的步骤，如无法运行 curl 时未探测镜像
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -194,12 +273,573 @@ pub fn get_cargo_config_path() -> PathBuf {
         .join("config.toml")
 }
 
-pub fn get_backup_dir(config_path: Option<&str>) -> PathBuf {
-    let config_path = resolve_config_path(config_path);
+#[cfg(not(test))]
+pub fn get_app_data_dir() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(appdata) = normalize_env_value(std::env::var("APPDATA").ok()) {
+            return Path::new(&appdata).join("quickchange");
+        }
+    }
+    Path::new(&get_home_dir()).join(".config").join("quickchange")
+}
+
+// 测试时不读写开发者真实的设置、撤销记录和历史仓库
+#[cfg(test)]
+pub fn get_app_data_dir() -> PathBuf {
+    test_support::app_data_dir()
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, MutexGuard};
+
+    static ACTIVE: Mutex<Option<PathBuf>> = Mutex::new(None);
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    pub fn app_data_dir() -> PathBuf {
+        ACTIVE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+            .map(|root| root.join("appdata"))
+            .unwrap_or_else(|| {
+                std::env::temp_dir().join(format!("cargo-assistant-appdata-{}", std::process::id()))
+            })
+    }
+
+    /// 测试用临时目录：`path()` 存放测试文件，同级的 appdata 作为应用数据目录；
+    /// 同一时间只有一个测试持有，离开作用域（包括测试失败）时整个删除
+    pub struct TestDir {
+        root: PathBuf,
+        work: PathBuf,
+        _serial: MutexGuard<'static, ()>,
+    }
+
+    impl TestDir {
+        pub fn new(name: &str) -> Self {
+            let serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let root = std::env::temp_dir().join(format!("cargo-assistant-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            let work = root.join("work");
+            fs::create_dir_all(&work).unwrap();
+            *ACTIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(root.clone());
+            TestDir {
+                root,
+                work,
+                _serial: serial,
+            }
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.work
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            *ACTIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+fn get_app_settings_path() -> PathBuf {
+    get_app_data_dir().join("settings.json")
+}
+
+pub fn load_app_settings() -> AppSettings {
+    fs::read_to_string(get_app_settings_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_app_settings(settings: &AppSettings) -> AppResult<()> {
+    let path = get_app_settings_path();
+    let content = serde_json::to_string_pretty(settings).map_err(AppError::serialize)?;
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| AppError::io("创建目录失败", e))?;
+        }
+    }
+    atomic_write(&path, &content)
+}
+
+pub fn get_backup_root() -> PathBuf {
+    match normalize_env_value(load_app_settings().backup_root) {
+        Some(root) => expand_path(&root),
+        None => get_app_data_dir().join("backups"),
+    }
+}
+
+pub(crate) fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// 以家目录为基准生成子目录名，同步盘在不同机器上可映射到同一目录
+fn backup_folder_key(config_path: &Path) -> String {
+    let absolute = std::path::absolute(config_path).unwrap_or_else(|_| config_path.to_path_buf());
+    let mut raw = absolute.to_string_lossy().replace('\\', "/");
+    let mut home = get_home_dir().replace('\\', "/");
+    if cfg!(windows) {
+        raw = raw.to_lowercase();
+        home = home.to_lowercase();
+    }
+    let normalized = match raw.strip_prefix(home.trim_end_matches('/')) {
+        Some(rest) if rest.starts_with('/') => format!("home{}", rest),
+        _ => raw,
+    };
+
+    let mut slug = String::new();
+    for ch in normalized.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '.' || ch == '_' {
+            slug.push(ch);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    let slug = if slug.is_empty() { "config" } else { slug };
+    format!("{}-{:08x}", slug, fnv1a64(normalized.as_bytes()) as u32)
+}
+
+fn get_legacy_backup_dir(config_path: &Path) -> PathBuf {
     let base_dir = config_path.parent().unwrap_or_else(|| Path::new("."));
     base_dir.join("quickchange-backups")
 }
 
+pub fn get_backup_dir(config_path: Option<&str>) -> PathBuf {
+    let config_path = resolve_config_path(config_path);
+    get_backup_root().join(backup_folder_key(&config_path))
+}
+
+pub fn get_backup_settings(config_path: Option<&str>) -> BackupSettings {
+    let custom = normalize_env_value(load_app_settings().backup_root).is_some();
+    BackupSettings {
+        root: get_backup_root().to_string_lossy().to_string(),
+        custom,
+        dir: get_backup_dir(config_path).to_string_lossy().to_string(),
+        migrated: 0,
+        conflicts: Vec::new(),
+    }
+}
+
+pub fn set_backup_root(
+    config_path: Option<&str>,
+    root: Option<String>,
+    migrate: bool,
+) -> AppResult<BackupSettings> {
+    let root = normalize_env_value(root);
+    let old_root = get_backup_root();
+    let new_root = match root.as_deref() {
+        Some(value) => expand_path(value),
+        None => get_app_data_dir().join("backups"),
+    };
+    if new_root.exists() && !new_root.is_dir() {
+        return Err(AppError::invalid("备份目录不是文件夹"));
+    }
+    fs::create_dir_all(&new_root).map_err(|e| AppError::io("创建备份目录失败", e))?;
+
+    // 先迁移再保存设置；迁移中途失败时把已移动的文件移回原处，设置保持不变
+    let mut migrated = 0;
+    let mut journal = MoveJournal::default();
+    if migrate && old_root != new_root && old_root.is_dir() {
+        let moved = (|| -> AppResult<usize> {
+            let mut count = 0;
+            let entries =
+                fs::read_dir(&old_root).map_err(|e| AppError::io("读取备份目录失败", e))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| AppError::io("读取备份目录失败", e))?;
+                let path = entry.path();
+                // 新目录位于旧目录内部时跳过自身
+                if !path.is_dir() || path == new_root {
+                    continue;
+                }
+                count += move_backup_files(&path, &new_root.join(entry.file_name()), &mut journal)?;
+            }
+            Ok(count)
+        })();
+        match moved {
+            Ok(count) => migrated = count,
+            Err(e) => {
+                journal.rollback();
+                return Err(e);
+            }
+        }
+    }
+
+    let mut settings = load_app_settings();
+    settings.backup_root = root;
+    if let Err(e) = save_app_settings(&settings) {
+        journal.rollback();
+        return Err(e);
+    }
+    let conflicts = journal.finish();
+    if migrate && old_root != new_root {
+        let _ = fs::remove_dir(&old_root);
+    }
+
+    let mut result = get_backup_settings(config_path);
+    result.migrated = migrated;
+    result.conflicts = conflicts
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    Ok(result)
+}
+
+fn unique_backup_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let source = Path::new(name);
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    let ext = source
+        .extension()
+        .map(|s| format!(".{}", s.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1;
+    loop {
+        let candidate = dir.join(format!("{}-{}{}", stem, index, ext));
+        if !candidate.exists() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+/// 迁移备份时的操作记录：失败时按相反顺序撤销，成功后再清理源目录中的校验清单和空目录
+#[derive(Default)]
+struct MoveJournal {
+    moved: Vec<(PathBuf, PathBuf)>,
+    // 被合并前的目标校验清单内容，None 表示原本不存在
+    manifests: Vec<(PathBuf, Option<String>)>,
+    created: Vec<PathBuf>,
+    leftovers: Vec<PathBuf>,
+    // 目标已存在而未迁移的源路径
+    conflicts: Vec<PathBuf>,
+}
+
+impl MoveJournal {
+    fn rollback(&mut self) {
+        for (from, to) in self.moved.drain(..).rev() {
+            if fs::rename(&to, &from).is_err() && to.is_file() && fs::copy(&to, &from).is_ok() {
+                let _ = fs::remove_file(&to);
+            }
+        }
+        for (path, content) in self.manifests.drain(..).rev() {
+            match content {
+                Some(content) => {
+                    let _ = atomic_write(&path, &content);
+                }
+                None => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        for dir in self.created.drain(..).rev() {
+            let _ = fs::remove_dir(&dir);
+        }
+        self.leftovers.clear();
+        self.conflicts.clear();
+    }
+
+    // 返回保留在原处的冲突项；仍有内容的源目录 remove_dir 会失败，随冲突项一起保留
+    fn finish(&mut self) -> Vec<PathBuf> {
+        self.moved.clear();
+        self.manifests.clear();
+        self.created.clear();
+        for path in self.leftovers.drain(..) {
+            if path.is_dir() {
+                let _ = fs::remove_dir(&path);
+            } else {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        std::mem::take(&mut self.conflicts)
+    }
+}
+
+fn move_path(from: &Path, to: &Path, journal: &mut MoveJournal) -> AppResult<()> {
+    // 跨盘符或跨设备时 rename 会失败，退回到复制后删除
+    if fs::rename(from, to).is_ok() {
+        journal.moved.push((from.to_path_buf(), to.to_path_buf()));
+        return Ok(());
+    }
+    if from.is_dir() {
+        // 整体复制目录内容，不按备份规则合并或改名
+        fs::create_dir(to).map_err(|e| AppError::io("创建目录失败", e).with_path(to))?;
+        journal.created.push(to.to_path_buf());
+        let entries = fs::read_dir(from).map_err(|e| AppError::io("读取目录失败", e).with_path(from))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| AppError::io("读取目录失败", e).with_path(from))?;
+            move_path(&entry.path(), &to.join(entry.file_name()), journal)?;
+        }
+        journal.leftovers.push(from.to_path_buf());
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| AppError::io("迁移备份失败", e).with_path(from))?;
+    journal.moved.push((from.to_path_buf(), to.to_path_buf()));
+    fs::remove_file(from).map_err(|e| AppError::io("删除文件失败", e).with_path(from))
+}
+
+fn is_backup_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == "toml")
+}
+
+fn count_backup_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.flatten().filter(|entry| is_backup_file(&entry.path())).count())
+        .unwrap_or(0)
+}
+
+/// 把一个配置的备份目录合并到目标目录，返回迁移的备份文件数（含隔离区）。
+/// 只有备份文件和隔离区逐个合并，重名时改名；history 仓库、undo.json 等其他内容整体迁移，
+/// 目标已存在时保留目标副本，源留在原处并记为冲突，避免把 git 仓库拆散
+fn move_backup_files(from: &Path, to: &Path, journal: &mut MoveJournal) -> AppResult<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+    if !to.exists() {
+        fs::create_dir_all(to).map_err(|e| AppError::io("创建备份目录失败", e))?;
+        journal.created.push(to.to_path_buf());
+    } else if !to.is_dir() {
+        return Err(AppError::invalid("备份目录不是文件夹").with_path(to));
+    }
+
+    let mut count = 0;
+    let mut renamed = HashMap::new();
+    let entries = fs::read_dir(from).map_err(|e| AppError::io("读取备份目录失败", e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::io("读取备份目录失败", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == BACKUP_MANIFEST {
+            continue;
+        }
+        if is_backup_file(&path) {
+            let dest = unique_backup_path(to, &name);
+            move_path(&path, &dest, journal)?;
+            if let Some(dest_name) = dest.file_name() {
+                renamed.insert(name, dest_name.to_string_lossy().to_string());
+            }
+            count += 1;
+            continue;
+        }
+        let dest = to.join(&name);
+        if !dest.exists() {
+            let backups = if name == BACKUP_QUARANTINE_DIR { count_backup_files(&path) } else { 0 };
+            move_path(&path, &dest, journal)?;
+            count += backups;
+        } else if name == BACKUP_QUARANTINE_DIR && path.is_dir() && dest.is_dir() {
+            count += move_backup_files(&path, &dest, journal)?;
+        } else {
+            journal.conflicts.push(path);
+        }
+    }
+
+    // 合并校验清单，重名文件按新名字记录；源清单留到迁移全部成功后再删除
+    let source_manifest = from.join(BACKUP_MANIFEST);
+    if source_manifest.is_file() {
+        let dest_manifest = to.join(BACKUP_MANIFEST);
+        let _lock = lock_backup_manifest(to)?;
+        journal
+            .manifests
+            .push((dest_manifest.clone(), fs::read_to_string(&dest_manifest).ok()));
+        let mut manifest = load_backup_manifest(to);
+        for (name, checksum) in load_backup_manifest(from) {
+            let name = renamed.get(&name).cloned().unwrap_or(name);
+            manifest.insert(name, checksum);
+        }
+        save_backup_manifest(to, &manifest)?;
+        journal.leftovers.push(source_manifest);
+    }
+    journal.leftovers.push(from.to_path_buf());
+    Ok(count)
+}
+
+// 校验清单的读改写都要持有锁：监视线程、GUI 和命令行可能同时创建备份
+fn lock_backup_manifest(dir: &Path) -> AppResult<ConfigLock> {
+    lock_config(&dir.join(BACKUP_MANIFEST))
+}
+
+fn update_backup_manifest<F>(dir: &Path, update: F) -> AppResult<()>
+where
+    F: FnOnce(&mut BTreeMap<String, BackupChecksum>) -> bool,
+{
+    let _lock = lock_backup_manifest(dir)?;
+    let mut manifest = load_backup_manifest(dir);
+    if update(&mut manifest) {
+        save_backup_manifest(dir, &manifest)?;
+    }
+    Ok(())
+}
+
+fn load_backup_manifest(dir: &Path) -> BTreeMap<String, BackupChecksum> {
+    fs::read_to_string(dir.join(BACKUP_MANIFEST))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_backup_manifest(dir: &Path, manifest: &BTreeMap<String, BackupChecksum>) -> AppResult<()> {
+    let path = dir.join(BACKUP_MANIFEST);
+    if manifest.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| AppError::io("更新备份校验清单失败", e))?;
+        }
+        return Ok(());
+    }
+    let content = serde_json::to_string_pretty(manifest).map_err(AppError::serialize)?;
+    atomic_write(&path, &content)
+}
+
+fn checksum_bytes(bytes: &[u8]) -> BackupChecksum {
+    BackupChecksum {
+        hash: format!("{:016x}", fnv1a64(bytes)),
+        size: bytes.len() as u64,
+    }
+}
+
+fn record_backup_checksum(dir: &Path, name: &str) -> AppResult<()> {
+    let bytes = fs::read(dir.join(name)).map_err(|e| AppError::io("读取备份文件失败", e))?;
+    update_backup_manifest(dir, |manifest| {
+        manifest.insert(name.to_string(), checksum_bytes(&bytes));
+        true
+    })
+}
+
+fn check_backup_file(path: &Path, expected: Option<&BackupChecksum>) -> Option<(String, String)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => return Some(("unreadable".to_string(), format!("读取备份文件失败: {}", e))),
+    };
+    if let Some(expected) = expected {
+        let actual = checksum_bytes(&bytes);
+        if actual.size < expected.size {
+            return Some((
+                "truncated".to_string(),
+                format!("文件大小 {} 字节，记录为 {} 字节", actual.size, expected.size),
+            ));
+        }
+        if actual.hash != expected.hash {
+            return Some(("hash_mismatch".to_string(), "校验值与备份时记录的不一致".to_string()));
+        }
+    }
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(_) => return Some(("parse_error".to_string(), "备份文件不是有效的 UTF-8 文本".to_string())),
+    };
+    if let Err(e) = toml::from_str::<CargoConfig>(&content) {
+        return Some(("parse_error".to_string(), format!("备份文件解析失败: {}", e)));
+    }
+    None
+}
+
+/// 校验备份目录下所有备份；`repair` 时隔离损坏文件并补全缺失的校验记录
+pub fn verify_backups(config_path: Option<&str>, repair: bool) -> AppResult<BackupVerifyReport> {
+    let dir = prepare_backup_dir(config_path)?;
+    let mut report = BackupVerifyReport::default();
+    if !dir.exists() {
+        return Ok(report);
+    }
+
+    // 修复时整个过程持有清单锁，避免把并发新建的备份当作过期记录删除
+    let _lock = if repair { Some(lock_backup_manifest(&dir)?) } else { None };
+    let mut manifest = load_backup_manifest(&dir);
+    let mut manifest_changed = false;
+    let quarantine_dir = dir.join(BACKUP_QUARANTINE_DIR);
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("读取备份目录失败", e))?;
+    let mut seen = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::io("读取备份目录失败", e))?;
+        let path = entry.path();
+        if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        seen.push(name.clone());
+        report.checked += 1;
+
+        let expected = manifest.get(&name);
+        match check_backup_file(&path, expected) {
+            None => {
+                if expected.is_none() {
+                    report.unverified += 1;
+                    if repair {
+                        let bytes =
+                            fs::read(&path).map_err(|e| AppError::io("读取备份文件失败", e))?;
+                        manifest.insert(name.clone(), checksum_bytes(&bytes));
+                        manifest_changed = true;
+                    }
+                }
+                report.ok += 1;
+            }
+            Some((kind, message)) => {
+                report.issues.push(BackupIssue {
+                    name: name.clone(),
+                    kind,
+                    message,
+                });
+                if repair {
+                    fs::create_dir_all(&quarantine_dir)
+                        .map_err(|e| AppError::io("创建隔离目录失败", e))?;
+                    let dest = unique_backup_path(&quarantine_dir, &name);
+                    move_path(&path, &dest, &mut MoveJournal::default())?;
+                    manifest.remove(&name);
+                    manifest_changed = true;
+                    report.quarantined.push(name);
+                }
+            }
+        }
+    }
+
+    if repair {
+        let stale: Vec<String> = manifest
+            .keys()
+            .filter(|name| !seen.contains(name))
+            .cloned()
+            .collect();
+        for name in stale {
+            manifest.remove(&name);
+            manifest_changed = true;
+        }
+        if manifest_changed {
+            save_backup_manifest(&dir, &manifest)?;
+        }
+    }
+
+    report.issues.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(report)
+}
+
+// 旧版本把备份放在配置文件同级的 quickchange-backups 目录，首次访问时迁移过来
+fn prepare_backup_dir(config_path: Option<&str>) -> AppResult<PathBuf> {
+    let dir = get_backup_dir(config_path);
+    let legacy = get_legacy_backup_dir(&resolve_config_path(config_path));
+    if legacy.is_dir() && legacy != dir {
+        let mut journal = MoveJournal::default();
+        if let Err(e) = move_backup_files(&legacy, &dir, &mut journal) {
+            journal.rollback();
+            return Err(e);
+        }
+        journal.finish();
+    }
+    Ok(dir)
+}
+
 fn expand_tilde(input: &str) -> String {
     let trimmed = input.trim();
     if trimmed == "~" {
@@ -321,14 +961,20 @@ pub fn resolve_config_path(path_override: Option<&str>) -> PathBuf {
     }
 }
 
-fn ensure_backup_dir(config_path: Option<&str>) -> Result<PathBuf, String> {
-    let dir = get_backup_dir(config_path);
+fn ensure_backup_dir(config_path: Option<&str>) -> AppResult<PathBuf> {
+    let dir = prepare_backup_dir(config_path)?;
     if !dir.exists() {
-        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup dir: {}", e))?;
+        fs::create_dir_all(&dir).map_err(|e| AppError::io("创建备份目录失败", e))?;
     }
     Ok(dir)
 }
 
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+const MAX_BACKUP_LABEL_LEN: usize = 80;
+
 fn sanitize_backup_label(label: &str) -> String {
     let mut out = String::new();
     let invalid_chars = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
@@ -346,7 +992,13 @@ fn sanitize_backup_label(label: &str) -> String {
         }
         out.push(ch);
     }
-    let cleaned = out.trim_matches('-').to_string();
+    let truncated: String = out.chars().take(MAX_BACKUP_LABEL_LEN).collect();
+    // Windows 不允许以点结尾，也不允许 CON、NUL 等设备名
+    let mut cleaned = truncated.trim_matches(|ch| ch == '-' || ch == '.').to_string();
+    let stem = cleaned.split('.').next().unwrap_or_default().to_ascii_lowercase();
+    if WINDOWS_RESERVED_NAMES.contains(&stem.as_str()) {
+        cleaned.insert(0, '_');
+    }
     if cleaned.is_empty() {
         "custom".to_string()
     } else {
@@ -363,7 +1015,57 @@ fn build_backup_name(label: Option<&str>) -> String {
         Some(value) => format!("manual-{}", sanitize_backup_label(value)),
         None => "auto".to_string(),
     };
-    format!("{}-{}.toml", prefix, stamp)
+    format!("{}-{}", prefix, stamp)
+}
+
+// 用 create_new 抢占文件名，同一毫秒内的多次备份会依次追加序号而不是互相覆盖
+fn create_unique_backup_file(dir: &Path, base: &str) -> AppResult<(String, fs::File)> {
+    for index in 0..1000 {
+        let name = if index == 0 {
+            format!("{}.toml", base)
+        } else {
+            format!("{}-{}.toml", base, index)
+        };
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(&name))
+        {
+            Ok(file) => return Ok((name, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(AppError::io("创建备份文件失败", e)),
+        }
+    }
+    Err(AppError::new(ErrorCode::AlreadyExists, "无法生成唯一的备份文件名"))
+}
+
+/// 把用户传入的备份名解析为备份目录下的文件，拒绝路径分隔符、`..` 和指向目录外的链接
+fn resolve_backup_file(backup_dir: &Path, name: &str) -> AppResult<PathBuf> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+        || name.chars().any(|ch| ch.is_control())
+        || Path::new(name).is_absolute()
+        || Path::new(name).extension().and_then(|ext| ext.to_str()) != Some("toml");
+    if invalid {
+        return Err(AppError::invalid("无效的备份名称"));
+    }
+
+    let path = backup_dir.join(name);
+    if !path.exists() {
+        return Err(AppError::not_found("备份文件不存在"));
+    }
+    let canonical_dir = backup_dir
+        .canonicalize()
+        .map_err(|e| AppError::io("解析备份目录失败", e))?;
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| AppError::io("解析备份路径失败", e))?;
+    if canonical.parent() != Some(canonical_dir.as_path()) || !canonical.is_file() {
+        return Err(AppError::invalid("备份文件不在备份目录内"));
+    }
+    Ok(path)
 }
 
 fn modified_secs(metadata: &fs::Metadata) -> u64 {
@@ -375,84 +1077,516 @@ fn modified_secs(metadata: &fs::Metadata) -> u64 {
         .unwrap_or(0)
 }
 
-fn atomic_write(path: &Path, content: &str) -> Result<(), String> {
+/// 配置文件的跨进程建议锁，析构时释放；锁文件放在应用数据目录的 `locks` 下，
+/// 按配置路径的哈希命名，不在项目的 `.cargo/` 中留下文件
+pub(crate) struct ConfigLock {
+    _file: fs::File,
+}
+
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn lock_file_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut key = absolute.to_string_lossy().replace('\\', "/");
+    if cfg!(windows) {
+        key = key.to_lowercase();
+    }
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config".to_string());
+    get_app_data_dir()
+        .join("locks")
+        .join(format!("{}-{:016x}.lock", file_name, fnv1a64(key.as_bytes())))
+}
+
+pub(crate) fn lock_config(path: &Path) -> AppResult<ConfigLock> {
+    let lock_path = lock_file_path(path);
+    if let Some(dir) = lock_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("创建目录失败", e))?;
+    }
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| AppError::io("打开锁文件失败", e))?;
+
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => {
+                // 清理旧版本放在配置文件旁的锁文件
+                if let (Some(dir), Some(name)) = (path.parent(), path.file_name()) {
+                    let _ = fs::remove_file(dir.join(format!(".{}.lock", name.to_string_lossy())));
+                }
+                return Ok(ConfigLock { _file: file });
+            }
+            Err(fs::TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Err(fs::TryLockError::WouldBlock) => {
+                return Err(AppError::new(ErrorCode::Locked, "配置文件正被其他进程写入，请稍后重试"));
+            }
+            Err(fs::TryLockError::Error(e)) => {
+                return Err(AppError::io("锁定配置文件失败", e));
+            }
+        }
+    }
+}
+
+fn create_temp_file(dir: &Path, path: &Path) -> AppResult<(PathBuf, fs::File)> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "config".to_string());
+    loop {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let tmp_path = dir.join(format!(
+            ".{}.tmp-{}-{}-{}",
+            file_name,
+            std::process::id(),
+            stamp,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&tmp_path) {
+            Ok(file) => return Ok((tmp_path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(AppError::io("写入临时文件失败", e)),
+        }
+    }
+}
+
+// 沿用原文件的权限，以 root 身份运行时还要把属主改回原用户
+fn copy_file_metadata(original: &Path, tmp_path: &Path) {
+    let Ok(metadata) = fs::metadata(original) else {
+        return;
+    };
+    let _ = fs::set_permissions(tmp_path, metadata.permissions());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = std::os::unix::fs::chown(tmp_path, Some(metadata.uid()), Some(metadata.gid()));
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(handle) = fs::File::open(dir) {
+        let _ = handle.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+pub(crate) fn atomic_write(path: &Path, content: &str) -> AppResult<()> {
     let dir = path.parent().unwrap_or_else(|| Path::new("."));
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis())
+    let (tmp_path, mut file) = create_temp_file(dir, path)?;
+    let written = file.write_all(content.as_bytes()).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(AppError::io("写入临时文件失败", e));
+    }
+    copy_file_metadata(path, &tmp_path);
+
+    // Windows 上目标文件被杀毒软件或编辑器短暂占用时 rename 会失败，重试而不是先删除原文件
+    let mut attempts = 0;
+    loop {
+        match fs::rename(&tmp_path, path) {
+            Ok(_) => break,
+            Err(_) if attempts < 5 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(50 * attempts));
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(AppError::io("替换配置文件失败", e));
+            }
+        }
+    }
+    sync_dir(dir);
+    Ok(())
+}
+
+fn ensure_config_file(path: &Path) -> AppResult<()> {
+    if path.exists() {
+        if path.is_file() {
+            return Ok(());
+        }
+        return Err(AppError::invalid("配置路径不是文件").with_path(path));
+    }
+
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).map_err(|e| AppError::io("创建目录失败", e))?;
+        }
+    }
+
+    fs::write(path, "").map_err(|e| AppError::io("创建配置文件失败", e))
+}
+
+fn build_revision(path: &Path, content: &[u8]) -> String {
+    let mtime = fs::metadata(path)
+        .ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
         .unwrap_or(0);
-    let tmp_path = dir.join(format!(".config.tmp-{}", stamp));
-    fs::write(&tmp_path, content).map_err(|e| format!("Failed to write temp file: {}", e))?;
-    match fs::rename(&tmp_path, path) {
-        Ok(_) => Ok(()),
-        Err(err) => {
-            if path.exists() {
-                let _ = fs::remove_file(path);
+    format!("{}-{:016x}", mtime, fnv1a64(content))
+}
+
+/// 配置文件当前的修订号，格式为 `<mtime 纳秒>-<内容哈希>`
+pub fn get_config_revision(path: &Path) -> String {
+    let content = fs::read(path).unwrap_or_default();
+    build_revision(path, &content)
+}
+
+// 只比较内容哈希，单纯 touch 导致的 mtime 变化不算冲突
+fn revision_matches(expected: &str, current: &str) -> bool {
+    let hash = |revision: &str| revision.rsplit('-').next().unwrap_or_default().to_string();
+    expected == current || hash(expected) == hash(current)
+}
+
+pub fn load_config_with_revision(path_override: Option<&str>) -> AppResult<LoadedConfig> {
+    let path = resolve_config_path(path_override);
+    if !path.exists() {
+        ensure_config_file(&path)?;
+    }
+    let content = fs::read_to_string(&path).map_err(|e| AppError::io("读取配置文件失败", e))?;
+    let config = if content.trim().is_empty() {
+        CargoConfig::default()
+    } else {
+        toml::from_str(&content)
+            .map_err(|e| AppError::parse("配置文件解析失败", &content, &e).with_path(&path))?
+    };
+    Ok(LoadedConfig {
+        config,
+        revision: build_revision(&path, content.as_bytes()),
+        path: path.to_string_lossy().to_string(),
+        skipped: Vec::new(),
+    })
+}
+
+// 按顶层表头把文件切成若干段，返回 (表头, 起始行号, 原文)；表头之前的根键归为空表头
+fn split_toml_sections(content: &str) -> Vec<(String, usize, String)> {
+    let mut sections = vec![(String::new(), 1, String::new())];
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let header = line.split('#').next().unwrap_or_default().trim();
+        // 表头都写在行首，缩进的 `[` 多半是数组元素
+        if line.starts_with('[') && header.ends_with(']') {
+            sections.push((header.to_string(), index + 1, String::new()));
+        }
+        if let Some((_, _, text)) = sections.last_mut() {
+            text.push_str(line);
+        }
+    }
+    sections
+}
+
+/// 尽量解析配置：逐段尝试，跳过无法解析或类型不符的表，其余内容照常加载
+pub fn recover_config(content: &str) -> (CargoConfig, Vec<SkippedSection>) {
+    let mut accepted = String::new();
+    let mut skipped = Vec::new();
+    for (header, line, text) in split_toml_sections(content) {
+        if text.is_empty() {
+            continue;
+        }
+        let candidate = format!("{}{}", accepted, text);
+        match toml::from_str::<CargoConfig>(&candidate) {
+            Ok(_) => accepted = candidate,
+            Err(e) => {
+                // 跳过的段落用等量空行占位，保证后续错误的行号与原文件一致
+                let mut error = AppError::parse("配置片段解析失败", &candidate, &e);
+                if let Some(error_line) = error.line() {
+                    let column = error.location.as_ref().map(|l| l.column as usize).unwrap_or(1);
+                    error = error.with_location(content, error_line, column);
+                }
+                accepted.push_str(&"\n".repeat(text.matches('\n').count()));
+                skipped.push(SkippedSection {
+                    header,
+                    line,
+                    content: text,
+                    error,
+                });
             }
-            fs::rename(&tmp_path, path)
-                .map_err(|e| format!("Failed to replace config: {} (original: {})", e, err))
         }
     }
+    let config = toml::from_str(&accepted).unwrap_or_default();
+    (config, skipped)
 }
 
-fn ensure_config_file(path: &Path) -> Result<(), String> {
-    if path.exists() {
-        if path.is_file() {
-            return Ok(());
-        }
-        return Err("配置路径不是文件".to_string());
+/// 与 `load_config_with_revision` 相同，但解析失败时改用 `recover_config` 加载可解析的部分
+pub fn load_config_recovering(path_override: Option<&str>) -> AppResult<LoadedConfig> {
+    let path = resolve_config_path(path_override);
+    if !path.exists() {
+        ensure_config_file(&path)?;
     }
+    let content = fs::read_to_string(&path).map_err(|e| AppError::io("读取配置文件失败", e))?;
+    let (config, skipped) = match toml::from_str::<CargoConfig>(&content) {
+        Ok(config) => (config, Vec::new()),
+        Err(_) => recover_config(&content),
+    };
+    Ok(LoadedConfig {
+        config,
+        revision: build_revision(&path, content.as_bytes()),
+        path: path.to_string_lossy().to_string(),
+        skipped,
+    })
+}
+
+pub fn load_config(path_override: Option<&str>) -> AppResult<CargoConfig> {
+    load_config_with_revision(path_override).map(|loaded| loaded.config)
+}
 
+fn write_config_unlocked(path: &Path, content: &str) -> AppResult<()> {
     if let Some(parent) = path.parent() {
         if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+            fs::create_dir_all(parent).map_err(|e| AppError::io("创建目录失败", e))?;
         }
     }
+    atomic_write(path, content)
+}
 
-    fs::write(path, "").map_err(|e| format!("Failed to create config file: {}", e))
+pub(crate) fn write_config_file(path: &Path, content: &str) -> AppResult<()> {
+    let _lock = lock_config(path)?;
+    write_config_unlocked(path, content)
 }
 
-pub fn load_config(path_override: Option<&str>) -> Result<CargoConfig, String> {
+pub fn save_config(config: &CargoConfig, path_override: Option<&str>) -> AppResult<()> {
     let path = resolve_config_path(path_override);
-    if !path.exists() {
-        ensure_config_file(&path)?;
-        return Ok(CargoConfig::default());
+    let content = toml::to_string_pretty(config).map_err(AppError::serialize)?;
+    write_config_file(&path, &content)
+}
+
+/// 带并发校验的保存：`expected_revision` 与磁盘上的修订号不一致时拒绝写入，返回新的修订号
+pub fn save_config_checked(
+    config: &CargoConfig,
+    path_override: Option<&str>,
+    expected_revision: Option<&str>,
+) -> AppResult<String> {
+    let path = resolve_config_path(path_override);
+    let content = toml::to_string_pretty(config).map_err(AppError::serialize)?;
+    // 校验修订号与写入必须在同一把锁内完成
+    let _lock = lock_config(&path)?;
+    if let Some(expected) = expected_revision {
+        let current = get_config_revision(&path);
+        if !revision_matches(expected, &current) {
+            return Err(AppError::new(
+                ErrorCode::Conflict,
+                "配置文件已在外部被修改，请重新加载或合并后再保存",
+            )
+            .with_path(&path));
+        }
     }
-    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read config: {}", e))?;
+    write_config_unlocked(&path, &content)?;
+    Ok(get_config_revision(&path))
+}
+
+pub fn serialize_config(config: &CargoConfig) -> AppResult<String> {
+    toml::to_string_pretty(config).map_err(AppError::serialize)
+}
+
+pub fn parse_config_value(content: &str) -> AppResult<toml::Value> {
     if content.trim().is_empty() {
-        return Ok(CargoConfig::default());
+        return Ok(toml::Value::Table(toml::Table::new()));
     }
-    toml::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))
+    content
+        .parse::<toml::Table>()
+        .map(toml::Value::Table)
+        .map_err(|e| AppError::parse("配置文件解析失败", content, &e))
 }
 
-pub fn save_config(config: &CargoConfig, path_override: Option<&str>) -> Result<(), String> {
-    let path = resolve_config_path(path_override);
-    let content =
-        toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+pub fn config_to_value(config: &CargoConfig) -> AppResult<toml::Value> {
+    toml::Value::try_from(config).map_err(AppError::serialize)
+}
 
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+fn quote_key_segment(segment: &str) -> String {
+    let bare = !segment.is_empty()
+        && segment
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+    if bare {
+        segment.to_string()
+    } else {
+        format!("\"{}\"", segment.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+pub fn join_key_path(prefix: &str, segment: &str) -> String {
+    if prefix.is_empty() {
+        quote_key_segment(segment)
+    } else {
+        format!("{}.{}", prefix, quote_key_segment(segment))
+    }
+}
+
+/// `join_key_path` 的逆操作，支持带引号的键段
+pub fn split_key_path(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = key.chars();
+    let mut quoted = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
         }
     }
+    segments.push(current);
+    segments
+}
 
-    atomic_write(&path, &content)
+/// 按键路径写入值，中间表不存在时自动创建；路径上遇到非表的值时返回 `false`
+pub fn insert_key_path(table: &mut toml::Table, key: &str, value: toml::Value) -> bool {
+    let segments = split_key_path(key);
+    let Some((last, parents)) = segments.split_last() else {
+        return false;
+    };
+    let mut current = table;
+    for segment in parents {
+        let entry = current
+            .entry(segment.clone())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        match entry {
+            toml::Value::Table(next) => current = next,
+            _ => return false,
+        }
+    }
+    if let Some(toml::Value::Table(existing)) = current.get(last) {
+        match &value {
+            toml::Value::Table(incoming) if incoming.is_empty() => return true,
+            _ if !existing.is_empty() => return false,
+            _ => {}
+        }
+    }
+    current.insert(last.clone(), value);
+    true
+}
+
+/// 删除键路径对应的值，并清理因此变空的父表；键不存在时返回 false
+pub fn remove_key_path(table: &mut toml::Table, key: &str) -> bool {
+    fn remove_in(table: &mut toml::Table, segments: &[String]) -> bool {
+        let Some((first, rest)) = segments.split_first() else {
+            return false;
+        };
+        if rest.is_empty() {
+            return table.remove(first).is_some();
+        }
+        let Some(toml::Value::Table(child)) = table.get_mut(first) else {
+            return false;
+        };
+        let removed = remove_in(child, rest);
+        if removed && child.is_empty() {
+            table.remove(first);
+        }
+        removed
+    }
+    remove_in(table, &split_key_path(key))
 }
 
-pub fn serialize_config(config: &CargoConfig) -> Result<String, String> {
-    toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))
+fn flatten_value_into(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) if !table.is_empty() => {
+            for (key, child) in table {
+                flatten_value_into(&join_key_path(prefix, key), child, out);
+            }
+        }
+        _ => {
+            if !prefix.is_empty() {
+                out.insert(prefix.to_string(), value.clone());
+            }
+        }
+    }
 }
 
-pub fn list_backups(config_path: Option<&str>) -> Result<Vec<BackupEntry>, String> {
-    let dir = get_backup_dir(config_path);
+/// 将配置展开为 `build.jobs` 形式的键路径，数组和空表视为叶子
+pub fn flatten_config_value(value: &toml::Value) -> BTreeMap<String, toml::Value> {
+    let mut out = BTreeMap::new();
+    flatten_value_into("", value, &mut out);
+    out
+}
+
+pub fn diff_config_values(old: &toml::Value, new: &toml::Value) -> Vec<KeyChange> {
+    let old_flat = flatten_config_value(old);
+    let new_flat = flatten_config_value(new);
+    let mut changes = Vec::new();
+    for (key, old_value) in &old_flat {
+        match new_flat.get(key) {
+            None => changes.push(KeyChange {
+                key: key.clone(),
+                kind: "removed".to_string(),
+                old: Some(old_value.clone()),
+                new: None,
+            }),
+            Some(new_value) if new_value != old_value => changes.push(KeyChange {
+                key: key.clone(),
+                kind: "changed".to_string(),
+                old: Some(old_value.clone()),
+                new: Some(new_value.clone()),
+            }),
+            _ => {}
+        }
+    }
+    for (key, new_value) in &new_flat {
+        if !old_flat.contains_key(key) {
+            changes.push(KeyChange {
+                key: key.clone(),
+                kind: "added".to_string(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes
+}
+
+/// 把 `overlay` 中出现的键逐个覆盖到 `base` 上，`unset` 中的键先行删除；其余键保持不变
+pub fn overlay_config(
+    base: &CargoConfig,
+    overlay: &CargoConfig,
+    unset: &[String],
+) -> AppResult<CargoConfig> {
+    let mut table = match config_to_value(base)? {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+    for key in unset {
+        remove_key_path(&mut table, key);
+    }
+    for (key, value) in flatten_config_value(&config_to_value(overlay)?) {
+        if !insert_key_path(&mut table, &key, value) {
+            return Err(AppError::new(ErrorCode::Conflict, "配置结构不兼容，无法覆盖该键").with_key(key));
+        }
+    }
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| AppError::invalid("覆盖后的配置无效").with_detail(e))
+}
+
+pub fn list_backups(config_path: Option<&str>) -> AppResult<Vec<BackupEntry>> {
+    let dir = prepare_backup_dir(config_path)?;
     if !dir.exists() {
         return Ok(Vec::new());
     }
 
     let mut items = Vec::new();
-    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read backup dir: {}", e))?;
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("读取备份目录失败", e))?;
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read backup entry: {}", e))?;
+        let entry = entry.map_err(|e| AppError::io("读取备份目录失败", e))?;
         let path = entry.path();
         if !path.is_file() {
             continue;
@@ -462,9 +1596,10 @@ pub fn list_backups(config_path: Option<&str>) -> Result<Vec<BackupEntry>, Strin
         }
         let metadata = entry
             .metadata()
-            .map_err(|e| format!("Failed to read backup metadata: {}", e))?;
+            .map_err(|e| AppError::io("读取备份信息失败", e))?;
         let name = entry.file_name().to_string_lossy().to_string();
         items.push(BackupEntry {
+            kind: backup_kind(&name),
             name,
             path: path.to_string_lossy().to_string(),
             modified: modified_secs(&metadata),
@@ -476,22 +1611,34 @@ pub fn list_backups(config_path: Option<&str>) -> Result<Vec<BackupEntry>, Strin
     Ok(items)
 }
 
-pub fn create_backup(
-    config_path: Option<&str>,
-    label: Option<String>,
-) -> Result<BackupEntry, String> {
-    let config_path = resolve_config_path(config_path);
+fn backup_kind(name: &str) -> String {
+    let kind = name.split('-').next().unwrap_or_default();
+    match kind {
+        "auto" | "manual" | "external" | "scheduled" | "profile" | "preset" => kind.to_string(),
+        _ => "custom".to_string(),
+    }
+}
+
+fn write_backup(config_path: &Path, base_name: &str) -> AppResult<BackupEntry> {
     if !config_path.exists() {
-        return Err("配置文件不存在，无法备份".to_string());
+        return Err(AppError::not_found("配置文件不存在，无法备份"));
     }
 
     let dir = ensure_backup_dir(config_path.to_str())?;
-    let name = build_backup_name(label.as_deref());
+    let content = fs::read(config_path).map_err(|e| AppError::io("读取配置文件失败", e))?;
+    let (name, mut file) = create_unique_backup_file(&dir, base_name)?;
     let dest_path = dir.join(&name);
-    fs::copy(&config_path, &dest_path).map_err(|e| format!("Failed to copy backup: {}", e))?;
-    let metadata = fs::metadata(&dest_path).map_err(|e| format!("Failed to read backup: {}", e))?;
+    let written = file.write_all(&content).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&dest_path);
+        return Err(AppError::io("写入备份文件失败", e));
+    }
+    record_backup_checksum(&dir, &name)?;
+    let metadata = fs::metadata(&dest_path).map_err(|e| AppError::io("读取备份文件失败", e))?;
 
     Ok(BackupEntry {
+        kind: backup_kind(&name),
         name,
         path: dest_path.to_string_lossy().to_string(),
         modified: modified_secs(&metadata),
@@ -499,46 +1646,421 @@ pub fn create_backup(
     })
 }
 
-pub fn restore_backup(config_path: Option<&str>, name: String) -> Result<(), String> {
-    let backup_dir = get_backup_dir(config_path);
-    let file_name = Path::new(&name)
-        .file_name()
-        .ok_or_else(|| "Invalid backup name".to_string())?;
-    let backup_path = backup_dir.join(file_name);
-    if !backup_path.exists() {
-        return Err("备份文件不存在".to_string());
+pub fn create_backup(
+    config_path: Option<&str>,
+    label: Option<String>,
+) -> AppResult<BackupEntry> {
+    let config_path = resolve_config_path(config_path);
+    write_backup(&config_path, &build_backup_name(label.as_deref()))
+}
+
+/// 由监视器创建的快照，`kind` 为 `external` 或 `scheduled`，体现在文件名前缀上
+pub fn create_tagged_backup(config_path: &Path, kind: &str) -> AppResult<BackupEntry> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    write_backup(config_path, &format!("{}-{}", kind, stamp))
+}
+
+pub fn restore_backup(config_path: Option<&str>, name: String) -> AppResult<()> {
+    let backup_dir = prepare_backup_dir(config_path)?;
+    let backup_path = resolve_backup_file(&backup_dir, &name)?;
+
+    let manifest = load_backup_manifest(&backup_dir);
+    if let Some(expected) = manifest.get(&name) {
+        if let Some((_, message)) = check_backup_file(&backup_path, Some(expected)) {
+            return Err(AppError::new(ErrorCode::Parse, "备份文件已损坏")
+                .with_detail(message)
+                .with_path(&backup_path));
+        }
     }
 
     let content =
-        fs::read_to_string(&backup_path).map_err(|e| format!("Failed to read backup: {}", e))?;
-    toml::from_str::<CargoConfig>(&content).map_err(|e| format!("备份文件解析失败: {}", e))?;
+        fs::read_to_string(&backup_path).map_err(|e| AppError::io("读取备份文件失败", e))?;
+    toml::from_str::<CargoConfig>(&content)
+        .map_err(|e| AppError::parse("备份文件解析失败", &content, &e).with_path(&backup_path))?;
 
     let config_path = resolve_config_path(config_path);
-    if let Some(parent) = config_path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    write_config_file(&config_path, &content)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    Toml,
+    Json,
+    Yaml,
+    Dotenv,
+    Env,
+}
+
+impl ImportFormat {
+    /// 未指定格式时按文件名推断，默认 TOML
+    pub fn from_path(path: &Path) -> ImportFormat {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "json" => ImportFormat::Json,
+            "yml" | "yaml" => ImportFormat::Yaml,
+            "env" => ImportFormat::Dotenv,
+            _ if name.starts_with(".env") => ImportFormat::Dotenv,
+            _ => ImportFormat::Toml,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportResult {
+    pub config: CargoConfig,
+    pub format: ImportFormat,
+    pub skipped: Vec<SkippedKey>,
+}
+
+fn unescape_dotenv(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// 解析 dotenv：支持 `export` 前缀、单双引号和行尾注释，不做变量展开
+pub fn parse_dotenv(content: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = if let Some(rest) = value.strip_prefix('\'') {
+            rest.split('\'').next().unwrap_or_default().to_string()
+        } else if let Some(rest) = value.strip_prefix('"') {
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (index, ch) in rest.char_indices() {
+                match ch {
+                    '\\' if !escaped => escaped = true,
+                    '"' if !escaped => {
+                        end = index;
+                        break;
+                    }
+                    _ => escaped = false,
+                }
+            }
+            unescape_dotenv(&rest[..end])
+        } else {
+            value.split(" #").next().unwrap_or_default().trim().to_string()
+        };
+        vars.push((name.trim().to_string(), value));
     }
-    atomic_write(&config_path, &content)
+    vars
+}
+
+fn parse_yaml_config(content: &str) -> AppResult<CargoConfig> {
+    serde_yaml_ng::from_str(content).map_err(|e| {
+        let error = AppError::new(ErrorCode::Parse, "YAML 解析失败").with_detail(&e);
+        match e.location() {
+            Some(location) => error.with_location(content, location.line(), location.column()),
+            None => error,
+        }
+    })
 }
 
-pub fn import_config_from_path(path: &str) -> Result<CargoConfig, String> {
+/// 从文件导入配置，支持 TOML、JSON、YAML 和 dotenv（按 CARGO_* 变量转换）
+pub fn import_config_from_path(
+    path: &str,
+    format: Option<ImportFormat>,
+) -> AppResult<ImportResult> {
     let resolved = expand_path(path);
+    let format = format.unwrap_or_else(|| ImportFormat::from_path(&resolved));
+    if format == ImportFormat::Env {
+        return Ok(import_config_from_env());
+    }
     let content =
-        fs::read_to_string(&resolved).map_err(|e| format!("Failed to read file: {}", e))?;
-    toml::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))
+        fs::read_to_string(&resolved).map_err(|e| AppError::io("读取文件失败", e))?;
+    let mut skipped = Vec::new();
+    let config = match format {
+        ImportFormat::Json => serde_json::from_str(&content)
+            .map_err(|e| AppError::json("JSON 解析失败", &content, e).with_path(&resolved))?,
+        ImportFormat::Yaml => parse_yaml_config(&content).map_err(|e| e.with_path(&resolved))?,
+        ImportFormat::Dotenv => {
+            let (config, ignored) =
+                crate::explain::config_from_env_vars(&parse_dotenv(&content), true);
+            skipped = ignored;
+            config
+        }
+        ImportFormat::Toml | ImportFormat::Env => toml::from_str(&content)
+            .map_err(|e| AppError::parse("配置文件解析失败", &content, &e).with_path(&resolved))?,
+    };
+    Ok(ImportResult {
+        config,
+        format,
+        skipped,
+    })
+}
+
+/// 从当前进程的 CARGO_*（及 RUSTFLAGS 等）环境变量导入配置
+pub fn import_config_from_env() -> ImportResult {
+    let vars: Vec<(String, String)> = std::env::vars().collect();
+    let (config, skipped) = crate::explain::config_from_env_vars(&vars, false);
+    ImportResult {
+        config,
+        format: ImportFormat::Env,
+        skipped,
+    }
+}
+
+/// 键路径对应的 CARGO_* 变量名，如 `build.jobs` -> `CARGO_BUILD_JOBS`
+pub fn env_var_name(key: &str) -> String {
+    let body = split_key_path(key)
+        .iter()
+        .map(|segment| segment.replace(['-', '.'], "_").to_uppercase())
+        .collect::<Vec<_>>()
+        .join("_");
+    format!("CARGO_{}", body)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Toml,
+    Dotenv,
+    Shell,
+    GithubActions,
+    Dockerfile,
+}
+
+impl ExportFormat {
+    /// 未指定格式时按文件名推断，无法识别的一律按 TOML 导出
+    pub fn from_path(path: &Path) -> ExportFormat {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name == "dockerfile" || ext == "dockerfile" {
+            ExportFormat::Dockerfile
+        } else if name == ".env" || ext == "env" {
+            ExportFormat::Dotenv
+        } else if ext == "sh" {
+            ExportFormat::Shell
+        } else if ext == "yml" || ext == "yaml" {
+            ExportFormat::GithubActions
+        } else {
+            ExportFormat::Toml
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SkippedKey {
+    pub key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExportResult {
+    pub path: String,
+    pub format: ExportFormat,
+    pub exported: usize,
+    pub skipped: Vec<SkippedKey>,
+}
+
+/// `(变量名, 值)` 列表，保持键路径的排序
+pub type EnvVarList = Vec<(String, String)>;
+
+// cargo 不支持通过环境变量设置的配置段
+const ENV_UNSUPPORTED_SECTIONS: &[&str] = &["source", "env", "patch"];
+
+/// 键名为 `token` 或 `*-token` 时视为敏感信息，导出和配置解释共用同一判断
+pub fn is_secret_key(key: &str) -> bool {
+    split_key_path(key)
+        .last()
+        .is_some_and(|last| last == "token" || last.ends_with("-token"))
+}
+
+// 带用户名密码的代理地址同样不应导出
+fn is_credential_proxy(key: &str, value: &toml::Value) -> bool {
+    split_key_path(key).last().is_some_and(|last| last == "proxy")
+        && value.as_str().is_some_and(|proxy| proxy.contains('@'))
+}
+
+fn env_value_text(value: &toml::Value) -> Result<String, &'static str> {
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Ok(value.to_string())
+        }
+        // 列表以空白分隔，元素本身含空白时无法还原
+        toml::Value::Array(items) => {
+            let mut parts = Vec::new();
+            for item in items {
+                match item.as_str() {
+                    Some(text) if !text.is_empty() && !text.contains(char::is_whitespace) => {
+                        parts.push(text.to_string())
+                    }
+                    _ => return Err("列表元素不是不含空白的字符串"),
+                }
+            }
+            Ok(parts.join(" "))
+        }
+        _ => Err("不支持的值类型"),
+    }
+}
+
+/// 把配置转换为 CARGO_* 变量列表，无法表示的键和（未要求时的）敏感键记入 skipped
+pub fn config_to_env_vars(
+    config: &CargoConfig,
+    include_secrets: bool,
+) -> AppResult<(EnvVarList, Vec<SkippedKey>)> {
+    let mut vars = Vec::new();
+    let mut skipped = Vec::new();
+    for (key, value) in flatten_config_value(&config_to_value(config)?) {
+        let segments = split_key_path(&key);
+        let skip = |reason: &str| SkippedKey {
+            key: key.clone(),
+            reason: reason.to_string(),
+        };
+        if ENV_UNSUPPORTED_SECTIONS.contains(&segments[0].as_str()) {
+            skipped.push(skip("cargo 不支持通过环境变量设置该配置段"));
+            continue;
+        }
+        let representable = segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+        });
+        if !representable {
+            skipped.push(skip("键名无法转换为环境变量名"));
+            continue;
+        }
+        if !include_secrets && (is_secret_key(&key) || is_credential_proxy(&key, &value)) {
+            skipped.push(skip("敏感信息已排除"));
+            continue;
+        }
+        match env_value_text(&value) {
+            Ok(text) => vars.push((env_var_name(&key), text)),
+            Err(reason) => skipped.push(skip(reason)),
+        }
+    }
+    Ok((vars, skipped))
+}
+
+fn dotenv_quote(value: &str) -> String {
+    let plain = value
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || "_./:@+-,=".contains(ch));
+    if plain && !value.is_empty() {
+        value.to_string()
+    } else {
+        format!("\"{}\"", escape_shell_value(value).replace('$', "\\$"))
+    }
+}
+
+fn render_env_vars(format: ExportFormat, vars: &[(String, String)]) -> String {
+    let header = "# Generated by Cargo Assistant";
+    let mut out = String::new();
+    match format {
+        ExportFormat::Toml => {}
+        ExportFormat::Dotenv => {
+            out.push_str(header);
+            out.push('\n');
+            for (name, value) in vars {
+                out.push_str(&format!("{}={}\n", name, dotenv_quote(value)));
+            }
+        }
+        ExportFormat::Shell => {
+            out.push_str("#!/bin/sh\n");
+            out.push_str(header);
+            out.push('\n');
+            for (name, value) in vars {
+                out.push_str(&format!("export {}='{}'\n", name, value.replace('\'', "'\\''")));
+            }
+        }
+        // JSON 字符串同时是合法的 YAML 双引号字符串和 Dockerfile ENV 值
+        ExportFormat::GithubActions => {
+            out.push_str(header);
+            out.push_str("\nenv:\n");
+            for (name, value) in vars {
+                out.push_str(&format!("  {}: {}\n", name, serde_json::Value::from(value.as_str())));
+            }
+        }
+        ExportFormat::Dockerfile => {
+            out.push_str(header);
+            out.push('\n');
+            let lines: Vec<String> = vars
+                .iter()
+                .map(|(name, value)| {
+                    // ENV 会展开 $VAR，需转义
+                    let quoted = serde_json::Value::from(value.as_str()).to_string();
+                    format!("{}={}", name, quoted.replace('$', "\\$"))
+                })
+                .collect();
+            if !lines.is_empty() {
+                out.push_str("ENV ");
+                out.push_str(&lines.join(" \\\n    "));
+                out.push('\n');
+            }
+        }
+    }
+    out
 }
 
-pub fn export_config_to_path(config: &CargoConfig, path: &str) -> Result<(), String> {
+/// 导出配置；TOML 以外的格式按 cargo 环境变量命名规则生成，默认排除敏感键
+pub fn export_config_to_path(
+    config: &CargoConfig,
+    path: &str,
+    format: Option<ExportFormat>,
+    include_secrets: bool,
+) -> AppResult<ExportResult> {
     let resolved = expand_path(path);
-    let content =
-        toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+    let format = format.unwrap_or_else(|| ExportFormat::from_path(&resolved));
+    let (content, exported, skipped) = if format == ExportFormat::Toml {
+        let content = toml::to_string_pretty(config).map_err(AppError::serialize)?;
+        let count = flatten_config_value(&config_to_value(config)?).len();
+        (content, count, Vec::new())
+    } else {
+        let (vars, skipped) = config_to_env_vars(config, include_secrets)?;
+        (render_env_vars(format, &vars), vars.len(), skipped)
+    };
     if let Some(parent) = resolved.parent() {
         if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+            fs::create_dir_all(parent).map_err(|e| AppError::io("创建目录失败", e))?;
         }
     }
-    atomic_write(&resolved, &content)
+    atomic_write(&resolved, &content)?;
+    Ok(ExportResult {
+        path: resolved.to_string_lossy().to_string(),
+        format,
+        exported,
+        skipped,
+    })
 }
 
 pub fn get_current_target() -> String {
@@ -702,25 +2224,25 @@ fn build_export_lines(dist: Option<&str>, root: Option<&str>) -> String {
 }
 
 #[cfg(not(target_os = "windows"))]
-fn read_env_value_from_file(path: &Path, key: &str) -> Result<Option<String>, String> {
+fn read_env_value_from_file(path: &Path, key: &str) -> AppResult<Option<String>> {
     if !path.exists() {
         return Ok(None);
     }
-    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read env file: {}", e))?;
+    let content = fs::read_to_string(path).map_err(|e| AppError::io("读取环境变量文件失败", e))?;
     Ok(parse_env_value_from_lines(&content, key))
 }
 
 #[cfg(not(target_os = "windows"))]
-fn write_env_file(path: &Path, dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_env_file(path: &Path, dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     if dist.is_none() && root.is_none() {
         if path.exists() {
-            fs::remove_file(path).map_err(|e| format!("Failed to remove env file: {}", e))?;
+            fs::remove_file(path).map_err(|e| AppError::io("删除环境变量文件失败", e))?;
         }
         return Ok(());
     }
     if let Some(parent) = path.parent() {
         if !parent.exists() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create env dir: {}", e))?;
+            fs::create_dir_all(parent).map_err(|e| AppError::io("创建环境变量目录失败", e))?;
         }
     }
     let mut content = String::new();
@@ -730,30 +2252,30 @@ fn write_env_file(path: &Path, dist: Option<&str>, root: Option<&str>) -> Result
         content.push_str(&exports);
         content.push('\n');
     }
-    fs::write(path, content).map_err(|e| format!("Failed to write env file: {}", e))
+    fs::write(path, content).map_err(|e| AppError::io("写入环境变量文件失败", e))
 }
 
 #[cfg(not(target_os = "windows"))]
-fn read_env_value_from_managed_block(path: &Path, key: &str) -> Result<Option<String>, String> {
+fn read_env_value_from_managed_block(path: &Path, key: &str) -> AppResult<Option<String>> {
     if !path.exists() {
         return Ok(None);
     }
     let content = fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read profile file: {}", e))?;
+        .map_err(|e| AppError::io("读取 shell 配置文件失败", e))?;
     let block = extract_managed_block_body(&content);
     Ok(block.and_then(|b| parse_env_value_from_lines(&b, key)))
 }
 
 #[cfg(not(target_os = "windows"))]
-fn write_managed_block(path: &Path, block: Option<&str>) -> Result<(), String> {
+fn write_managed_block(path: &Path, block: Option<&str>) -> AppResult<()> {
     let content = if path.exists() {
-        fs::read_to_string(path).map_err(|e| format!("Failed to read profile file: {}", e))?
+        fs::read_to_string(path).map_err(|e| AppError::io("读取 shell 配置文件失败", e))?
     } else {
         String::new()
     };
     let updated = apply_managed_block(&content, block);
     if updated != content {
-        fs::write(path, updated).map_err(|e| format!("Failed to write profile file: {}", e))?;
+        fs::write(path, updated).map_err(|e| AppError::io("写入 shell 配置文件失败", e))?;
     }
     Ok(())
 }
@@ -849,7 +2371,7 @@ fn decode_powershell_output(bytes: &[u8]) -> String {
 }
 
 #[cfg(target_os = "windows")]
-fn run_powershell(script: &str) -> Result<String, String> {
+fn run_powershell(script: &str) -> AppResult<String> {
     let output = create_hidden_command("powershell")
         .args([
             "-NoProfile",
@@ -858,9 +2380,12 @@ fn run_powershell(script: &str) -> Result<String, String> {
             script,
         ])
         .output()
-        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+        .map_err(|e| AppError::io("无法运行 PowerShell", e))?;
     if !output.status.success() {
-        return Err(decode_powershell_output(&output.stderr));
+        return Err(AppError::command(
+            "PowerShell 执行失败",
+            decode_powershell_output(&output.stderr),
+        ));
     }
     Ok(decode_powershell_output(&output.stdout))
 }
@@ -884,18 +2409,18 @@ struct WindowsRustupEnvWriteResult {
 }
 
 #[cfg(target_os = "windows")]
-fn read_windows_env_snapshot() -> Result<WindowsRustupEnvSnapshot, String> {
+fn read_windows_env_snapshot() -> AppResult<WindowsRustupEnvSnapshot> {
     let script = format!(
         "$result = [ordered]@{{ user_dist = [Environment]::GetEnvironmentVariable('{dist}','User'); user_root = [Environment]::GetEnvironmentVariable('{root}','User'); system_dist = [Environment]::GetEnvironmentVariable('{dist}','Machine'); system_root = [Environment]::GetEnvironmentVariable('{root}','Machine') }}; $result | ConvertTo-Json -Compress",
         dist = RUSTUP_DIST_SERVER,
         root = RUSTUP_UPDATE_ROOT
     );
     let output = run_powershell(&script)?;
-    serde_json::from_str(&output).map_err(|e| format!("Failed to parse PowerShell output: {}", e))
+    serde_json::from_str(&output).map_err(|e| AppError::json("解析 PowerShell 输出失败", &output, e))
 }
 
 #[cfg(target_os = "windows")]
-fn write_windows_env_all(dist: Option<&str>, root: Option<&str>) -> Result<WindowsRustupEnvWriteResult, String> {
+fn write_windows_env_all(dist: Option<&str>, root: Option<&str>) -> AppResult<WindowsRustupEnvWriteResult> {
     let dist_expr = dist
         .map(|v| format!("'{}'", escape_powershell_single_quotes(v)))
         .unwrap_or_else(|| "$null".to_string());
@@ -910,11 +2435,11 @@ fn write_windows_env_all(dist: Option<&str>, root: Option<&str>) -> Result<Windo
         root_key = RUSTUP_UPDATE_ROOT
     );
     let output = run_powershell(&script)?;
-    serde_json::from_str(&output).map_err(|e| format!("Failed to parse PowerShell output: {}", e))
+    serde_json::from_str(&output).map_err(|e| AppError::json("解析 PowerShell 输出失败", &output, e))
 }
 
 #[cfg(target_os = "windows")]
-fn write_windows_env_user(dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_windows_env_user(dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     let dist_expr = dist
         .map(|v| format!("'{}'", escape_powershell_single_quotes(v)))
         .unwrap_or_else(|| "$null".to_string());
@@ -963,36 +2488,33 @@ fn get_user_profile_path() -> PathBuf {
 
 #[cfg(not(target_os = "windows"))]
 fn get_linux_env_file() -> PathBuf {
-    Path::new(&get_home_dir())
-        .join(".config")
-        .join("quickchange")
-        .join("rustup.env")
+    get_app_data_dir().join("rustup.env")
 }
 
 #[cfg(target_os = "macos")]
-fn read_macos_user_env(key: &str) -> Result<Option<String>, String> {
+fn read_macos_user_env(key: &str) -> AppResult<Option<String>> {
     let profile = get_user_profile_path();
     read_env_value_from_managed_block(&profile, key)
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn read_unix_user_env(key: &str) -> Result<Option<String>, String> {
+fn read_unix_user_env(key: &str) -> AppResult<Option<String>> {
     let env_file = get_linux_env_file();
     read_env_value_from_file(&env_file, key)
 }
 
 #[cfg(target_os = "macos")]
-fn read_macos_system_env(key: &str) -> Result<Option<String>, String> {
+fn read_macos_system_env(key: &str) -> AppResult<Option<String>> {
     read_env_value_from_managed_block(Path::new("/etc/zprofile"), key)
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn read_unix_system_env(key: &str) -> Result<Option<String>, String> {
+fn read_unix_system_env(key: &str) -> AppResult<Option<String>> {
     read_env_value_from_file(Path::new("/etc/profile.d/quickchange-rustup.sh"), key)
 }
 
 #[cfg(target_os = "macos")]
-fn write_macos_user_env(dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_macos_user_env(dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     let profile = get_user_profile_path();
     if dist.is_none() && root.is_none() {
         return write_managed_block(&profile, None);
@@ -1003,7 +2525,7 @@ fn write_macos_user_env(dist: Option<&str>, root: Option<&str>) -> Result<(), St
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn write_unix_user_env(dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_unix_user_env(dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     let env_file = get_linux_env_file();
     write_env_file(&env_file, dist, root)?;
     let profile = get_user_profile_path();
@@ -1017,7 +2539,7 @@ fn write_unix_user_env(dist: Option<&str>, root: Option<&str>) -> Result<(), Str
 }
 
 #[cfg(target_os = "macos")]
-fn write_macos_system_env(dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_macos_system_env(dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     let profile = Path::new("/etc/zprofile");
     if dist.is_none() && root.is_none() {
         return write_managed_block(profile, None);
@@ -1028,7 +2550,7 @@ fn write_macos_system_env(dist: Option<&str>, root: Option<&str>) -> Result<(),
 }
 
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn write_unix_system_env(dist: Option<&str>, root: Option<&str>) -> Result<(), String> {
+fn write_unix_system_env(dist: Option<&str>, root: Option<&str>) -> AppResult<()> {
     write_env_file(Path::new("/etc/profile.d/quickchange-rustup.sh"), dist, root)
 }
 
@@ -1060,6 +2582,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                 }
             }
             Err(err) => {
+                let err = err.to_string();
                 status.dist.user.error = Some(err.clone());
                 status.root.user.error = Some(err.clone());
                 status.dist.system.error = Some(err.clone());
@@ -1077,7 +2600,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.dist.user.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.dist.user.error = Some(err),
+            Err(err) => status.dist.user.error = Some(err.to_string()),
         }
         match read_macos_system_env(RUSTUP_DIST_SERVER) {
             Ok(value) => {
@@ -1086,7 +2609,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.dist.system.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.dist.system.error = Some(err),
+            Err(err) => status.dist.system.error = Some(err.to_string()),
         }
         match read_macos_user_env(RUSTUP_UPDATE_ROOT) {
             Ok(value) => {
@@ -1095,7 +2618,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.root.user.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.root.user.error = Some(err),
+            Err(err) => status.root.user.error = Some(err.to_string()),
         }
         match read_macos_system_env(RUSTUP_UPDATE_ROOT) {
             Ok(value) => {
@@ -1104,7 +2627,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.root.system.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.root.system.error = Some(err),
+            Err(err) => status.root.system.error = Some(err.to_string()),
         }
     }
 
@@ -1117,7 +2640,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.dist.user.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.dist.user.error = Some(err),
+            Err(err) => status.dist.user.error = Some(err.to_string()),
         }
         match read_unix_system_env(RUSTUP_DIST_SERVER) {
             Ok(value) => {
@@ -1126,7 +2649,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.dist.system.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.dist.system.error = Some(err),
+            Err(err) => status.dist.system.error = Some(err.to_string()),
         }
         match read_unix_user_env(RUSTUP_UPDATE_ROOT) {
             Ok(value) => {
@@ -1135,7 +2658,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.root.user.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.root.user.error = Some(err),
+            Err(err) => status.root.user.error = Some(err.to_string()),
         }
         match read_unix_system_env(RUSTUP_UPDATE_ROOT) {
             Ok(value) => {
@@ -1144,7 +2667,7 @@ pub fn get_rustup_env_status() -> RustupEnvStatus {
                     status.root.system.source = Some("managed".to_string());
                 }
             }
-            Err(err) => status.root.system.error = Some(err),
+            Err(err) => status.root.system.error = Some(err.to_string()),
         }
     }
 
@@ -1201,6 +2724,7 @@ pub fn set_rustup_env(dist: Option<String>, root: Option<String>) -> RustupEnvWr
                     };
                 }
                 Err(err) => {
+                    let err = err.to_string();
                     user = RustupEnvWriteOutcome { ok: false, error: Some(err.clone()), skipped: false };
                     system = RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false };
                 }
@@ -1208,7 +2732,7 @@ pub fn set_rustup_env(dist: Option<String>, root: Option<String>) -> RustupEnvWr
         } else {
             user = match write_windows_env_user(dist.as_deref(), root.as_deref()) {
                 Ok(_) => RustupEnvWriteOutcome { ok: true, error: None, skipped: false },
-                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false },
+                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err.to_string()), skipped: false },
             };
             system = RustupEnvWriteOutcome {
                 ok: true,
@@ -1226,12 +2750,12 @@ pub fn set_rustup_env(dist: Option<String>, root: Option<String>) -> RustupEnvWr
     {
         user = match write_macos_user_env(dist.as_deref(), root.as_deref()) {
             Ok(_) => RustupEnvWriteOutcome { ok: true, error: None, skipped: false },
-            Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false },
+            Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err.to_string()), skipped: false },
         };
         system = if is_admin {
             match write_macos_system_env(dist.as_deref(), root.as_deref()) {
                 Ok(_) => RustupEnvWriteOutcome { ok: true, error: None, skipped: false },
-                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false },
+                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err.to_string()), skipped: false },
             }
         } else {
             RustupEnvWriteOutcome {
@@ -1246,12 +2770,12 @@ pub fn set_rustup_env(dist: Option<String>, root: Option<String>) -> RustupEnvWr
     {
         user = match write_unix_user_env(dist.as_deref(), root.as_deref()) {
             Ok(_) => RustupEnvWriteOutcome { ok: true, error: None, skipped: false },
-            Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false },
+            Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err.to_string()), skipped: false },
         };
         system = if is_admin {
             match write_unix_system_env(dist.as_deref(), root.as_deref()) {
                 Ok(_) => RustupEnvWriteOutcome { ok: true, error: None, skipped: false },
-                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err), skipped: false },
+                Err(err) => RustupEnvWriteOutcome { ok: false, error: Some(err.to_string()), skipped: false },
             }
         } else {
             RustupEnvWriteOutcome {
@@ -1267,7 +2791,11 @@ pub fn set_rustup_env(dist: Option<String>, root: Option<String>) -> RustupEnvWr
         set_process_rustup_env(dist.as_deref(), root.as_deref());
     }
 
-    RustupEnvWriteResult { user, system }
+    RustupEnvWriteResult {
+        user,
+        system,
+        warning: None,
+    }
 }
 
 pub fn get_rustup_env_effective() -> (Option<String>, Option<String>) {
@@ -1277,72 +2805,482 @@ pub fn get_rustup_env_effective() -> (Option<String>, Option<String>) {
     (dist, root)
 }
 
-pub fn clear_backups(config_path: Option<&str>) -> Result<usize, String> {
-    let backup_dir = get_backup_dir(config_path);
+pub fn clear_backups(config_path: Option<&str>) -> AppResult<usize> {
+    let backup_dir = prepare_backup_dir(config_path)?;
     if !backup_dir.exists() {
         return Ok(0);
     }
 
     let mut count = 0;
     let entries =
-        fs::read_dir(&backup_dir).map_err(|e| format!("Failed to read backup dir: {}", e))?;
+        fs::read_dir(&backup_dir).map_err(|e| AppError::io("读取备份目录失败", e))?;
     for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let entry = entry.map_err(|e| AppError::io("读取备份目录失败", e))?;
         let path = entry.path();
         if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
             fs::remove_file(&path)
-                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                .map_err(|e| AppError::io("删除文件失败", e).with_path(&path))?;
             count += 1;
         }
     }
+    update_backup_manifest(&backup_dir, |manifest| {
+        manifest.clear();
+        true
+    })?;
 
     Ok(count)
 }
 
-pub fn delete_backup(config_path: Option<&str>, name: String) -> Result<(), String> {
-    let backup_dir = get_backup_dir(config_path);
-    let file_name = Path::new(&name)
-        .file_name()
-        .ok_or_else(|| "Invalid backup name".to_string())?;
-    let backup_path = backup_dir.join(file_name);
-
-    if !backup_path.exists() {
-        return Err("备份文件不存在".to_string());
-    }
+pub fn delete_backup(config_path: Option<&str>, name: String) -> AppResult<()> {
+    let backup_dir = prepare_backup_dir(config_path)?;
+    let backup_path = resolve_backup_file(&backup_dir, &name)?;
 
-    fs::remove_file(&backup_path).map_err(|e| format!("Failed to delete backup: {}", e))?;
-    Ok(())
+    fs::remove_file(&backup_path).map_err(|e| AppError::io("删除备份失败", e))?;
+    update_backup_manifest(&backup_dir, |manifest| manifest.remove(&name).is_some())
 }
 
 pub fn rename_backup(
     config_path: Option<&str>,
     old_name: String,
     new_name: String,
-) -> Result<(), String> {
-    let backup_dir = get_backup_dir(config_path);
+) -> AppResult<()> {
+    let backup_dir = prepare_backup_dir(config_path)?;
+    rename_backup_file(&backup_dir, &old_name, &new_name).map(|_| ())
+}
 
-    let old_file_name = Path::new(&old_name)
-        .file_name()
-        .ok_or_else(|| "Invalid old backup name".to_string())?;
-    let old_path = backup_dir.join(old_file_name);
+// 返回实际使用的新文件名
+fn rename_backup_file(backup_dir: &Path, old_name: &str, new_name: &str) -> AppResult<String> {
+    let old_path = resolve_backup_file(backup_dir, old_name)?;
 
-    if !old_path.exists() {
-        return Err("备份文件不存在".to_string());
+    // 新名称与手动备份标签走同一套清洗规则，并确保以 .toml 结尾
+    let trimmed = new_name.trim();
+    let label = trimmed.strip_suffix(".toml").unwrap_or(trimmed);
+    if label.trim().is_empty() {
+        return Err(AppError::invalid("备份名称不能为空"));
+    }
+    let new_name_with_ext = format!("{}.toml", sanitize_backup_label(label));
+    if new_name_with_ext == old_name {
+        return Ok(new_name_with_ext);
     }
-
-    // 确保新文件名以 .toml 结尾
-    let new_name_with_ext = if new_name.ends_with(".toml") {
-        new_name
-    } else {
-        format!("{}.toml", new_name)
-    };
-
     let new_path = backup_dir.join(&new_name_with_ext);
 
     if new_path.exists() {
-        return Err("目标文件名已存在".to_string());
+        return Err(AppError::new(ErrorCode::AlreadyExists, "目标文件名已存在"));
     }
 
-    fs::rename(&old_path, &new_path).map_err(|e| format!("Failed to rename backup: {}", e))?;
-    Ok(())
+    // 优先用硬链接实现不覆盖的重命名，文件系统不支持时退回普通 rename
+    match fs::hard_link(&old_path, &new_path) {
+        Ok(_) => {
+            fs::remove_file(&old_path).map_err(|e| AppError::io("重命名备份失败", e))?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(AppError::new(ErrorCode::AlreadyExists, "目标文件名已存在"));
+        }
+        Err(_) => {
+            fs::rename(&old_path, &new_path)
+                .map_err(|e| AppError::io("重命名备份失败", e))?;
+        }
+    }
+    update_backup_manifest(backup_dir, |manifest| match manifest.remove(old_name) {
+        Some(checksum) => {
+            manifest.insert(new_name_with_ext.clone(), checksum);
+            true
+        }
+        None => false,
+    })?;
+    Ok(new_name_with_ext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::TestDir;
+    use super::*;
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let test_dir = TestDir::new("migrate");
+        let root = test_dir.path().to_path_buf();
+        let (from, to) = (root.join("old"), root.join("new"));
+        for name in ["a", "b"] {
+            fs::create_dir_all(from.join(name)).unwrap();
+            fs::write(from.join(name).join("x.toml"), name).unwrap();
+            fs::write(from.join(name).join(BACKUP_MANIFEST), "{}").unwrap();
+        }
+        fs::create_dir_all(&to).unwrap();
+        // 目标中与 b 同名的文件使 b 的迁移失败
+        fs::write(to.join("b"), "").unwrap();
+
+        let mut journal = MoveJournal::default();
+        let result = ["a", "b"]
+            .iter()
+            .try_for_each(|name| move_backup_files(&from.join(name), &to.join(name), &mut journal).map(|_| ()));
+        assert!(result.is_err());
+        journal.rollback();
+
+        for name in ["a", "b"] {
+            assert_eq!(fs::read_to_string(from.join(name).join("x.toml")).unwrap(), name);
+            assert!(from.join(name).join(BACKUP_MANIFEST).is_file());
+        }
+        assert!(!to.join("a").exists());
+    }
+
+    #[test]
+    fn migration_keeps_existing_history_repo_intact() {
+        let test_dir = TestDir::new("migrate-history");
+        let root = test_dir.path().to_path_buf();
+        let (from, to) = (root.join("old"), root.join("new"));
+        for (dir, head) in [(&from, "ref: refs/heads/old"), (&to, "ref: refs/heads/new")] {
+            let git = dir.join("history").join(".git");
+            fs::create_dir_all(git.join("refs").join("heads")).unwrap();
+            fs::write(git.join("HEAD"), head).unwrap();
+            fs::write(dir.join("undo.json"), head).unwrap();
+            fs::write(dir.join("x.toml"), head).unwrap();
+        }
+        fs::create_dir_all(from.join(BACKUP_QUARANTINE_DIR)).unwrap();
+        fs::write(from.join(BACKUP_QUARANTINE_DIR).join("bad.toml"), "").unwrap();
+
+        let mut journal = MoveJournal::default();
+        let count = move_backup_files(&from, &to, &mut journal).unwrap();
+        let conflicts = journal.finish();
+
+        // 备份文件改名合并，隔离区整体迁移，都计入数量
+        assert_eq!(count, 2);
+        assert!(to.join("x-1.toml").is_file());
+        assert!(to.join(BACKUP_QUARANTINE_DIR).join("bad.toml").is_file());
+        // 目标的 history 仓库和 undo.json 保持原样，源的副本留在原处并报告冲突
+        let git = to.join("history").join(".git");
+        assert_eq!(fs::read_to_string(git.join("HEAD")).unwrap(), "ref: refs/heads/new");
+        assert!(!git.join("HEAD-1").exists());
+        assert_eq!(fs::read_to_string(to.join("undo.json")).unwrap(), "ref: refs/heads/new");
+        assert!(from.join("history").join(".git").join("HEAD").is_file());
+        assert!(from.join("undo.json").is_file());
+        assert_eq!(conflicts.len(), 2);
+    }
+
+    #[test]
+    fn migration_moves_history_repo_as_a_unit() {
+        let test_dir = TestDir::new("migrate-history-new");
+        let root = test_dir.path().to_path_buf();
+        let (from, to) = (root.join("old"), root.join("new"));
+        let git = from.join("history").join(".git");
+        fs::create_dir_all(&git).unwrap();
+        fs::write(git.join("HEAD"), "ref: refs/heads/master").unwrap();
+        fs::write(from.join("history").join("config.toml"), "").unwrap();
+        fs::write(from.join("a.toml"), "").unwrap();
+
+        let mut journal = MoveJournal::default();
+        assert_eq!(move_backup_files(&from, &to, &mut journal).unwrap(), 1);
+        assert!(journal.finish().is_empty());
+        assert!(to.join("history").join(".git").join("HEAD").is_file());
+        assert!(to.join("history").join("config.toml").is_file());
+        assert!(!from.exists());
+    }
+
+    #[test]
+    fn lock_file_is_not_created_next_to_config() {
+        let test_dir = TestDir::new("lock");
+        let dir = test_dir.path().to_path_buf();
+        let path = dir.join("config.toml");
+        write_config_file(&path, "[build]\njobs = 2\n").unwrap();
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("config.toml")]);
+        assert_ne!(lock_file_path(&path), lock_file_path(&dir.join("other.toml")));
+    }
+
+    #[test]
+    fn concurrent_checksums_are_not_lost() {
+        let test_dir = TestDir::new("manifest");
+        let dir = test_dir.path().to_path_buf();
+        let handles: Vec<_> = (0..8)
+            .map(|index| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    let name = format!("backup-{}.toml", index);
+                    fs::write(dir.join(&name), format!("# {}", index)).unwrap();
+                    record_backup_checksum(&dir, &name).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(load_backup_manifest(&dir).len(), 8);
+    }
+
+    #[test]
+    fn backups_in_the_same_millisecond_get_unique_names() {
+        let test_dir = TestDir::new("unique");
+        let dir = test_dir.path().to_path_buf();
+        // 复用同一个名称模拟同一毫秒内的多次备份
+        let base = build_backup_name(None);
+        let stamp = base.strip_prefix("auto-").unwrap();
+        assert!(!stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit()), "{}", base);
+        let (first, _) = create_unique_backup_file(&dir, &base).unwrap();
+        let (second, _) = create_unique_backup_file(&dir, &base).unwrap();
+        assert_eq!(first, format!("{}.toml", base));
+        assert_eq!(second, format!("{}-1.toml", base));
+        assert_eq!(backup_kind(&second), "auto");
+
+        let manual = build_backup_name(Some("same"));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                let manual = manual.clone();
+                std::thread::spawn(move || create_unique_backup_file(&dir, &manual).unwrap().0)
+            })
+            .collect();
+        let mut names: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 8);
+        assert!(names.iter().all(|name| backup_kind(name) == "manual"));
+    }
+
+    #[test]
+    fn rename_sanitizes_separators_and_reserved_names() {
+        let test_dir = TestDir::new("rename");
+        let dir = test_dir.path().to_path_buf();
+        fs::write(dir.join("manual-a.toml"), "").unwrap();
+
+        let renamed = rename_backup_file(&dir, "manual-a.toml", "../outside").unwrap();
+        assert_eq!(renamed, "outside.toml");
+        assert!(dir.join(&renamed).is_file());
+        assert!(!dir.parent().unwrap().join("outside.toml").exists());
+
+        let renamed = rename_backup_file(&dir, &renamed, "a/b\\c").unwrap();
+        assert_eq!(renamed, "a-b-c.toml");
+
+        let renamed = rename_backup_file(&dir, &renamed, "CON").unwrap();
+        assert_eq!(renamed, "_CON.toml");
+        let renamed = rename_backup_file(&dir, &renamed, "nul.txt").unwrap();
+        assert_eq!(renamed, "_nul.txt.toml");
+
+        assert!(rename_backup_file(&dir, &renamed, "  ").is_err());
+        fs::write(dir.join("taken.toml"), "").unwrap();
+        let err = rename_backup_file(&dir, &renamed, "taken").unwrap_err();
+        assert_eq!(err.code, ErrorCode::AlreadyExists);
+    }
+
+    #[test]
+    fn backup_names_cannot_escape_the_backup_dir() {
+        let test_dir = TestDir::new("escape");
+        let root = test_dir.path().to_path_buf();
+        let dir = root.join("backups");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(root.join("secret.toml"), "").unwrap();
+        fs::write(dir.join("ok.toml"), "").unwrap();
+
+        assert!(resolve_backup_file(&dir, "ok.toml").is_ok());
+        for name in ["../secret.toml", "..", ".", "", "sub/ok.toml", "ok.txt"] {
+            assert_eq!(resolve_backup_file(&dir, name).unwrap_err().code, ErrorCode::InvalidInput, "{}", name);
+        }
+        let absolute = root.join("secret.toml");
+        assert!(resolve_backup_file(&dir, &absolute.to_string_lossy()).is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("secret.toml"), dir.join("link.toml")).unwrap();
+            let err = resolve_backup_file(&dir, "link.toml").unwrap_err();
+            assert_eq!(err.code, ErrorCode::InvalidInput);
+            assert!(rename_backup_file(&dir, "link.toml", "moved").is_err());
+            assert!(root.join("secret.toml").is_file());
+        }
+    }
+
+    const PARTLY_BROKEN: &str = "# top\n[build] # 构建\njobs = 4\n  [not-a-header]\n\n[http]\ntimeout = \"slow\"\n\n[alias]\nb = \"build\"\n\n[doc]\nbrowser = \"firefox\n[net]\noffline = true\n";
+
+    #[test]
+    fn toml_sections_are_split_at_headers_with_line_numbers() {
+        let sections = split_toml_sections(PARTLY_BROKEN);
+        let headers: Vec<(&str, usize)> = sections.iter().map(|(h, l, _)| (h.as_str(), *l)).collect();
+        assert_eq!(
+            headers,
+            vec![("", 1), ("[build]", 2), ("[http]", 6), ("[alias]", 9), ("[doc]", 12), ("[net]", 14)]
+        );
+        assert_eq!(sections[1].2, "[build] # 构建\njobs = 4\n  [not-a-header]\n\n");
+        let joined: String = sections.iter().map(|(_, _, text)| text.as_str()).collect();
+        assert_eq!(joined, PARTLY_BROKEN);
+    }
+
+    #[test]
+    fn recovering_skips_bad_sections_and_keeps_line_numbers() {
+        let (config, skipped) = recover_config(PARTLY_BROKEN);
+
+        assert_eq!(config.build.as_ref().unwrap().jobs, Some(4));
+        assert_eq!(config.alias.as_ref().unwrap()["b"], "build");
+        assert_eq!(config.net.as_ref().unwrap().offline, Some(true));
+        assert!(config.http.is_none());
+        assert!(config.doc.is_none());
+
+        let found: Vec<(&str, usize)> = skipped.iter().map(|s| (s.header.as_str(), s.line)).collect();
+        assert_eq!(found, vec![("[http]", 6), ("[doc]", 12)]);
+        // 错误位置指向原文件中的行，片段也取自原文件
+        assert_eq!(skipped[0].error.line(), Some(7));
+        assert!(skipped[0].error.location.as_ref().unwrap().snippet.contains("timeout = \"slow\""));
+        assert_eq!(skipped[1].error.line(), Some(13));
+        assert!(skipped[1].content.starts_with("[doc]\n"));
+    }
+
+    #[test]
+    fn json_import_errors_include_a_snippet() {
+        let test_dir = TestDir::new("json");
+        let path = test_dir.path().join("bad.json");
+        fs::write(&path, "{\n  \"build\": {\n    \"jobs\": 4,\n  }\n}\n").unwrap();
+
+        let err = import_config_from_path(&path.to_string_lossy(), None).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Parse);
+        assert_eq!(err.line(), Some(4));
+        assert!(err.location.as_ref().unwrap().snippet.contains("\"jobs\": 4,"));
+    }
+
+    #[test]
+    fn env_var_names_follow_cargo_rules() {
+        assert_eq!(env_var_name("build.jobs"), "CARGO_BUILD_JOBS");
+        assert_eq!(
+            env_var_name("registries.my-registry.token"),
+            "CARGO_REGISTRIES_MY_REGISTRY_TOKEN"
+        );
+        assert_eq!(
+            env_var_name("target.x86_64-unknown-linux-gnu.linker"),
+            "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_LINKER"
+        );
+        assert_eq!(
+            env_var_name("profile.release.package.my-crate.opt-level"),
+            "CARGO_PROFILE_RELEASE_PACKAGE_MY_CRATE_OPT_LEVEL"
+        );
+        assert_eq!(env_var_name("profile.dev.\"a.b\".debug"), "CARGO_PROFILE_DEV_A_B_DEBUG");
+    }
+
+    #[test]
+    fn secret_keys_are_tokens() {
+        assert!(is_secret_key("registry.token"));
+        assert!(is_secret_key("registries.my-registry.token"));
+        assert!(is_secret_key("registries.\"my.registry\".token"));
+        assert!(is_secret_key("registry.global-token"));
+        assert!(!is_secret_key("registry.credential-provider"));
+        assert!(!is_secret_key("registry.tokens"));
+        assert!(!is_secret_key("alias.token-helper"));
+    }
+
+    #[test]
+    fn env_vars_are_quoted_for_each_format() {
+        let vars = vec![
+            ("CARGO_A".to_string(), "plain-1.0".to_string()),
+            ("CARGO_B".to_string(), r#"a b $HOME "q" 'x'"#.to_string()),
+            ("CARGO_C".to_string(), String::new()),
+        ];
+
+        assert_eq!(render_env_vars(ExportFormat::Toml, &vars), "");
+        assert_eq!(
+            render_env_vars(ExportFormat::Dotenv, &vars),
+            concat!(
+                "# Generated by Cargo Assistant\n",
+                "CARGO_A=plain-1.0\n",
+                r#"CARGO_B="a b \$HOME \"q\" 'x'""#,
+                "\nCARGO_C=\"\"\n",
+            )
+        );
+        assert_eq!(
+            render_env_vars(ExportFormat::Shell, &vars),
+            concat!(
+                "#!/bin/sh\n# Generated by Cargo Assistant\n",
+                "export CARGO_A='plain-1.0'\n",
+                r#"export CARGO_B='a b $HOME "q" '\''x'\'''"#,
+                "\nexport CARGO_C=''\n",
+            )
+        );
+        assert_eq!(
+            render_env_vars(ExportFormat::GithubActions, &vars),
+            concat!(
+                "# Generated by Cargo Assistant\nenv:\n",
+                "  CARGO_A: \"plain-1.0\"\n",
+                r#"  CARGO_B: "a b $HOME \"q\" 'x'""#,
+                "\n  CARGO_C: \"\"\n",
+            )
+        );
+        assert_eq!(
+            render_env_vars(ExportFormat::Dockerfile, &vars),
+            concat!(
+                "# Generated by Cargo Assistant\n",
+                "ENV CARGO_A=\"plain-1.0\" \\\n",
+                r#"    CARGO_B="a b \$HOME \"q\" 'x'" \"#,
+                "\n    CARGO_C=\"\"\n",
+            )
+        );
+        assert_eq!(
+            render_env_vars(ExportFormat::Dockerfile, &[]),
+            "# Generated by Cargo Assistant\n"
+        );
+    }
+
+    #[test]
+    fn export_format_is_guessed_from_the_file_name() {
+        let cases = [
+            ("Dockerfile", ExportFormat::Dockerfile),
+            ("build/app.dockerfile", ExportFormat::Dockerfile),
+            (".env", ExportFormat::Dotenv),
+            ("ci.ENV", ExportFormat::Dotenv),
+            ("cargo-env.sh", ExportFormat::Shell),
+            (".github/workflows/ci.yml", ExportFormat::GithubActions),
+            ("env.yaml", ExportFormat::GithubActions),
+            ("config.toml", ExportFormat::Toml),
+            ("config", ExportFormat::Toml),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(ExportFormat::from_path(Path::new(path)), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn dotenv_handles_quotes_escapes_comments_and_export() {
+        let content = concat!(
+            "# 注释\n",
+            "\n",
+            "export CARGO_BUILD_JOBS=4\n",
+            r#"CARGO_HTTP_USER_AGENT="say \"hi\"\n\\done" # 行尾注释"#,
+            "\n",
+            "CARGO_A='single # kept' # dropped\n",
+            "CARGO_B=plain # dropped\n",
+            "CARGO_C=a#b\n",
+            "NO_EQUALS_SIGN\n",
+            "  CARGO_D = spaced  \n",
+            "CARGO_E=\n",
+        );
+        let vars = parse_dotenv(content);
+        let expected: Vec<(String, String)> = [
+            ("CARGO_BUILD_JOBS", "4"),
+            ("CARGO_HTTP_USER_AGENT", "say \"hi\"\n\\done"),
+            ("CARGO_A", "single # kept"),
+            ("CARGO_B", "plain"),
+            ("CARGO_C", "a#b"),
+            ("CARGO_D", "spaced"),
+            ("CARGO_E", ""),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        assert_eq!(vars, expected);
+    }
+
+    #[test]
+    fn yaml_config_is_parsed_and_errors_have_a_location() {
+        let config = parse_yaml_config(
+            "build:\n  jobs: 4\n  rustflags: [\"-C\", \"opt-level=2\"]\nalias:\n  b: build\n",
+        )
+        .unwrap();
+        let build = config.build.unwrap();
+        assert_eq!(build.jobs, Some(4));
+        assert_eq!(build.rustflags.unwrap(), vec!["-C", "opt-level=2"]);
+        assert_eq!(config.alias.unwrap()["b"], "build");
+
+        let err = parse_yaml_config("build:\n  jobs: 4\n  rustflags: [\n").unwrap_err();
+        assert_eq!(err.code, ErrorCode::Parse);
+        assert!(err.line().is_some());
+
+        let err = parse_yaml_config("build:\n  jobs: many\n").unwrap_err();
+        assert_eq!(err.line(), Some(2));
+    }
 }
@@ -0,0 +1,224 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// 前端据此区分错误类型，序列化后的取值视为稳定接口，不要随意改名
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    InvalidInput,
+    Parse,
+    Serialize,
+    Conflict,
+    Locked,
+    Io,
+    Command,
+    Internal,
+}
+
+/// 解析错误在源文件中的位置，`snippet` 为出错行及上下文（带行号和列指示）
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceLocation {
+    pub line: u32,
+    pub column: u32,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub detail: Option<String>,
+    pub path: Option<String>,
+    pub key: Option<String>,
+    #[serde(flatten)]
+    pub location: Option<Box<SourceLocation>>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            message: message.into(),
+            detail: None,
+            path: None,
+            key: None,
+            location: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// 后台任务（spawn_blocking）异常退出
+    pub fn task(err: impl fmt::Display) -> Self {
+        Self::internal("后台任务执行失败").with_detail(err)
+    }
+
+    pub fn with_detail(mut self, detail: impl fmt::Display) -> Self {
+        let detail = detail.to_string();
+        self.detail = if detail.trim().is_empty() { None } else { Some(detail) };
+        self
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_string_lossy().to_string());
+        self
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn io(message: impl Into<String>, err: io::Error) -> Self {
+        let code = match err.kind() {
+            io::ErrorKind::NotFound => ErrorCode::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            io::ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, message).with_detail(err)
+    }
+
+    pub fn serialize(err: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::Serialize, "序列化失败").with_detail(err)
+    }
+
+    pub fn command(message: impl Into<String>, stderr: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::Command, message).with_detail(stderr)
+    }
+
+    /// TOML 解析错误，附带行列号及出错位置所在的键路径
+    pub fn parse(message: impl Into<String>, content: &str, err: &toml::de::Error) -> Self {
+        let mut error = Self::new(ErrorCode::Parse, message).with_detail(err.message());
+        if let Some(span) = err.span() {
+            let (line, column) = line_column(content, span.start);
+            error.key = key_at_line(content, line);
+            error = error.with_location(content, line, column);
+        }
+        error
+    }
+
+    /// 按行列号附加源码片段，行号从 1 开始
+    pub fn with_location(mut self, content: &str, line: usize, column: usize) -> Self {
+        self.location = Some(Box::new(SourceLocation {
+            line: line as u32,
+            column: column as u32,
+            snippet: source_snippet(content, line, column),
+        }));
+        self
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line as usize)
+    }
+
+    /// JSON 解析错误，与 `parse` 一样附带出错位置的源码片段
+    pub fn json(message: impl Into<String>, content: &str, err: serde_json::Error) -> Self {
+        let error = Self::new(ErrorCode::Parse, message).with_detail(&err);
+        if err.line() > 0 {
+            error.with_location(content, err.line(), err.column())
+        } else {
+            error
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.message, detail),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// 把字节偏移换算为从 1 开始的行号和列号
+pub fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(content.len());
+    while !content.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+const SNIPPET_CONTEXT: usize = 2;
+
+/// 截取出错行前后各两行，并在出错列下方加 `^` 标记
+pub fn source_snippet(content: &str, line: usize, column: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return String::new();
+    }
+    let first = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+    let last = (line + SNIPPET_CONTEXT).min(lines.len());
+    let width = last.to_string().len();
+
+    let mut snippet = String::new();
+    for number in first..=last {
+        let text = lines[number - 1];
+        snippet.push_str(&format!("{:>width$} | {}\n", number, text, width = width));
+        if number == line {
+            let offset: String = text
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+            snippet.push_str(&format!("{:>width$} | {}^\n", "", offset, width = width));
+        }
+    }
+    snippet
+}
+
+fn strip_key_quotes(key: &str) -> String {
+    key.split('.')
+        .map(|segment| segment.trim().trim_matches('"').trim_matches('\''))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// 根据出错行向上查找最近的表头，拼出类似 `build.jobs` 的键路径
+fn key_at_line(content: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let current = lines.get(line.checked_sub(1)?)?.trim();
+    let header = lines[..line - 1]
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+        .find(|l| l.starts_with('['))
+        .map(|l| strip_key_quotes(l.trim_start_matches('[').trim_end_matches(']')));
+
+    if current.starts_with('[') {
+        return Some(strip_key_quotes(current.trim_start_matches('[').trim_end_matches(']')));
+    }
+    let key = current.split('=').next().map(strip_key_quotes)?;
+    if key.is_empty() || !current.contains('=') {
+        return header;
+    }
+    match header {
+        Some(header) if !header.is_empty() => Some(format!("{}.{}", header, key)),
+        _ => Some(key),
+    }
+}
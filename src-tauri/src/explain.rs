@@ -0,0 +1,719 @@
+use crate::config::{self, CargoConfig, SkippedKey};
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// 常用的 cargo 配置键，用于把 CARGO_* 环境变量反查回键路径
+const KNOWN_KEYS: &[&str] = &[
+    "build.jobs",
+    "build.target",
+    "build.target-dir",
+    "build.rustc",
+    "build.rustc-wrapper",
+    "build.rustc-workspace-wrapper",
+    "build.rustdoc",
+    "build.rustflags",
+    "build.rustdocflags",
+    "build.incremental",
+    "build.dep-info-basedir",
+    "cargo-new.vcs",
+    "doc.browser",
+    "http.proxy",
+    "http.timeout",
+    "http.cainfo",
+    "http.check-revoke",
+    "http.multiplexing",
+    "http.low-speed-limit",
+    "http.ssl-version",
+    "http.user-agent",
+    "http.debug",
+    "install.root",
+    "net.retry",
+    "net.git-fetch-with-cli",
+    "net.offline",
+    "registry.default",
+    "registry.token",
+    "registry.global-credential-providers",
+    "term.verbose",
+    "term.quiet",
+    "term.color",
+    "term.progress.when",
+    "term.progress.width",
+    "future-incompat-report.frequency",
+];
+
+// 以列表形式读取的键，环境变量按空白拆分
+const LIST_KEYS: &[&str] = &["rustflags", "rustdocflags", "global-credential-providers"];
+
+// 不走 CARGO_ 前缀规则、且会整体替换配置值的环境变量，按优先级从高到低排列
+const SPECIAL_ENV: &[(&str, &str)] = &[
+    ("CARGO_TARGET_DIR", "build.target-dir"),
+    ("CARGO_ENCODED_RUSTFLAGS", "build.rustflags"),
+    ("RUSTFLAGS", "build.rustflags"),
+    ("CARGO_ENCODED_RUSTDOCFLAGS", "build.rustdocflags"),
+    ("RUSTDOCFLAGS", "build.rustdocflags"),
+    ("RUSTC", "build.rustc"),
+    ("RUSTC_WRAPPER", "build.rustc-wrapper"),
+    ("RUSTC_WORKSPACE_WRAPPER", "build.rustc-workspace-wrapper"),
+    ("RUSTDOC", "build.rustdoc"),
+    ("CARGO_INCREMENTAL", "build.incremental"),
+    ("CARGO_INSTALL_ROOT", "install.root"),
+];
+
+// 配置中未设置 http.proxy 时 cargo 才会读取的代理变量
+const PROXY_FALLBACK_ENV: &[&str] = &["http_proxy", "HTTPS_PROXY", "https_proxy"];
+
+const REGISTRY_FIELDS: &[&str] = &["TOKEN", "INDEX", "CREDENTIAL_PROVIDER", "PROTOCOL"];
+const TARGET_FIELDS: &[&str] = &["LINKER", "RUNNER", "RUSTFLAGS", "AR"];
+const PROFILE_FIELDS: &[&str] = &[
+    "OPT_LEVEL",
+    "CODEGEN_UNITS",
+    "DEBUG_ASSERTIONS",
+    "OVERFLOW_CHECKS",
+    "SPLIT_DEBUGINFO",
+    "TRIM_PATHS",
+    "INCREMENTAL",
+    "DEBUG",
+    "STRIP",
+    "PANIC",
+    "RPATH",
+    "LTO",
+];
+
+/// 配置值的一处来源：`kind` 为 `file`、`env` 或 `env-fallback`，`source` 为文件路径或变量名
+#[derive(Debug, Serialize, Clone)]
+pub struct SettingSource {
+    pub kind: String,
+    pub source: String,
+    pub value: toml::Value,
+}
+
+/// 单个键的解释：`sources` 按优先级从高到低列出所有定义，`merged` 表示多处列表被拼接
+#[derive(Debug, Serialize, Clone)]
+pub struct ExplainedSetting {
+    pub key: String,
+    pub value: toml::Value,
+    pub origin: SettingSource,
+    pub sources: Vec<SettingSource>,
+    pub merged: bool,
+    pub overrides_app_config: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigFileInfo {
+    pub path: String,
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExplainReport {
+    pub config_path: String,
+    pub app_config_loaded: bool,
+    pub files: Vec<ConfigFileInfo>,
+    pub settings: Vec<ExplainedSetting>,
+}
+
+fn cargo_home() -> PathBuf {
+    match std::env::var("CARGO_HOME") {
+        Ok(home) if !home.trim().is_empty() => config::expand_path(&home),
+        _ => Path::new(&config::get_home_dir()).join(".cargo"),
+    }
+}
+
+fn dir_config_file(cargo_dir: &Path) -> Option<PathBuf> {
+    ["config.toml", "config"]
+        .iter()
+        .map(|name| cargo_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+// 与 cargo 相同的查找顺序：从项目目录逐级向上，最后是 CARGO_HOME；返回优先级从高到低
+fn discover_files(project_dir: Option<&str>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    if let Some(dir) = project_dir {
+        let start = config::expand_path(dir);
+        for ancestor in start.ancestors() {
+            if let Some(path) = dir_config_file(&ancestor.join(".cargo")) {
+                files.push(path);
+            }
+        }
+    }
+    if let Some(path) = dir_config_file(&cargo_home()) {
+        files.push(path);
+    }
+    let mut seen = Vec::new();
+    files.retain(|path| {
+        let key = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+    files
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn env_segment(raw: &str) -> String {
+    raw.to_lowercase().replace('_', "-")
+}
+
+// 三元组中的下划线无法与连字符区分，只能按惯例还原 x86_64
+fn target_segment(raw: &str) -> String {
+    env_segment(raw).replace("x86-64", "x86_64")
+}
+
+fn split_field<'a>(rest: &'a str, fields: &[&'a str]) -> Option<(&'a str, &'a str)> {
+    fields.iter().find_map(|field| {
+        rest.strip_suffix(field)
+            .and_then(|name| name.strip_suffix('_'))
+            .filter(|name| !name.is_empty())
+            .map(|name| (name, *field))
+    })
+}
+
+// 无法通过已知键反查时，按 registries / target / profile / alias 的命名规则推断
+fn guess_env_key(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("CARGO_")?;
+    if let Some(rest) = rest.strip_prefix("REGISTRIES_") {
+        let (registry, field) = split_field(rest, REGISTRY_FIELDS)?;
+        return Some(format!("registries.{}.{}", env_segment(registry), env_segment(field)));
+    }
+    if let Some(rest) = rest.strip_prefix("TARGET_") {
+        let (triple, field) = split_field(rest, TARGET_FIELDS)?;
+        return Some(format!("target.{}.{}", target_segment(triple), env_segment(field)));
+    }
+    if let Some(rest) = rest.strip_prefix("PROFILE_") {
+        let (profile, field) = split_field(rest, PROFILE_FIELDS)?;
+        return Some(format!("profile.{}.{}", env_segment(profile), env_segment(field)));
+    }
+    if let Some(alias) = rest.strip_prefix("ALIAS_") {
+        return Some(config::join_key_path("alias", &alias.to_lowercase()));
+    }
+    None
+}
+
+// 已知键（含配置文件中出现的键）的变量名到键路径的映射
+fn known_env_keys<'a>(extra: impl Iterator<Item = &'a String>) -> HashMap<String, String> {
+    let mut known: HashMap<String, String> = KNOWN_KEYS
+        .iter()
+        .map(|key| (config::env_var_name(key), key.to_string()))
+        .collect();
+    for key in extra {
+        known.insert(config::env_var_name(key), key.clone());
+    }
+    known
+}
+
+/// 环境变量对应的配置键；RUSTFLAGS 等特殊变量优先，其次已知键，最后按命名规则推断
+fn env_config_key(name: &str, known: &HashMap<String, String>) -> Option<String> {
+    SPECIAL_ENV
+        .iter()
+        .find(|(var, _)| *var == name)
+        .map(|(_, key)| key.to_string())
+        .or_else(|| known.get(name).cloned())
+        .or_else(|| guess_env_key(name))
+}
+
+fn is_list_key(key: &str) -> bool {
+    config::split_key_path(key)
+        .last()
+        .is_some_and(|last| LIST_KEYS.contains(&last.as_str()))
+}
+
+fn env_value(key: &str, var: &str, raw: &str) -> toml::Value {
+    if var.starts_with("CARGO_ENCODED_") {
+        let items = raw.split('\u{1f}').filter(|s| !s.is_empty());
+        return toml::Value::Array(items.map(|s| toml::Value::String(s.to_string())).collect());
+    }
+    if is_list_key(key) {
+        let items = raw.split_whitespace();
+        return toml::Value::Array(items.map(|s| toml::Value::String(s.to_string())).collect());
+    }
+    if let Ok(flag) = raw.parse::<bool>() {
+        return toml::Value::Boolean(flag);
+    }
+    if let Ok(number) = raw.parse::<i64>() {
+        return toml::Value::Integer(number);
+    }
+    toml::Value::String(raw.to_string())
+}
+
+fn mask(key: &str, value: toml::Value) -> toml::Value {
+    if config::is_secret_key(key) {
+        toml::Value::String("********".to_string())
+    } else {
+        value
+    }
+}
+
+fn merge_lists(sources: &[SettingSource]) -> toml::Value {
+    let items = sources
+        .iter()
+        .rev()
+        .filter_map(|source| source.value.as_array())
+        .flatten()
+        .cloned()
+        .collect();
+    toml::Value::Array(items)
+}
+
+fn explain_key(key: &str, mut sources: Vec<SettingSource>, app_path: &Path) -> Option<ExplainedSetting> {
+    // env-fallback 只在没有其他来源时生效
+    if sources.iter().any(|s| s.kind != "env-fallback") {
+        sources.retain(|s| s.kind != "env-fallback");
+    }
+    let origin = sources.first()?.clone();
+    // RUSTFLAGS 等特殊变量整体替换配置值，不参与列表拼接
+    let replaced = SPECIAL_ENV.iter().any(|(var, _)| *var == origin.source);
+    let lists = sources.iter().filter(|s| s.value.is_array()).count();
+    let merged = !replaced && lists > 1 && lists == sources.len();
+    let value = if merged { merge_lists(&sources) } else { origin.value.clone() };
+
+    let app_source = app_path.to_string_lossy();
+    let app_defined = sources.iter().any(|s| s.kind == "file" && s.source == app_source);
+    let overrides_app_config = app_defined && origin.source != app_source && !merged;
+
+    Some(ExplainedSetting {
+        key: key.to_string(),
+        value: mask(key, value),
+        origin: SettingSource {
+            value: mask(key, origin.value.clone()),
+            ..origin
+        },
+        sources: sources
+            .into_iter()
+            .map(|s| SettingSource {
+                value: mask(key, s.value.clone()),
+                ..s
+            })
+            .collect(),
+        merged,
+        overrides_app_config,
+    })
+}
+
+/// 解释每个生效配置项的取值与来源；`project_dir` 为空时只考虑 CARGO_HOME 和环境变量，
+/// `key` 非空时只返回该键
+pub fn explain_config(
+    config_path: Option<&str>,
+    project_dir: Option<&str>,
+    key: Option<&str>,
+) -> AppResult<ExplainReport> {
+    let app_path = config::resolve_config_path(config_path);
+    let mut files = discover_files(project_dir);
+    let app_config_loaded = files.iter().any(|path| same_file(path, &app_path));
+    if let Some(path) = files.iter_mut().find(|path| same_file(path, &app_path)) {
+        *path = app_path.clone();
+    }
+
+    // 高优先级来源先放入，后续来源依次追加在后面
+    let mut sources: BTreeMap<String, Vec<SettingSource>> = BTreeMap::new();
+    let env: HashMap<String, String> = std::env::vars().collect();
+
+    for (var, target) in SPECIAL_ENV {
+        if let Some(raw) = env.get(*var).filter(|raw| !raw.is_empty()) {
+            let entry = sources.entry(target.to_string()).or_default();
+            // 同一键的特殊变量只取优先级最高的一个
+            if entry.is_empty() {
+                entry.push(SettingSource {
+                    kind: "env".to_string(),
+                    source: var.to_string(),
+                    value: env_value(target, var, raw),
+                });
+            }
+        }
+    }
+
+    let mut file_infos = Vec::new();
+    let mut file_values = Vec::new();
+    for path in &files {
+        let parsed = fs::read_to_string(path)
+            .map_err(|e| AppError::io("读取配置文件失败", e))
+            .and_then(|content| config::parse_config_value(&content))
+            .map_err(|e| e.with_path(path));
+        match parsed {
+            Ok(value) => {
+                file_values.push((path, config::flatten_config_value(&value)));
+                file_infos.push(ConfigFileInfo {
+                    path: path.to_string_lossy().to_string(),
+                    error: None,
+                });
+            }
+            Err(err) => file_infos.push(ConfigFileInfo {
+                path: path.to_string_lossy().to_string(),
+                error: Some(err),
+            }),
+        }
+    }
+
+    let known = known_env_keys(file_values.iter().flat_map(|(_, flat)| flat.keys()));
+    let mut env_vars: Vec<(&String, &String)> = env
+        .iter()
+        .filter(|(name, _)| name.starts_with("CARGO_"))
+        .collect();
+    env_vars.sort();
+    for (var, raw) in env_vars {
+        if SPECIAL_ENV.iter().any(|(special, _)| special == var) {
+            continue;
+        }
+        let Some(target) = env_config_key(var, &known) else {
+            continue;
+        };
+        sources.entry(target.clone()).or_default().push(SettingSource {
+            kind: "env".to_string(),
+            value: env_value(&target, var, raw),
+            source: var.clone(),
+        });
+    }
+
+    for (path, flat) in &file_values {
+        for (key, value) in flat {
+            sources.entry(key.clone()).or_default().push(SettingSource {
+                kind: "file".to_string(),
+                source: path.to_string_lossy().to_string(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    for var in PROXY_FALLBACK_ENV {
+        if let Some(raw) = env.get(*var).filter(|raw| !raw.is_empty()) {
+            sources.entry("http.proxy".to_string()).or_default().push(SettingSource {
+                kind: "env-fallback".to_string(),
+                source: var.to_string(),
+                value: toml::Value::String(raw.clone()),
+            });
+            break;
+        }
+    }
+
+    let settings = sources
+        .into_iter()
+        .filter(|(name, _)| key.is_none_or(|wanted| wanted == name))
+        .filter_map(|(name, list)| explain_key(&name, list, &app_path))
+        .collect();
+
+    Ok(ExplainReport {
+        config_path: app_path.to_string_lossy().to_string(),
+        app_config_loaded,
+        files: file_infos,
+        settings,
+    })
+}
+
+fn try_config(table: &toml::Table) -> bool {
+    toml::Value::Table(table.clone()).try_into::<CargoConfig>().is_ok()
+}
+
+/// 把环境变量转换为配置，用于从进程环境或 dotenv 导入；同一键按 cargo 的优先级只取一个变量。
+/// `report_unrelated` 为真时，非 cargo 变量也记入 skipped
+pub fn config_from_env_vars(
+    vars: &[(String, String)],
+    report_unrelated: bool,
+) -> (CargoConfig, Vec<SkippedKey>) {
+    let known = known_env_keys(std::iter::empty());
+    let rank = |name: &str| {
+        SPECIAL_ENV
+            .iter()
+            .position(|(var, _)| *var == name)
+            .unwrap_or(SPECIAL_ENV.len())
+    };
+    let mut ordered: Vec<&(String, String)> = vars.iter().collect();
+    ordered.sort_by(|a, b| rank(&a.0).cmp(&rank(&b.0)).then_with(|| a.0.cmp(&b.0)));
+
+    let mut table = toml::Table::new();
+    let mut owners: HashMap<String, String> = HashMap::new();
+    let mut skipped = Vec::new();
+    for (name, raw) in ordered {
+        let skip = |reason: String| SkippedKey {
+            key: name.clone(),
+            reason,
+        };
+        let related = name.starts_with("CARGO_") || rank(name) < SPECIAL_ENV.len();
+        if !related {
+            if report_unrelated {
+                skipped.push(skip("不是 cargo 配置变量".to_string()));
+            }
+            continue;
+        }
+        let Some(key) = env_config_key(name, &known) else {
+            skipped.push(skip("无法识别对应的配置项".to_string()));
+            continue;
+        };
+        if let Some(owner) = owners.get(&key) {
+            skipped.push(skip(format!("{} 已由 {} 设置", key, owner)));
+            continue;
+        }
+        // 推断的类型与配置字段不符时退回字符串，仍不符则跳过
+        let candidates = [env_value(&key, name, raw), toml::Value::String(raw.clone())];
+        let accepted = candidates.into_iter().any(|value| {
+            let mut next = table.clone();
+            if config::insert_key_path(&mut next, &key, value) && try_config(&next) {
+                table = next;
+                true
+            } else {
+                false
+            }
+        });
+        if accepted {
+            owners.insert(key, name.clone());
+        } else {
+            skipped.push(skip(format!("值的类型与 {} 不符", key)));
+        }
+    }
+    let config = toml::Value::Table(table).try_into().unwrap_or_default();
+    (config, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn skipped_reason<'a>(skipped: &'a [SkippedKey], name: &str) -> Option<&'a str> {
+        skipped.iter().find(|s| s.key == name).map(|s| s.reason.as_str())
+    }
+
+    #[test]
+    fn encoded_rustflags_win_over_rustflags_and_build_rustflags() {
+        let (config, skipped) = config_from_env_vars(
+            &vars(&[
+                ("CARGO_BUILD_RUSTFLAGS", "-D warnings"),
+                ("RUSTFLAGS", "-C target-cpu=native"),
+                ("CARGO_ENCODED_RUSTFLAGS", "-C\u{1f}link-arg=a b"),
+            ]),
+            false,
+        );
+        assert_eq!(config.build.unwrap().rustflags.unwrap(), vec!["-C", "link-arg=a b"]);
+        assert_eq!(
+            skipped_reason(&skipped, "RUSTFLAGS"),
+            Some("build.rustflags 已由 CARGO_ENCODED_RUSTFLAGS 设置")
+        );
+        assert_eq!(
+            skipped_reason(&skipped, "CARGO_BUILD_RUSTFLAGS"),
+            Some("build.rustflags 已由 CARGO_ENCODED_RUSTFLAGS 设置")
+        );
+    }
+
+    #[test]
+    fn rustflags_win_over_build_rustflags() {
+        let (config, skipped) = config_from_env_vars(
+            &vars(&[("CARGO_BUILD_RUSTFLAGS", "-D warnings"), ("RUSTFLAGS", "-C  opt-level=3 ")]),
+            false,
+        );
+        assert_eq!(config.build.unwrap().rustflags.unwrap(), vec!["-C", "opt-level=3"]);
+        assert_eq!(
+            skipped_reason(&skipped, "CARGO_BUILD_RUSTFLAGS"),
+            Some("build.rustflags 已由 RUSTFLAGS 设置")
+        );
+    }
+
+    #[test]
+    fn values_fall_back_to_strings_when_the_type_does_not_fit() {
+        let (config, skipped) = config_from_env_vars(
+            &vars(&[
+                ("CARGO_BUILD_TARGET", "1234"),
+                ("CARGO_ALIAS_T", "true"),
+                ("CARGO_BUILD_JOBS", "many"),
+                ("CARGO_NET_OFFLINE", "true"),
+                ("CARGO_HTTP_TIMEOUT", "30"),
+            ]),
+            false,
+        );
+        let build = config.build.unwrap();
+        assert_eq!(build.target.as_deref(), Some("1234"));
+        assert_eq!(build.jobs, None);
+        assert_eq!(config.alias.unwrap()["t"], "true");
+        assert_eq!(config.net.unwrap().offline, Some(true));
+        assert_eq!(config.http.unwrap().timeout, Some(30));
+        assert_eq!(skipped_reason(&skipped, "CARGO_BUILD_JOBS"), Some("值的类型与 build.jobs 不符"));
+    }
+
+    #[test]
+    fn unrelated_and_unknown_variables_are_reported() {
+        let input = vars(&[("PATH", "/usr/bin"), ("CARGO_SOMETHING_ELSE", "1")]);
+        let (_, skipped) = config_from_env_vars(&input, true);
+        assert_eq!(skipped_reason(&skipped, "PATH"), Some("不是 cargo 配置变量"));
+        assert_eq!(
+            skipped_reason(&skipped, "CARGO_SOMETHING_ELSE"),
+            Some("无法识别对应的配置项")
+        );
+
+        let (_, skipped) = config_from_env_vars(&input, false);
+        assert!(skipped_reason(&skipped, "PATH").is_none());
+    }
+
+    #[test]
+    fn env_keys_are_guessed_from_naming_rules() {
+        let cases = [
+            ("CARGO_REGISTRIES_MY_REG_TOKEN", Some("registries.my-reg.token")),
+            ("CARGO_REGISTRIES_MY_REG_CREDENTIAL_PROVIDER", Some("registries.my-reg.credential-provider")),
+            ("CARGO_REGISTRIES_TOKEN", None),
+            ("CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_LINKER", Some("target.x86_64-unknown-linux-gnu.linker")),
+            ("CARGO_TARGET_X86_64_PC_WINDOWS_MSVC_RUSTFLAGS", Some("target.x86_64-pc-windows-msvc.rustflags")),
+            ("CARGO_TARGET_AARCH64_APPLE_DARWIN_RUNNER", Some("target.aarch64-apple-darwin.runner")),
+            ("CARGO_PROFILE_RELEASE_OPT_LEVEL", Some("profile.release.opt-level")),
+            ("CARGO_PROFILE_RELEASE_DEBUG_ASSERTIONS", Some("profile.release.debug-assertions")),
+            ("CARGO_PROFILE_DEV_DEBUG", Some("profile.dev.debug")),
+            ("CARGO_PROFILE_MY_PROFILE_LTO", Some("profile.my-profile.lto")),
+            ("CARGO_ALIAS_B", Some("alias.b")),
+            ("CARGO_BUILD_JOBS", None),
+            ("RUSTFLAGS", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(guess_env_key(name).as_deref(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn env_values_are_typed_by_key_and_variable() {
+        let list = |items: &[&str]| {
+            toml::Value::Array(items.iter().map(|s| toml::Value::String(s.to_string())).collect())
+        };
+        assert_eq!(
+            env_value("build.rustflags", "CARGO_ENCODED_RUSTFLAGS", "-C\u{1f}\u{1f}a b"),
+            list(&["-C", "a b"])
+        );
+        assert_eq!(env_value("build.rustflags", "RUSTFLAGS", " -C  a "), list(&["-C", "a"]));
+        assert_eq!(
+            env_value(
+                "target.x86_64-unknown-linux-gnu.rustflags",
+                "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUSTFLAGS",
+                "-C a"
+            ),
+            list(&["-C", "a"])
+        );
+        assert_eq!(env_value("net.offline", "CARGO_NET_OFFLINE", "true"), toml::Value::Boolean(true));
+        assert_eq!(env_value("build.jobs", "CARGO_BUILD_JOBS", "8"), toml::Value::Integer(8));
+        assert_eq!(
+            env_value("build.target", "CARGO_BUILD_TARGET", "wasm32-unknown-unknown"),
+            toml::Value::String("wasm32-unknown-unknown".into())
+        );
+    }
+
+    fn source(kind: &str, source: &str, value: toml::Value) -> SettingSource {
+        SettingSource {
+            kind: kind.to_string(),
+            source: source.to_string(),
+            value,
+        }
+    }
+
+    fn flags(items: &[&str]) -> toml::Value {
+        toml::Value::Array(items.iter().map(|s| toml::Value::String(s.to_string())).collect())
+    }
+
+    const APP: &str = "/home/u/.cargo/config.toml";
+    const PROJECT: &str = "/work/app/.cargo/config.toml";
+
+    #[test]
+    fn lists_from_several_files_are_merged() {
+        let setting = explain_key(
+            "build.rustflags",
+            vec![
+                source("file", PROJECT, flags(&["-C", "a"])),
+                source("file", APP, flags(&["-D", "b"])),
+            ],
+            Path::new(APP),
+        )
+        .unwrap();
+        assert!(setting.merged);
+        assert_eq!(setting.value, flags(&["-D", "b", "-C", "a"]));
+        assert_eq!(setting.origin.source, PROJECT);
+        assert!(!setting.overrides_app_config);
+    }
+
+    #[test]
+    fn special_env_vars_replace_file_lists() {
+        let setting = explain_key(
+            "build.rustflags",
+            vec![
+                source("env", "RUSTFLAGS", flags(&["-C", "x"])),
+                source("file", APP, flags(&["-D", "b"])),
+            ],
+            Path::new(APP),
+        )
+        .unwrap();
+        assert!(!setting.merged);
+        assert_eq!(setting.value, flags(&["-C", "x"]));
+        assert!(setting.overrides_app_config);
+    }
+
+    #[test]
+    fn higher_priority_sources_override_the_app_config() {
+        let jobs = |n| toml::Value::Integer(n);
+        let setting = explain_key(
+            "build.jobs",
+            vec![source("file", PROJECT, jobs(2)), source("file", APP, jobs(8))],
+            Path::new(APP),
+        )
+        .unwrap();
+        assert!(!setting.merged);
+        assert_eq!(setting.value, jobs(2));
+        assert!(setting.overrides_app_config);
+
+        let setting =
+            explain_key("build.jobs", vec![source("file", APP, jobs(8))], Path::new(APP)).unwrap();
+        assert!(!setting.overrides_app_config);
+
+        let setting =
+            explain_key("build.jobs", vec![source("file", PROJECT, jobs(2))], Path::new(APP)).unwrap();
+        assert!(!setting.overrides_app_config);
+        assert!(explain_key("build.jobs", Vec::new(), Path::new(APP)).is_none());
+    }
+
+    #[test]
+    fn proxy_fallback_only_applies_without_other_sources() {
+        let proxy = |url: &str| toml::Value::String(url.to_string());
+        let setting = explain_key(
+            "http.proxy",
+            vec![
+                source("file", APP, proxy("http://config:1")),
+                source("env-fallback", "https_proxy", proxy("http://env:1")),
+            ],
+            Path::new(APP),
+        )
+        .unwrap();
+        assert_eq!(setting.sources.len(), 1);
+        assert_eq!(setting.value, proxy("http://config:1"));
+
+        let setting = explain_key(
+            "http.proxy",
+            vec![source("env-fallback", "https_proxy", proxy("http://env:1"))],
+            Path::new(APP),
+        )
+        .unwrap();
+        assert_eq!(setting.origin.kind, "env-fallback");
+    }
+
+    #[test]
+    fn tokens_are_masked_everywhere() {
+        let token = toml::Value::String("secret".into());
+        let setting = explain_key(
+            "registries.my-reg.token",
+            vec![
+                source("env", "CARGO_REGISTRIES_MY_REG_TOKEN", token.clone()),
+                source("file", APP, token),
+            ],
+            Path::new(APP),
+        )
+        .unwrap();
+        let masked = toml::Value::String("********".into());
+        assert_eq!(setting.value, masked);
+        assert_eq!(setting.origin.value, masked);
+        assert!(setting.sources.iter().all(|s| s.value == masked));
+    }
+}
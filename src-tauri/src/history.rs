@@ -0,0 +1,286 @@
+use crate::config::{self, CargoConfig, KeyChange};
+use crate::error::{AppError, AppResult};
+use crate::{create_hidden_command, decode_command_output};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const HISTORY_FILE: &str = "config.toml";
+const GIT_AUTHOR: [&str; 6] = [
+    "-c",
+    "user.name=Cargo Assistant",
+    "-c",
+    "user.email=cargo-assistant@localhost",
+    "-c",
+    "commit.gpgsign=false",
+];
+
+type WarningListener = Box<dyn Fn(&AppError) + Send + 'static>;
+
+static WARNING_LISTENER: Mutex<Option<WarningListener>> = Mutex::new(None);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryStatus {
+    pub enabled: bool,
+    pub initialized: bool,
+    pub repo: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub short_id: String,
+    pub subject: String,
+    pub body: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryDiff {
+    pub entry: HistoryEntry,
+    pub patch: String,
+    pub changes: Vec<KeyChange>,
+}
+
+pub fn get_history_repo(config_path: Option<&str>) -> PathBuf {
+    config::get_backup_dir(config_path).join("history")
+}
+
+fn run_git(repo: &Path, args: &[&str]) -> AppResult<String> {
+    let output = create_hidden_command("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| AppError::io("无法运行 git", e))?;
+    if output.status.success() {
+        Ok(decode_command_output(&output.stdout))
+    } else {
+        Err(AppError::command("git 命令执行失败", decode_command_output(&output.stderr)))
+    }
+}
+
+fn has_commits(repo: &Path) -> bool {
+    run_git(repo, &["rev-parse", "--verify", "-q", "HEAD"]).is_ok()
+}
+
+fn ensure_repo(repo: &Path) -> AppResult<()> {
+    if repo.join(".git").exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(repo).map_err(|e| AppError::io("创建历史目录失败", e))?;
+    run_git(repo, &["init", "-q"])?;
+    Ok(())
+}
+
+// 只接受提交哈希，避免把用户输入当作 git 参数或引用表达式
+fn validate_revision(id: &str) -> AppResult<&str> {
+    let id = id.trim();
+    let valid = (4..=64).contains(&id.len()) && id.chars().all(|ch| ch.is_ascii_hexdigit());
+    if valid {
+        Ok(id)
+    } else {
+        Err(AppError::invalid("无效的历史版本"))
+    }
+}
+
+pub(crate) fn build_commit_message(changes: &[KeyChange], initial: bool) -> (String, String) {
+    if initial {
+        return ("Initial config snapshot".to_string(), String::new());
+    }
+    if changes.is_empty() {
+        return ("Reformat config".to_string(), String::new());
+    }
+
+    let shown: Vec<&str> = changes.iter().take(3).map(|c| c.key.as_str()).collect();
+    let mut subject = format!("Update {}", shown.join(", "));
+    if changes.len() > shown.len() {
+        subject.push_str(&format!(" and {} more", changes.len() - shown.len()));
+    }
+    let body = changes
+        .iter()
+        .map(|c| format!("{}: {}", c.kind, c.key))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (subject, body)
+}
+
+fn parse_log(output: &str) -> Vec<HistoryEntry> {
+    output
+        .split('\u{1e}')
+        .filter_map(|record| {
+            let mut fields = record.trim_matches('\n').split('\u{1f}');
+            let id = fields.next()?.trim().to_string();
+            if id.is_empty() {
+                return None;
+            }
+            Some(HistoryEntry {
+                id,
+                short_id: fields.next().unwrap_or_default().to_string(),
+                timestamp: fields.next().and_then(|v| v.parse().ok()).unwrap_or(0),
+                subject: fields.next().unwrap_or_default().to_string(),
+                body: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn read_log(repo: &Path, args: &[&str]) -> AppResult<Vec<HistoryEntry>> {
+    let mut full = vec!["log", "--format=%H%x1f%h%x1f%ct%x1f%s%x1f%b%x1e"];
+    full.extend_from_slice(args);
+    let output = run_git(repo, &full)?;
+    Ok(parse_log(&output))
+}
+
+// 文件内容按原始字节读取，run_git 会去掉首尾空白
+fn show_file(repo: &Path, revision: &str) -> AppResult<String> {
+    let spec = format!("{}:{}", revision, HISTORY_FILE);
+    let output = create_hidden_command("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["show", &spec])
+        .output()
+        .map_err(|e| AppError::io("无法运行 git", e))?;
+    if !output.status.success() {
+        return Err(AppError::command("git 命令执行失败", decode_command_output(&output.stderr)));
+    }
+    String::from_utf8(output.stdout).map_err(|_| AppError::invalid("历史版本不是有效的 UTF-8 文本"))
+}
+
+pub fn get_history_status(config_path: Option<&str>) -> HistoryStatus {
+    let repo = get_history_repo(config_path);
+    HistoryStatus {
+        enabled: config::load_app_settings().git_history,
+        initialized: repo.join(".git").exists(),
+        repo: repo.to_string_lossy().to_string(),
+    }
+}
+
+pub fn set_history_enabled(config_path: Option<&str>, enabled: bool) -> AppResult<HistoryStatus> {
+    // 先完成首次提交再保存开关，提交失败时不会留下“已启用但没有仓库”的状态
+    if enabled {
+        record_change(config_path, None)?;
+    }
+    let mut settings = config::load_app_settings();
+    settings.git_history = enabled;
+    config::save_app_settings(&settings)?;
+    Ok(get_history_status(config_path))
+}
+
+/// 把当前配置文件写入历史仓库并提交，内容未变化时返回 `None`
+pub fn record_change(
+    config_path: Option<&str>,
+    message: Option<String>,
+) -> AppResult<Option<HistoryEntry>> {
+    let repo = get_history_repo(config_path);
+    // GUI、命令行和文件监听可能同时提交，读取配置到 git commit 之间必须串行
+    let _lock = config::lock_config(&repo)?;
+    let source = config::resolve_config_path(config_path);
+    let content = if source.exists() {
+        fs::read_to_string(&source).map_err(|e| AppError::io("读取配置文件失败", e))?
+    } else {
+        String::new()
+    };
+
+    ensure_repo(&repo)?;
+    let tracked = repo.join(HISTORY_FILE);
+    let previous = fs::read_to_string(&tracked).unwrap_or_default();
+    let initial = !has_commits(&repo);
+    if !initial && previous == content {
+        return Ok(None);
+    }
+
+    let empty = toml::Value::Table(toml::Table::new());
+    let old_value = config::parse_config_value(&previous).unwrap_or_else(|_| empty.clone());
+    let new_value = config::parse_config_value(&content).unwrap_or(empty);
+    let changes = config::diff_config_values(&old_value, &new_value);
+    let (subject, body) = match message {
+        Some(message) => (message, String::new()),
+        None => build_commit_message(&changes, initial),
+    };
+
+    fs::write(&tracked, &content).map_err(|e| AppError::io("写入历史文件失败", e))?;
+    run_git(&repo, &["add", "--", HISTORY_FILE])?;
+    let mut args: Vec<&str> = GIT_AUTHOR.to_vec();
+    args.extend_from_slice(&["commit", "-q", "-m", &subject]);
+    if !body.is_empty() {
+        args.extend_from_slice(&["-m", &body]);
+    }
+    run_git(&repo, &args)?;
+
+    Ok(read_log(&repo, &["-n", "1"])?.into_iter().next())
+}
+
+pub fn record_if_enabled(config_path: Option<&str>) -> AppResult<Option<HistoryEntry>> {
+    if !config::load_app_settings().git_history {
+        return Ok(None);
+    }
+    record_change(config_path, None)
+}
+
+pub fn set_warning_listener<F>(listener: F)
+where
+    F: Fn(&AppError) + Send + 'static,
+{
+    let mut guard = WARNING_LISTENER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(Box::new(listener));
+}
+
+/// 配置写入成功后提交历史；提交失败不影响本次写入，交给监听器提示（GUI 事件或命令行 stderr）
+pub fn record_after_write(config_path: Option<&str>) {
+    if let Err(err) = record_if_enabled(config_path) {
+        let guard = WARNING_LISTENER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(listener) = guard.as_ref() {
+            listener(&err);
+        }
+    }
+}
+
+pub fn list_history(config_path: Option<&str>, limit: Option<usize>) -> AppResult<Vec<HistoryEntry>> {
+    let repo = get_history_repo(config_path);
+    if !repo.join(".git").exists() || !has_commits(&repo) {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(200).to_string();
+    read_log(&repo, &["-n", &limit, "--", HISTORY_FILE])
+}
+
+pub fn show_history_diff(config_path: Option<&str>, id: String) -> AppResult<HistoryDiff> {
+    let repo = get_history_repo(config_path);
+    let id = validate_revision(&id)?;
+    let entry = read_log(&repo, &["-n", "1", id])?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("历史版本不存在"))?;
+
+    let parent = format!("{}^", entry.id);
+    let old_content = show_file(&repo, &parent).unwrap_or_default();
+    let new_content = show_file(&repo, &entry.id)?;
+    let empty = toml::Value::Table(toml::Table::new());
+    let old_value = config::parse_config_value(&old_content).unwrap_or_else(|_| empty.clone());
+    let new_value = config::parse_config_value(&new_content).unwrap_or(empty);
+
+    let patch = run_git(
+        &repo,
+        &["show", "--format=", "--no-color", &entry.id, "--", HISTORY_FILE],
+    )?;
+    Ok(HistoryDiff {
+        changes: config::diff_config_values(&old_value, &new_value),
+        patch,
+        entry,
+    })
+}
+
+pub fn restore_history(config_path: Option<&str>, id: String) -> AppResult<Option<HistoryEntry>> {
+    let repo = get_history_repo(config_path);
+    let id = validate_revision(&id)?;
+    let content = show_file(&repo, id)?;
+    toml::from_str::<CargoConfig>(&content)
+        .map_err(|e| AppError::parse("历史版本解析失败", &content, &e))?;
+
+    let target = config::resolve_config_path(config_path);
+    config::write_config_file(&target, &content)?;
+    let short = id.chars().take(7).collect::<String>();
+    record_change(config_path, Some(format!("Restore {}", short)))
+}
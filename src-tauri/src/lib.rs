@@ -1,11 +1,42 @@
+pub mod cli;
 mod config;
+mod error;
+mod explain;
+mod history;
+mod merge;
+mod mirrors;
+mod presets;
+mod profiles;
+mod rustup;
+mod toolchain_file;
+mod undo;
+mod watcher;
 
-use config::{AdminStatus, BackupEntry, CargoConfig, RustupEnvStatus, RustupEnvWriteResult};
+use config::{
+    AdminStatus, BackupEntry, BackupSettings, BackupVerifyReport, CargoConfig, ExportFormat,
+    ExportResult, ImportFormat, ImportResult, LoadedConfig, RustupEnvStatus, RustupEnvWriteResult,
+};
+use error::{AppError, AppResult};
+use explain::ExplainReport;
+use history::{HistoryDiff, HistoryEntry, HistoryStatus};
+use merge::{DiskMergeResult, ImportMergeResult, ImportStrategy, MergeChoice};
+use presets::{ApplyPresetResult, OptionLibrary, Preset, PresetInfo, PresetPreview};
+use profiles::{ApplyProfileResult, ConfigProfile, ProfileDiff, ProfileSummary};
 use serde::Serialize;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
+use std::collections::HashMap;
 use std::path::Path;
 use std::process::Command;
+use mirrors::{MirrorCatalog, RustupProbe};
+use tauri::Emitter;
+use toolchain_file::{ToolchainFileInfo, ToolchainSpec};
+use rustup::{
+    ComponentList, ComponentRunResult, DirectoryOverride, RustupRunResult, RustupSettings,
+    RustupSettingsUpdate, TargetList, ToolchainList, ToolchainResolution,
+};
+use undo::{UndoOutcome, UndoStatus};
+use watcher::WatchStatus;
 
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
@@ -38,13 +69,80 @@ fn decode_command_output(bytes: &[u8]) -> String {
 }
 
 #[tauri::command]
-fn get_config(path: Option<String>) -> Result<CargoConfig, String> {
-    config::load_config(path.as_deref())
+fn get_config(path: Option<String>, recover: Option<bool>) -> AppResult<LoadedConfig> {
+    let loaded = if recover.unwrap_or(false) {
+        config::load_config_recovering(path.as_deref())?
+    } else {
+        config::load_config_with_revision(path.as_deref())?
+    };
+    watcher::track(path.as_deref());
+    Ok(loaded)
+}
+
+#[tauri::command]
+async fn save_config(
+    config: CargoConfig,
+    path: Option<String>,
+    revision: Option<String>,
+) -> AppResult<String> {
+    // 等待文件锁、写撤销记录和 git 提交都可能较慢，不能占用主线程
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let before = undo::read_before(path.as_deref());
+        let revision = config::save_config_checked(&config, path.as_deref(), revision.as_deref())?;
+        watcher::mark_saved(path.as_deref());
+        let _ = undo::record_change(path.as_deref(), &before, "save");
+        history::record_after_write(path.as_deref());
+        Ok::<_, AppError>(revision)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
 }
 
 #[tauri::command]
-fn save_config(config: CargoConfig, path: Option<String>) -> Result<(), String> {
-    config::save_config(&config, path.as_deref())
+fn get_undo_status(path: Option<String>) -> UndoStatus {
+    undo::get_undo_status(path.as_deref())
+}
+
+#[tauri::command]
+async fn undo_config_change(path: Option<String>) -> AppResult<UndoOutcome> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let outcome = undo::undo_config_change(path.as_deref())?;
+        watcher::mark_saved(path.as_deref());
+        history::record_after_write(path.as_deref());
+        Ok::<_, AppError>(outcome)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn redo_config_change(path: Option<String>) -> AppResult<UndoOutcome> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let outcome = undo::redo_config_change(path.as_deref())?;
+        watcher::mark_saved(path.as_deref());
+        history::record_after_write(path.as_deref());
+        Ok::<_, AppError>(outcome)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn merge_config(
+    base: CargoConfig,
+    local: CargoConfig,
+    path: Option<String>,
+    resolutions: Option<HashMap<String, MergeChoice>>,
+) -> AppResult<DiskMergeResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        merge::merge_with_disk(&base, &local, path.as_deref(), &resolutions.unwrap_or_default())
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
 }
 
 #[tauri::command]
@@ -60,7 +158,7 @@ fn get_current_target() -> String {
 }
 
 #[tauri::command]
-fn open_config_folder(path: Option<String>) -> Result<(), String> {
+fn open_config_folder(path: Option<String>) -> AppResult<()> {
     let path = config::resolve_config_path(path.as_deref());
     let target = if path.is_dir() {
         path
@@ -74,30 +172,30 @@ fn open_config_folder(path: Option<String>) -> Result<(), String> {
         Command::new("explorer")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
     {
         Command::new("xdg-open")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     Ok(())
 }
 
 #[tauri::command]
-fn open_folder(path: String) -> Result<(), String> {
+fn open_folder(path: String) -> AppResult<()> {
     let target = std::path::PathBuf::from(&path);
     if !target.exists() {
-        return Err("目录不存在".to_string());
+        return Err(AppError::not_found("目录不存在"));
     }
 
     #[cfg(target_os = "windows")]
@@ -105,30 +203,30 @@ fn open_folder(path: String) -> Result<(), String> {
         Command::new("explorer")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
     {
         Command::new("xdg-open")
             .arg(&target)
             .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+            .map_err(|e| AppError::io("打开文件夹失败", e))?;
     }
     Ok(())
 }
 
 #[tauri::command]
-fn open_config_file(path: Option<String>) -> Result<(), String> {
+fn open_config_file(path: Option<String>) -> AppResult<()> {
     let path = config::resolve_config_path(path.as_deref());
     if !path.exists() {
-        return Err("配置文件不存在".to_string());
+        return Err(AppError::not_found("配置文件不存在"));
     }
     #[cfg(target_os = "windows")]
     {
@@ -136,21 +234,21 @@ fn open_config_file(path: Option<String>) -> Result<(), String> {
         create_hidden_command("cmd")
             .args(["/C", "start", "", &path_str])
             .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+            .map_err(|e| AppError::io("打开文件失败", e))?;
     }
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
             .arg(path)
             .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+            .map_err(|e| AppError::io("打开文件失败", e))?;
     }
     #[cfg(all(not(target_os = "windows"), not(target_os = "macos")))]
     {
         Command::new("xdg-open")
             .arg(path)
             .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+            .map_err(|e| AppError::io("打开文件失败", e))?;
     }
     Ok(())
 }
@@ -178,24 +276,47 @@ fn check_file_exists(path: String) -> bool {
 }
 
 #[tauri::command]
-async fn get_admin_status() -> Result<AdminStatus, String> {
+async fn get_admin_status() -> AppResult<AdminStatus> {
     tauri::async_runtime::spawn_blocking(config::get_admin_status)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::task)
 }
 
 #[tauri::command]
-async fn get_rustup_env_status() -> Result<RustupEnvStatus, String> {
+async fn get_rustup_env_status() -> AppResult<RustupEnvStatus> {
     tauri::async_runtime::spawn_blocking(config::get_rustup_env_status)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::task)
+}
+
+/// 写入前校验地址并探测 dist 服务器，`probe` 为 false 时跳过探测
+#[tauri::command]
+async fn set_rustup_env(
+    dist: Option<String>,
+    root: Option<String>,
+    probe: Option<bool>,
+) -> AppResult<RustupEnvWriteResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let check = mirrors::check_rustup_env(dist.as_deref(), root.as_deref(), probe.unwrap_or(true))?;
+        let mut result = config::set_rustup_env(check.dist, check.root);
+        result.warning = check.warning;
+        Ok(result)
+    })
+    .await
+    .map_err(AppError::task)?
+}
+
+#[tauri::command]
+fn get_mirror_catalog() -> MirrorCatalog {
+    mirrors::mirror_catalog()
 }
 
 #[tauri::command]
-async fn set_rustup_env(dist: Option<String>, root: Option<String>) -> Result<RustupEnvWriteResult, String> {
-    tauri::async_runtime::spawn_blocking(move || config::set_rustup_env(dist, root))
+async fn probe_rustup_mirror(dist: String) -> AppResult<RustupProbe> {
+    let result = tauri::async_runtime::spawn_blocking(move || mirrors::probe_rustup_dist(&dist))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::task)??;
+    Ok(result)
 }
 
 #[tauri::command]
@@ -206,90 +327,346 @@ fn get_backup_dir(path: Option<String>) -> String {
 }
 
 #[tauri::command]
-async fn list_backups(path: Option<String>) -> Result<Vec<BackupEntry>, String> {
+fn get_backup_settings(path: Option<String>) -> BackupSettings {
+    config::get_backup_settings(path.as_deref())
+}
+
+#[tauri::command]
+async fn set_backup_root(
+    path: Option<String>,
+    root: Option<String>,
+    migrate: Option<bool>,
+) -> AppResult<BackupSettings> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        config::set_backup_root(path.as_deref(), root, migrate.unwrap_or(true))
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn list_backups(path: Option<String>) -> AppResult<Vec<BackupEntry>> {
     let result = tauri::async_runtime::spawn_blocking(move || config::list_backups(path.as_deref()))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn create_backup(path: Option<String>, label: Option<String>) -> Result<BackupEntry, String> {
+async fn create_backup(path: Option<String>, label: Option<String>) -> AppResult<BackupEntry> {
     let result = tauri::async_runtime::spawn_blocking(move || config::create_backup(path.as_deref(), label))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn restore_backup(path: Option<String>, name: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || config::restore_backup(path.as_deref(), name))
-        .await
-        .map_err(|e| e.to_string())??;
+async fn restore_backup(path: Option<String>, name: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let before = undo::read_before(path.as_deref());
+        config::restore_backup(path.as_deref(), name)?;
+        watcher::mark_saved(path.as_deref());
+        let _ = undo::record_change(path.as_deref(), &before, "restore_backup");
+        history::record_after_write(path.as_deref());
+        Ok::<(), AppError>(())
+    })
+    .await
+    .map_err(AppError::task)??;
     Ok(())
 }
 
 #[tauri::command]
-async fn clear_backups(path: Option<String>) -> Result<usize, String> {
+async fn clear_backups(path: Option<String>) -> AppResult<usize> {
     let result = tauri::async_runtime::spawn_blocking(move || config::clear_backups(path.as_deref()))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn delete_backup(path: Option<String>, name: String) -> Result<(), String> {
+async fn delete_backup(path: Option<String>, name: String) -> AppResult<()> {
     tauri::async_runtime::spawn_blocking(move || config::delete_backup(path.as_deref(), name))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(())
 }
 
 #[tauri::command]
-async fn rename_backup(path: Option<String>, old_name: String, new_name: String) -> Result<(), String> {
+async fn rename_backup(path: Option<String>, old_name: String, new_name: String) -> AppResult<()> {
     tauri::async_runtime::spawn_blocking(move || config::rename_backup(path.as_deref(), old_name, new_name))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(())
 }
 
 #[tauri::command]
-async fn import_config(path: String) -> Result<CargoConfig, String> {
-    let result = tauri::async_runtime::spawn_blocking(move || config::import_config_from_path(&path))
+async fn verify_backups(path: Option<String>, repair: Option<bool>) -> AppResult<BackupVerifyReport> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        config::verify_backups(path.as_deref(), repair.unwrap_or(false))
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+fn get_config_watch() -> WatchStatus {
+    watcher::get_watch_status()
+}
+
+#[tauri::command]
+async fn set_config_watch(
+    path: Option<String>,
+    enabled: bool,
+    interval_minutes: Option<u64>,
+) -> AppResult<WatchStatus> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        watcher::set_config_watch(path, enabled, interval_minutes)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+fn get_history_status(path: Option<String>) -> HistoryStatus {
+    history::get_history_status(path.as_deref())
+}
+
+#[tauri::command]
+async fn set_history_enabled(path: Option<String>, enabled: bool) -> AppResult<HistoryStatus> {
+    let result = tauri::async_runtime::spawn_blocking(move || history::set_history_enabled(path.as_deref(), enabled))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn export_config(config: CargoConfig, path: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || config::export_config_to_path(&config, &path))
+async fn list_history(path: Option<String>, limit: Option<usize>) -> AppResult<Vec<HistoryEntry>> {
+    let result = tauri::async_runtime::spawn_blocking(move || history::list_history(path.as_deref(), limit))
         .await
-        .map_err(|e| e.to_string())??;
-    Ok(())
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn show_history_diff(path: Option<String>, id: String) -> AppResult<HistoryDiff> {
+    let result = tauri::async_runtime::spawn_blocking(move || history::show_history_diff(path.as_deref(), id))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn restore_history(path: Option<String>, id: String) -> AppResult<Option<HistoryEntry>> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let before = undo::read_before(path.as_deref());
+        let entry = history::restore_history(path.as_deref(), id)?;
+        watcher::mark_saved(path.as_deref());
+        let _ = undo::record_change(path.as_deref(), &before, "restore_history");
+        Ok::<_, AppError>(entry)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn list_profiles(path: Option<String>) -> AppResult<Vec<ProfileSummary>> {
+    let result = tauri::async_runtime::spawn_blocking(move || profiles::list_profiles(path.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn save_profile(profile: ConfigProfile) -> AppResult<ConfigProfile> {
+    let result = tauri::async_runtime::spawn_blocking(move || profiles::save_profile(profile))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn create_profile(
+    path: Option<String>,
+    name: String,
+    description: Option<String>,
+    include_rustup: Option<bool>,
+) -> AppResult<ConfigProfile> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        profiles::create_profile_from_config(
+            path.as_deref(),
+            &name,
+            description,
+            include_rustup.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn delete_profile(name: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || profiles::delete_profile(&name))
+        .await
+        .map_err(AppError::task)?
+}
+
+#[tauri::command]
+async fn diff_profile(path: Option<String>, name: String) -> AppResult<ProfileDiff> {
+    let result = tauri::async_runtime::spawn_blocking(move || profiles::diff_profile(path.as_deref(), &name))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn apply_profile(path: Option<String>, name: String) -> AppResult<ApplyProfileResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let before = undo::read_before(path.as_deref());
+        let result = profiles::apply_profile(path.as_deref(), &name)?;
+        watcher::mark_saved(path.as_deref());
+        let _ = undo::record_change(path.as_deref(), &before, "profile");
+        history::record_after_write(path.as_deref());
+        Ok::<_, AppError>(result)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn explain_config(
+    path: Option<String>,
+    project_dir: Option<String>,
+    key: Option<String>,
+) -> AppResult<ExplainReport> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        explain::explain_config(path.as_deref(), project_dir.as_deref(), key.as_deref())
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+fn list_presets() -> Vec<PresetInfo> {
+    presets::list_presets()
+}
+
+#[tauri::command]
+fn get_option_library() -> OptionLibrary {
+    presets::option_library()
+}
+
+#[tauri::command]
+async fn preview_preset(path: Option<String>, id: String) -> AppResult<PresetPreview> {
+    let result = tauri::async_runtime::spawn_blocking(move || presets::preview_preset(path.as_deref(), &id))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn apply_preset(path: Option<String>, id: String) -> AppResult<ApplyPresetResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let before = undo::read_before(path.as_deref());
+        let result = presets::apply_preset(path.as_deref(), &id)?;
+        watcher::mark_saved(path.as_deref());
+        let _ = undo::record_change(path.as_deref(), &before, "preset");
+        history::record_after_write(path.as_deref());
+        Ok::<_, AppError>(result)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn import_preset(file: String, replace: Option<bool>) -> AppResult<Preset> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        presets::import_preset(&file, replace.unwrap_or(false))
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
 }
 
 #[tauri::command]
-async fn preview_config(config: CargoConfig) -> Result<String, String> {
+fn delete_preset(id: String) -> AppResult<()> {
+    presets::delete_preset(&id)
+}
+
+#[tauri::command]
+async fn import_config(path: Option<String>, format: Option<ImportFormat>) -> AppResult<ImportResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || match path {
+        Some(path) => config::import_config_from_path(&path, format),
+        None => Ok(config::import_config_from_env()),
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn export_config(
+    config: CargoConfig,
+    path: String,
+    format: Option<ExportFormat>,
+    include_secrets: Option<bool>,
+) -> AppResult<ExportResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        config::export_config_to_path(&config, &path, format, include_secrets.unwrap_or(false))
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn merge_import(
+    config: CargoConfig,
+    path: Option<String>,
+    format: Option<ImportFormat>,
+    strategy: ImportStrategy,
+    sections: Option<HashMap<String, ImportStrategy>>,
+) -> AppResult<ImportMergeResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let imported = match path {
+            Some(path) => config::import_config_from_path(&path, format)?,
+            None => config::import_config_from_env(),
+        };
+        merge::merge_import(&config, imported, strategy, &sections.unwrap_or_default())
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn preview_config(config: CargoConfig) -> AppResult<String> {
     let result = tauri::async_runtime::spawn_blocking(move || config::serialize_config(&config))
         .await
-        .map_err(|e| e.to_string())??;
+        .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn preview_config_path(path: String) -> Result<String, String> {
+async fn preview_config_path(path: String) -> AppResult<String> {
     let result = tauri::async_runtime::spawn_blocking(move || {
-        let config = config::import_config_from_path(&path)?;
-        config::serialize_config(&config)
+        let imported = config::import_config_from_path(&path, None)?;
+        config::serialize_config(&imported.config)
     })
     .await
-    .map_err(|e| e.to_string())??;
+    .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn install_sccache(_window: tauri::Window) -> Result<(), String> {
+async fn install_sccache(_window: tauri::Window) -> AppResult<()> {
     tauri::async_runtime::spawn_blocking(|| {
         #[cfg(target_os = "windows")]
         let mut cmd = create_hidden_command("powershell");
@@ -301,70 +678,202 @@ async fn install_sccache(_window: tauri::Window) -> Result<(), String> {
         #[cfg(not(target_os = "windows"))]
         cmd.args(["install", "sccache", "--locked"]);
 
-        let output = cmd.output().map_err(|e| e.to_string())?;
+        let output = cmd.output().map_err(|e| AppError::io("无法运行 cargo", e))?;
         if output.status.success() {
             Ok(())
         } else {
-            Err(decode_command_output(&output.stderr))
+            Err(AppError::command("安装 sccache 失败", decode_command_output(&output.stderr)))
         }
     })
     .await
-    .map_err(|e| e.to_string())?
+    .map_err(AppError::task)?
 }
 
 #[tauri::command]
-async fn get_installed_targets() -> Result<Vec<String>, String> {
-    let result = tauri::async_runtime::spawn_blocking(|| -> Result<Vec<String>, String> {
-        let mut cmd = if cfg!(target_os = "windows") {
-            create_hidden_command("rustup")
-        } else {
-            Command::new("rustup")
-        };
+async fn get_installed_targets(toolchain: Option<String>) -> AppResult<Vec<String>> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::installed_targets(toolchain.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn install_target(target: String, toolchain: Option<String>) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || rustup::install_target(toolchain.as_deref(), &target))
+        .await
+        .map_err(AppError::task)?
+}
 
-        apply_rustup_env(&mut cmd);
+#[tauri::command]
+async fn remove_target(target: String, toolchain: Option<String>) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || rustup::remove_target(toolchain.as_deref(), &target))
+        .await
+        .map_err(AppError::task)?
+}
 
-        let output = cmd
-            .args(["target", "list", "--installed"])
-            .output()
-            .map_err(|e| e.to_string())?;
+#[tauri::command]
+async fn list_targets(toolchain: Option<String>) -> AppResult<TargetList> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::list_targets(toolchain.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
 
-        if output.status.success() {
-            let stdout = decode_command_output(&output.stdout);
-            let targets = stdout.lines().map(|s| s.trim().to_string()).collect();
-            Ok(targets)
-        } else {
-            Err(decode_command_output(&output.stderr))
-        }
+#[tauri::command]
+async fn list_toolchains(cwd: Option<String>) -> AppResult<ToolchainList> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::list_toolchains(cwd.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn install_toolchain(name: String, profile: Option<String>) -> AppResult<RustupRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        rustup::install_toolchain(&name, profile.as_deref())
     })
     .await
-    .map_err(|e| e.to_string())??;
+    .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn install_target(target: String) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut cmd = if cfg!(target_os = "windows") {
-            create_hidden_command("rustup")
-        } else {
-            Command::new("rustup")
-        };
+async fn uninstall_toolchain(name: String) -> AppResult<RustupRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::uninstall_toolchain(&name))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
 
-        apply_rustup_env(&mut cmd);
+#[tauri::command]
+async fn link_toolchain(name: String, path: String) -> AppResult<RustupRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::link_toolchain(&name, &path))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
 
-        let output = cmd
-            .args(["target", "add", &target])
-            .output()
-            .map_err(|e| e.to_string())?;
+#[tauri::command]
+async fn set_default_toolchain(name: String) -> AppResult<RustupRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::set_default_toolchain(&name))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
 
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(decode_command_output(&output.stderr))
-        }
+#[tauri::command]
+async fn update_toolchains(name: Option<String>) -> AppResult<RustupRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::update_toolchains(name.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn list_components(toolchain: Option<String>) -> AppResult<ComponentList> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::list_components(toolchain.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn add_components(
+    toolchain: Option<String>,
+    components: Vec<String>,
+) -> AppResult<ComponentRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        rustup::add_components(toolchain.as_deref(), &components)
     })
     .await
-    .map_err(|e| e.to_string())?
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn remove_components(
+    toolchain: Option<String>,
+    components: Vec<String>,
+) -> AppResult<ComponentRunResult> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        rustup::remove_components(toolchain.as_deref(), &components)
+    })
+    .await
+    .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_rustup_settings() -> AppResult<RustupSettings> {
+    let result = tauri::async_runtime::spawn_blocking(rustup::read_rustup_settings)
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn update_rustup_settings(update: RustupSettingsUpdate) -> AppResult<RustupSettings> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::update_rustup_settings(&update))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn list_overrides() -> AppResult<Vec<DirectoryOverride>> {
+    let result = tauri::async_runtime::spawn_blocking(rustup::list_overrides)
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn set_override(dir: String, toolchain: String) -> AppResult<Vec<DirectoryOverride>> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::set_override(&dir, &toolchain))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+/// `dir` 为空时清理所有指向已删除目录的覆盖
+#[tauri::command]
+async fn unset_override(dir: Option<String>) -> AppResult<Vec<DirectoryOverride>> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::unset_override(dir.as_deref()))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn resolve_project_toolchain(dir: String) -> AppResult<ToolchainResolution> {
+    let result = tauri::async_runtime::spawn_blocking(move || rustup::resolve_toolchain_for(&dir))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn read_toolchain_file(dir: String) -> AppResult<ToolchainFileInfo> {
+    let result = tauri::async_runtime::spawn_blocking(move || toolchain_file::read_toolchain_file(&dir))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn save_toolchain_file(dir: String, spec: ToolchainSpec) -> AppResult<ToolchainFileInfo> {
+    let result = tauri::async_runtime::spawn_blocking(move || toolchain_file::save_toolchain_file(&dir, spec))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn install_toolchain_file(dir: String) -> AppResult<ToolchainFileInfo> {
+    let result = tauri::async_runtime::spawn_blocking(move || toolchain_file::install_toolchain_file(&dir))
+        .await
+        .map_err(AppError::task)??;
+    Ok(result)
 }
 
 #[derive(Debug, Serialize)]
@@ -393,23 +902,9 @@ fn get_dir_size(path: &Path) -> u64 {
     total_size
 }
 
-fn apply_rustup_env(cmd: &mut Command) {
-    let (dist, root) = config::get_rustup_env_effective();
-    if let Some(dist) = dist {
-        cmd.env("RUSTUP_DIST_SERVER", dist);
-    } else {
-        cmd.env_remove("RUSTUP_DIST_SERVER");
-    }
-    if let Some(root) = root {
-        cmd.env("RUSTUP_UPDATE_ROOT", root);
-    } else {
-        cmd.env_remove("RUSTUP_UPDATE_ROOT");
-    }
-}
-
 #[tauri::command]
-async fn get_cargo_cache_stats() -> Result<CacheStats, String> {
-    let result = tauri::async_runtime::spawn_blocking(|| -> Result<CacheStats, String> {
+async fn get_cargo_cache_stats() -> AppResult<CacheStats> {
+    let result = tauri::async_runtime::spawn_blocking(|| -> AppResult<CacheStats> {
         let home = config::get_home_dir();
         let cargo_home = Path::new(&home).join(".cargo");
 
@@ -435,19 +930,19 @@ async fn get_cargo_cache_stats() -> Result<CacheStats, String> {
         })
     })
     .await
-    .map_err(|e| e.to_string())??;
+    .map_err(AppError::task)??;
     Ok(result)
 }
 
 #[tauri::command]
-async fn clean_cargo_cache(target: String) -> Result<(), String> {
+async fn clean_cargo_cache(target: String) -> AppResult<()> {
     tauri::async_runtime::spawn_blocking(move || {
         let home = config::get_home_dir();
         let cargo_home = Path::new(&home).join(".cargo");
         let path_to_clean = match target.as_str() {
             "registry" => cargo_home.join("registry"),
             "git" => cargo_home.join("git"),
-            _ => return Err("Invalid target".to_string()),
+            _ => return Err(AppError::invalid("无效的缓存类型")),
         };
 
         if !path_to_clean.exists() {
@@ -456,16 +951,16 @@ async fn clean_cargo_cache(target: String) -> Result<(), String> {
 
         // Safety check: ensure we are deleting inside .cargo
         if !path_to_clean.starts_with(&cargo_home) {
-            return Err("Safety check failed: path not in .cargo".to_string());
+            return Err(AppError::invalid("安全检查失败：路径不在 .cargo 目录内"));
         }
 
-        std::fs::remove_dir_all(&path_to_clean).map_err(|e| e.to_string())?;
-        std::fs::create_dir_all(&path_to_clean).map_err(|e| e.to_string())?;
+        std::fs::remove_dir_all(&path_to_clean).map_err(|e| AppError::io("清理缓存失败", e))?;
+        std::fs::create_dir_all(&path_to_clean).map_err(|e| AppError::io("创建目录失败", e))?;
 
         Ok(())
     })
     .await
-    .map_err(|e| e.to_string())?
+    .map_err(AppError::task)?
 }
 
 #[tauri::command]
@@ -478,9 +973,38 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            let handle = app.handle().clone();
+            watcher::set_listener(move |event| {
+                let _ = handle.emit("config-changed", event);
+            });
+            let handle = app.handle().clone();
+            history::set_warning_listener(move |err| {
+                let _ = handle.emit("history-error", err);
+            });
+            watcher::start_from_settings();
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_config,
             save_config,
+            merge_config,
+            get_undo_status,
+            undo_config_change,
+            redo_config_change,
+            list_profiles,
+            save_profile,
+            create_profile,
+            delete_profile,
+            diff_profile,
+            apply_profile,
+            explain_config,
+            list_presets,
+            get_option_library,
+            preview_preset,
+            apply_preset,
+            import_preset,
+            delete_preset,
             get_config_path,
             get_current_target,
             open_config_folder,
@@ -491,20 +1015,53 @@ pub fn run() {
             get_admin_status,
             get_rustup_env_status,
             set_rustup_env,
+            get_mirror_catalog,
+            probe_rustup_mirror,
             get_backup_dir,
+            get_backup_settings,
+            set_backup_root,
             list_backups,
             create_backup,
             restore_backup,
             clear_backups,
             delete_backup,
             rename_backup,
+            verify_backups,
+            get_config_watch,
+            set_config_watch,
+            get_history_status,
+            set_history_enabled,
+            list_history,
+            show_history_diff,
+            restore_history,
             import_config,
+            merge_import,
             export_config,
             preview_config,
             preview_config_path,
             install_sccache,
             get_installed_targets,
             install_target,
+            remove_target,
+            list_targets,
+            list_toolchains,
+            install_toolchain,
+            uninstall_toolchain,
+            link_toolchain,
+            set_default_toolchain,
+            update_toolchains,
+            list_components,
+            add_components,
+            remove_components,
+            get_rustup_settings,
+            update_rustup_settings,
+            list_overrides,
+            set_override,
+            unset_override,
+            resolve_project_toolchain,
+            read_toolchain_file,
+            save_toolchain_file,
+            install_toolchain_file,
             get_cargo_cache_stats,
             clean_cargo_cache,
             exit_app
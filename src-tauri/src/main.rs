@@ -1,6 +1,21 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+#[cfg(target_os = "windows")]
+extern "system" {
+    fn AttachConsole(process_id: u32) -> i32;
+}
+
 fn main() {
+    // 以已知子命令启动时作为命令行工具运行，不创建窗口
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cargo_assistant_lib::cli::is_cli_invocation(&args) {
+        // GUI 子系统的程序默认没有控制台，挂到父进程的控制台上才能输出
+        #[cfg(target_os = "windows")]
+        unsafe {
+            AttachConsole(u32::MAX);
+        }
+        std::process::exit(cargo_assistant_lib::cli::run(args));
+    }
     cargo_assistant_lib::run()
 }
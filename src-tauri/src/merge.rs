@@ -0,0 +1,448 @@
+use crate::config::{self, CargoConfig, ImportResult, SkippedKey};
+use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeChoice {
+    Base,
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MergedKey {
+    pub key: String,
+    pub source: String,
+    pub value: Option<toml::Value>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MergeConflict {
+    pub key: String,
+    pub base: Option<toml::Value>,
+    pub local: Option<toml::Value>,
+    pub remote: Option<toml::Value>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MergeResult {
+    pub merged: CargoConfig,
+    pub auto_merged: Vec<MergedKey>,
+    pub conflicts: Vec<MergeConflict>,
+    pub clean: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiskMergeResult {
+    #[serde(flatten)]
+    pub result: MergeResult,
+    pub remote: CargoConfig,
+    pub revision: String,
+}
+
+/// 以键为粒度做三方合并：双方只有一方改动时自动采用改动方，双方改成不同值时记为冲突。
+/// 冲突键先保留本地值，`resolutions` 中给出选择的键按选择处理且不再计入冲突。
+pub fn merge_configs(
+    base: &CargoConfig,
+    local: &CargoConfig,
+    remote: &CargoConfig,
+    resolutions: &HashMap<String, MergeChoice>,
+) -> AppResult<MergeResult> {
+    let base_flat = config::flatten_config_value(&config::config_to_value(base)?);
+    let local_flat = config::flatten_config_value(&config::config_to_value(local)?);
+    let remote_flat = config::flatten_config_value(&config::config_to_value(remote)?);
+
+    let keys: BTreeSet<&String> = base_flat
+        .keys()
+        .chain(local_flat.keys())
+        .chain(remote_flat.keys())
+        .collect();
+
+    let mut merged = toml::Table::new();
+    let mut auto_merged = Vec::new();
+    let mut conflicts = Vec::new();
+    for key in keys {
+        let b = base_flat.get(key);
+        let l = local_flat.get(key);
+        let r = remote_flat.get(key);
+
+        let (value, source) = if l == r {
+            (l, None)
+        } else if l == b {
+            (r, Some("remote"))
+        } else if r == b {
+            (l, Some("local"))
+        } else {
+            match resolutions.get(key) {
+                Some(MergeChoice::Base) => (b, Some("base")),
+                Some(MergeChoice::Local) => (l, Some("local")),
+                Some(MergeChoice::Remote) => (r, Some("remote")),
+                None => {
+                    conflicts.push(MergeConflict {
+                        key: key.clone(),
+                        base: b.cloned(),
+                        local: l.cloned(),
+                        remote: r.cloned(),
+                    });
+                    (l, None)
+                }
+            }
+        };
+
+        // 一方把键改成表、另一方仍是标量时无法同时保留，按冲突处理
+        let inserted = match value {
+            Some(value) => config::insert_key_path(&mut merged, key, value.clone()),
+            None => true,
+        };
+        if !inserted {
+            if !conflicts.iter().any(|c| &c.key == key) {
+                conflicts.push(MergeConflict {
+                    key: key.clone(),
+                    base: b.cloned(),
+                    local: l.cloned(),
+                    remote: r.cloned(),
+                });
+            }
+        } else if let Some(source) = source {
+            auto_merged.push(MergedKey {
+                key: key.clone(),
+                source: source.to_string(),
+                value: value.cloned(),
+            });
+        }
+    }
+
+    let merged: CargoConfig = toml::Value::Table(merged)
+        .try_into()
+        .map_err(|e| AppError::internal("合并结果无法转换为配置").with_detail(e))?;
+    Ok(MergeResult {
+        clean: conflicts.is_empty(),
+        merged,
+        auto_merged,
+        conflicts,
+    })
+}
+
+/// 把应用内的修改与磁盘上的当前配置合并，返回磁盘版本的修订号供随后保存使用
+pub fn merge_with_disk(
+    base: &CargoConfig,
+    local: &CargoConfig,
+    path: Option<&str>,
+    resolutions: &HashMap<String, MergeChoice>,
+) -> AppResult<DiskMergeResult> {
+    let loaded = config::load_config_with_revision(path)?;
+    let result = merge_configs(base, local, &loaded.config, resolutions)?;
+    Ok(DiskMergeResult {
+        result,
+        remote: loaded.config,
+        revision: loaded.revision,
+    })
+}
+
+/// 导入合并策略：保留现有值、优先导入值，或忽略导入内容
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    KeepExisting,
+    PreferImported,
+    Skip,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportedKey {
+    pub key: String,
+    pub existing: Option<toml::Value>,
+    pub imported: toml::Value,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportMergeResult {
+    pub merged: CargoConfig,
+    pub added: Vec<ImportedKey>,
+    pub overridden: Vec<ImportedKey>,
+    pub skipped: Vec<ImportedKey>,
+    pub import_skipped: Vec<SkippedKey>,
+}
+
+/// 把导入的配置逐键合并到 `base` 上。`sections` 按顶层配置段（如 `source`、`build`）
+/// 覆盖默认策略；现有键为标量而导入键需要表（或反之）时无法合并，计入 skipped
+pub fn merge_import(
+    base: &CargoConfig,
+    imported: ImportResult,
+    strategy: ImportStrategy,
+    sections: &HashMap<String, ImportStrategy>,
+) -> AppResult<ImportMergeResult> {
+    let base_flat = config::flatten_config_value(&config::config_to_value(base)?);
+    let mut merged = match config::config_to_value(base)? {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+
+    let mut added = Vec::new();
+    let mut overridden = Vec::new();
+    let mut skipped = Vec::new();
+    for (key, value) in config::flatten_config_value(&config::config_to_value(&imported.config)?) {
+        let existing = base_flat.get(&key).cloned();
+        if existing.as_ref() == Some(&value) {
+            continue;
+        }
+        let section = config::split_key_path(&key).remove(0);
+        let strategy = sections.get(&section).copied().unwrap_or(strategy);
+        let entry = ImportedKey {
+            key: key.clone(),
+            existing,
+            imported: value.clone(),
+        };
+        let take = match strategy {
+            ImportStrategy::Skip => false,
+            ImportStrategy::KeepExisting => entry.existing.is_none(),
+            ImportStrategy::PreferImported => true,
+        };
+        if take && config::insert_key_path(&mut merged, &key, value) {
+            if entry.existing.is_some() {
+                overridden.push(entry);
+            } else {
+                added.push(entry);
+            }
+        } else {
+            skipped.push(entry);
+        }
+    }
+
+    let merged: CargoConfig = toml::Value::Table(merged)
+        .try_into()
+        .map_err(|e| AppError::invalid("合并结果无法转换为配置").with_detail(e))?;
+    Ok(ImportMergeResult {
+        merged,
+        added,
+        overridden,
+        skipped,
+        import_skipped: imported.skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> CargoConfig {
+        toml::from_str(content).unwrap()
+    }
+
+    fn flat(config: &CargoConfig) -> std::collections::BTreeMap<String, toml::Value> {
+        config::flatten_config_value(&config::config_to_value(config).unwrap())
+    }
+
+    fn merge(base: &str, local: &str, remote: &str) -> MergeResult {
+        merge_configs(&parse(base), &parse(local), &parse(remote), &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn non_overlapping_edits_merge_cleanly() {
+        let base = "[build]\njobs = 4\n[http]\ntimeout = 30\n";
+        let local = "[build]\njobs = 8\n[http]\ntimeout = 30\n";
+        let remote = "[build]\njobs = 4\n[http]\ntimeout = 60\n[alias]\nb = \"build\"\n";
+        let result = merge(base, local, remote);
+
+        assert!(result.clean);
+        let merged = flat(&result.merged);
+        assert_eq!(merged["build.jobs"], toml::Value::Integer(8));
+        assert_eq!(merged["http.timeout"], toml::Value::Integer(60));
+        assert_eq!(merged["alias.b"], toml::Value::String("build".into()));
+        let sources: Vec<(&str, &str)> = result
+            .auto_merged
+            .iter()
+            .map(|k| (k.key.as_str(), k.source.as_str()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![("alias.b", "remote"), ("build.jobs", "local"), ("http.timeout", "remote")]
+        );
+    }
+
+    #[test]
+    fn removal_on_one_side_is_applied() {
+        let result = merge(
+            "[build]\njobs = 4\n[http]\ntimeout = 30\n",
+            "[build]\njobs = 4\n",
+            "[build]\njobs = 4\n[http]\ntimeout = 30\n",
+        );
+        assert!(result.clean);
+        assert!(!flat(&result.merged).contains_key("http.timeout"));
+        assert_eq!(result.auto_merged.len(), 1);
+        assert_eq!(result.auto_merged[0].source, "local");
+        assert_eq!(result.auto_merged[0].value, None);
+    }
+
+    #[test]
+    fn identical_edits_on_both_sides_are_not_reported() {
+        let result = merge("[build]\njobs = 4\n", "[build]\njobs = 8\n", "[build]\njobs = 8\n");
+        assert!(result.clean);
+        assert!(result.auto_merged.is_empty());
+        assert_eq!(flat(&result.merged)["build.jobs"], toml::Value::Integer(8));
+    }
+
+    #[test]
+    fn conflicting_edits_keep_local_value_until_resolved() {
+        let base = parse("[build]\njobs = 4\n");
+        let local = parse("[build]\njobs = 8\n");
+        let remote = parse("[build]\njobs = 2\n");
+
+        let result = merge_configs(&base, &local, &remote, &HashMap::new()).unwrap();
+        assert!(!result.clean);
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.key, "build.jobs");
+        assert_eq!(conflict.base, Some(toml::Value::Integer(4)));
+        assert_eq!(conflict.local, Some(toml::Value::Integer(8)));
+        assert_eq!(conflict.remote, Some(toml::Value::Integer(2)));
+        assert_eq!(flat(&result.merged)["build.jobs"], toml::Value::Integer(8));
+        assert!(result.auto_merged.is_empty());
+
+        for (choice, expected, source) in [
+            (MergeChoice::Base, 4, "base"),
+            (MergeChoice::Local, 8, "local"),
+            (MergeChoice::Remote, 2, "remote"),
+        ] {
+            let resolutions = HashMap::from([("build.jobs".to_string(), choice)]);
+            let result = merge_configs(&base, &local, &remote, &resolutions).unwrap();
+            assert!(result.clean);
+            assert_eq!(flat(&result.merged)["build.jobs"], toml::Value::Integer(expected));
+            assert_eq!(result.auto_merged.len(), 1);
+            assert_eq!(result.auto_merged[0].source, source);
+        }
+    }
+
+    #[test]
+    fn scalar_against_table_is_a_conflict_and_not_auto_merged() {
+        let result = merge(
+            "",
+            "[env]\nFOO = \"local\"\n",
+            "[env]\nFOO = { value = \"remote\", force = true }\n",
+        );
+
+        assert!(!result.clean);
+        let conflict_keys: Vec<&str> = result.conflicts.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(conflict_keys, vec!["env.FOO.force", "env.FOO.value"]);
+        let merged_keys: Vec<&str> = result.auto_merged.iter().map(|k| k.key.as_str()).collect();
+        assert_eq!(merged_keys, vec!["env.FOO"]);
+        assert_eq!(flat(&result.merged)["env.FOO"], toml::Value::String("local".into()));
+    }
+
+    fn import(content: &str) -> ImportResult {
+        ImportResult {
+            config: parse(content),
+            format: config::ImportFormat::Toml,
+            skipped: vec![SkippedKey {
+                key: "unknown".to_string(),
+                reason: "不支持的键".to_string(),
+            }],
+        }
+    }
+
+    fn keys(entries: &[ImportedKey]) -> Vec<&str> {
+        entries.iter().map(|e| e.key.as_str()).collect()
+    }
+
+    const IMPORT_BASE: &str = "[build]\njobs = 4\n[http]\ntimeout = 30\n";
+    const IMPORTED: &str = "[build]\njobs = 8\ntarget = \"x86_64-unknown-linux-gnu\"\n[http]\ntimeout = 30\n";
+
+    #[test]
+    fn keep_existing_only_adds_new_keys() {
+        let result = merge_import(
+            &parse(IMPORT_BASE),
+            import(IMPORTED),
+            ImportStrategy::KeepExisting,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        let merged = flat(&result.merged);
+        assert_eq!(merged["build.jobs"], toml::Value::Integer(4));
+        assert_eq!(merged["build.target"], toml::Value::String("x86_64-unknown-linux-gnu".into()));
+        assert_eq!(keys(&result.added), vec!["build.target"]);
+        assert!(result.overridden.is_empty());
+        assert_eq!(keys(&result.skipped), vec!["build.jobs"]);
+        assert_eq!(result.skipped[0].existing, Some(toml::Value::Integer(4)));
+        assert_eq!(result.skipped[0].imported, toml::Value::Integer(8));
+        // 与现有值相同的键不出现在任何列表中
+        assert!(!keys(&result.skipped).contains(&"http.timeout"));
+        assert_eq!(result.import_skipped.len(), 1);
+        assert_eq!(result.import_skipped[0].key, "unknown");
+    }
+
+    #[test]
+    fn prefer_imported_overrides_existing_keys() {
+        let result = merge_import(
+            &parse(IMPORT_BASE),
+            import(IMPORTED),
+            ImportStrategy::PreferImported,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        let merged = flat(&result.merged);
+        assert_eq!(merged["build.jobs"], toml::Value::Integer(8));
+        assert_eq!(merged["http.timeout"], toml::Value::Integer(30));
+        assert_eq!(keys(&result.added), vec!["build.target"]);
+        assert_eq!(keys(&result.overridden), vec!["build.jobs"]);
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn skip_leaves_base_untouched() {
+        let result = merge_import(
+            &parse(IMPORT_BASE),
+            import(IMPORTED),
+            ImportStrategy::Skip,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(flat(&result.merged), flat(&parse(IMPORT_BASE)));
+        assert!(result.added.is_empty());
+        assert!(result.overridden.is_empty());
+        assert_eq!(keys(&result.skipped), vec!["build.jobs", "build.target"]);
+    }
+
+    #[test]
+    fn section_overrides_replace_default_strategy() {
+        let imported = "[build]\njobs = 8\n[http]\ntimeout = 60\n[alias]\nb = \"build\"\n";
+        let sections = HashMap::from([
+            ("build".to_string(), ImportStrategy::PreferImported),
+            ("alias".to_string(), ImportStrategy::Skip),
+        ]);
+        let result = merge_import(
+            &parse(IMPORT_BASE),
+            import(imported),
+            ImportStrategy::KeepExisting,
+            &sections,
+        )
+        .unwrap();
+
+        let merged = flat(&result.merged);
+        assert_eq!(merged["build.jobs"], toml::Value::Integer(8));
+        assert_eq!(merged["http.timeout"], toml::Value::Integer(30));
+        assert!(!merged.contains_key("alias.b"));
+        assert!(result.added.is_empty());
+        assert_eq!(keys(&result.overridden), vec!["build.jobs"]);
+        assert_eq!(keys(&result.skipped), vec!["alias.b", "http.timeout"]);
+    }
+
+    #[test]
+    fn scalar_against_table_import_is_skipped() {
+        let result = merge_import(
+            &parse("[env]\nFOO = \"bar\"\n"),
+            import("[env]\nFOO = { value = \"baz\" }\n"),
+            ImportStrategy::PreferImported,
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(flat(&result.merged)["env.FOO"], toml::Value::String("bar".into()));
+        assert!(result.added.is_empty());
+        assert_eq!(keys(&result.skipped), vec!["env.FOO.value"]);
+    }
+}
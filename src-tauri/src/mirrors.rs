@@ -0,0 +1,364 @@
+use crate::config::{CargoConfig, SourceEntry};
+use crate::create_hidden_command;
+use crate::error::{AppError, AppResult};
+use serde::Serialize;
+use std::process::Output;
+use std::time::Instant;
+
+// 探测时读取的校验文件，体积很小且所有镜像都会同步
+const PROBE_FILE: &str = "dist/channel-rust-stable.toml.sha256";
+const PROBE_TIMEOUT_SECS: &str = "10";
+
+// 前端通过 get_mirror_catalog 读取这两份列表
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct CratesMirror {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub registry: &'static str,
+    pub replace_with: &'static str,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct RustupMirror {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub dist: &'static str,
+    pub root: &'static str,
+    /// 同一提供方的 crates.io 镜像 id
+    pub crates: &'static str,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct MirrorCatalog {
+    pub crates: &'static [CratesMirror],
+    pub rustup: &'static [RustupMirror],
+}
+
+/// 对 dist 服务器的一次探测结果
+#[derive(Debug, Serialize, Clone)]
+pub struct RustupProbe {
+    pub url: String,
+    pub status: u16,
+    pub elapsed_ms: u64,
+    /// 校验文件中 stable 渠道清单的 sha256
+    pub checksum: String,
+}
+
+pub const CRATES_MIRRORS: &[CratesMirror] = &[
+    CratesMirror {
+        id: "official",
+        name: "Official (crates.io)",
+        registry: "https://github.com/rust-lang/crates.io-index",
+        replace_with: "crates-io",
+    },
+    CratesMirror {
+        id: "ustc",
+        name: "USTC (中科大)",
+        registry: "sparse+https://mirrors.ustc.edu.cn/crates.io-index/",
+        replace_with: "ustc",
+    },
+    CratesMirror {
+        id: "tuna",
+        name: "TUNA (清华大学)",
+        registry: "https://mirrors.tuna.tsinghua.edu.cn/git/crates.io-index.git",
+        replace_with: "tuna",
+    },
+    CratesMirror {
+        id: "sjtu",
+        name: "SJTU (上海交大)",
+        registry: "https://mirrors.sjtug.sjtu.edu.cn/git/crates.io-index",
+        replace_with: "sjtu",
+    },
+    CratesMirror {
+        id: "rsproxy",
+        name: "Rsproxy (字节跳动)",
+        registry: "sparse+https://rsproxy.cn/crates.io-index",
+        replace_with: "rsproxy",
+    },
+    CratesMirror {
+        id: "aliyun",
+        name: "Aliyun (阿里云)",
+        registry: "https://code.aliyun.com/rustcc/crates.io-index.git",
+        replace_with: "aliyun",
+    },
+];
+
+pub const RUSTUP_MIRRORS: &[RustupMirror] = &[
+    RustupMirror { id: "official", name: "Official (官方)", dist: "", root: "", crates: "official" },
+    RustupMirror {
+        id: "ustc",
+        name: "USTC (中科大)",
+        dist: "https://mirrors.ustc.edu.cn/rustup",
+        root: "https://mirrors.ustc.edu.cn/rustup",
+        crates: "ustc",
+    },
+    RustupMirror {
+        id: "tuna",
+        name: "TUNA (清华)",
+        dist: "https://mirrors.tuna.tsinghua.edu.cn/rustup",
+        root: "https://mirrors.tuna.tsinghua.edu.cn/rustup",
+        crates: "tuna",
+    },
+    RustupMirror {
+        id: "sjtu",
+        name: "SJTU (上交)",
+        dist: "https://mirrors.sjtug.sjtu.edu.cn/rust-static",
+        root: "https://mirrors.sjtug.sjtu.edu.cn/rust-static/rustup",
+        crates: "sjtu",
+    },
+    RustupMirror {
+        id: "rsproxy",
+        name: "Rsproxy (字节)",
+        dist: "https://rsproxy.cn/rustup",
+        root: "https://rsproxy.cn/rustup",
+        crates: "rsproxy",
+    },
+];
+
+pub fn find_crates_mirror(id: &str) -> Option<&'static CratesMirror> {
+    CRATES_MIRRORS.iter().find(|mirror| mirror.id == id)
+}
+
+pub fn find_rustup_mirror(id: &str) -> Option<&'static RustupMirror> {
+    RUSTUP_MIRRORS.iter().find(|mirror| mirror.id == id)
+}
+
+pub fn mirror_catalog() -> MirrorCatalog {
+    MirrorCatalog {
+        crates: CRATES_MIRRORS,
+        rustup: RUSTUP_MIRRORS,
+    }
+}
+
+/// 当前配置使用的 crates.io 镜像 id：未替换时为 `official`，不在列表中的替换源为 `custom`
+pub fn current_crates_mirror(config: &CargoConfig) -> &'static str {
+    let replace_with = config
+        .source
+        .as_ref()
+        .and_then(|source| source.get("crates-io"))
+        .and_then(|entry| entry.replace_with.as_deref());
+    match replace_with {
+        None => "official",
+        Some(name) => CRATES_MIRRORS
+            .iter()
+            .find(|mirror| mirror.id != "official" && mirror.replace_with == name)
+            .map(|mirror| mirror.id)
+            .unwrap_or("custom"),
+    }
+}
+
+/// 与前端切换镜像的逻辑相同：写入替换源并移除其他内置镜像的 source 项
+pub fn apply_crates_mirror(config: &mut CargoConfig, id: &str) -> AppResult<()> {
+    let mirror = find_crates_mirror(id)
+        .ok_or_else(|| AppError::not_found("未知的镜像").with_key(id.to_string()))?;
+    let mut source = config.source.take().unwrap_or_default();
+    for other in CRATES_MIRRORS.iter().filter(|m| m.id != "official") {
+        if other.replace_with != mirror.replace_with {
+            source.remove(other.replace_with);
+        }
+    }
+    if mirror.id == "official" {
+        source.remove("crates-io");
+    } else {
+        source.insert(
+            "crates-io".to_string(),
+            SourceEntry {
+                registry: None,
+                replace_with: Some(mirror.replace_with.to_string()),
+            },
+        );
+        source.insert(
+            mirror.replace_with.to_string(),
+            SourceEntry {
+                registry: Some(mirror.registry.to_string()),
+                replace_with: None,
+            },
+        );
+    }
+    config.source = if source.is_empty() { None } else { Some(source) };
+    Ok(())
+}
+
+/// 校验镜像地址：只接受带主机名的 http(s) URL，返回去掉末尾 `/` 的地址
+pub fn validate_mirror_url(url: &str, name: &str) -> AppResult<String> {
+    let url = url.trim().trim_end_matches('/');
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or_default();
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+        && !url
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '`' | '<' | '>' | '\\'));
+    if valid {
+        Ok(url.to_string())
+    } else {
+        Err(AppError::invalid(format!("{} 必须是 http:// 或 https:// 开头的地址", name))
+            .with_key(url.to_string()))
+    }
+}
+
+fn probe_url(dist: &str) -> AppResult<String> {
+    let dist = validate_mirror_url(dist, "RUSTUP_DIST_SERVER")?;
+    Ok(format!("{}/{}", dist, PROBE_FILE))
+}
+
+fn run_curl(url: &str) -> std::io::Result<Output> {
+    create_hidden_command("curl")
+        .args(["-sS", "-L", "--max-time", PROBE_TIMEOUT_SECS, "-w", "\n%{http_code}", url])
+        .output()
+}
+
+/// 通过 curl 读取 dist 服务器上的 stable 校验文件，确认镜像可用
+pub fn probe_rustup_dist(dist: &str) -> AppResult<RustupProbe> {
+    let url = probe_url(dist)?;
+    let started = Instant::now();
+    let output = run_curl(&url).map_err(|e| AppError::io("无法运行 curl", e))?;
+    parse_probe_output(url, started, output)
+}
+
+fn parse_probe_output(url: String, started: Instant, output: Output) -> AppResult<RustupProbe> {
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(AppError::command("无法连接 rustup 镜像", stderr).with_key(url));
+    }
+
+    // -w 把状态码追加在响应体之后的最后一行
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (body, code) = stdout.trim_end().rsplit_once('\n').unwrap_or(("", stdout.trim()));
+    let status: u16 = code.trim().parse().unwrap_or(0);
+    if status != 200 {
+        return Err(AppError::invalid(format!("rustup 镜像返回 HTTP {}", status)).with_key(url));
+    }
+    let checksum = body.split_whitespace().next().unwrap_or_default();
+    if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::invalid("rustup 镜像返回的不是校验文件")
+            .with_key(url)
+            .with_detail(body.chars().take(200).collect::<String>()));
+    }
+    Ok(RustupProbe {
+        url,
+        status,
+        elapsed_ms,
+        checksum: checksum.to_string(),
+    })
+}
+
+/// 写入前检查的结果；`warning` 说明被跳过的探测
+#[derive(Debug, Clone)]
+pub struct RustupEnvCheck {
+    pub dist: Option<String>,
+    pub root: Option<String>,
+    pub probe: Option<RustupProbe>,
+    pub warning: Option<String>,
+}
+
+/// 写入 rustup 环境变量前的检查：校验地址，`probe` 为 true 时探测 dist 服务器
+pub fn check_rustup_env(dist: Option<&str>, root: Option<&str>, probe: bool) -> AppResult<RustupEnvCheck> {
+    // 空值表示清除该变量
+    let validate = |value: Option<&str>, name: &str| match value.map(str::trim) {
+        Some(url) if !url.is_empty() => validate_mirror_url(url, name).map(Some),
+        _ => Ok(None),
+    };
+    let dist = validate(dist, "RUSTUP_DIST_SERVER")?;
+    let root = validate(root, "RUSTUP_UPDATE_ROOT")?;
+    let (probe, warning) = match dist.as_deref() {
+        Some(dist) if probe => {
+            let url = probe_url(dist)?;
+            let started = Instant::now();
+            match run_curl(&url) {
+                Ok(output) => (Some(parse_probe_output(url, started, output)?), None),
+                // 没有 curl 时无法探测，不阻止写入，只提示
+                Err(e) => (None, Some(format!("无法运行 curl，未探测镜像: {}", e))),
+            }
+        }
+        _ => (None, None),
+    };
+    Ok(RustupEnvCheck {
+        dist,
+        root,
+        probe,
+        warning,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // 只应答一次请求的本地 HTTP 服务器，返回 dist 地址
+    fn serve_once(status: &str, body: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn mirror_urls_are_validated() {
+        assert_eq!(
+            validate_mirror_url(" https://rsproxy.cn/rustup/ ", "x").unwrap(),
+            "https://rsproxy.cn/rustup"
+        );
+        assert!(validate_mirror_url("http://127.0.0.1:8080", "x").is_ok());
+        assert!(validate_mirror_url("http://[::1]:8080/dist", "x").is_ok());
+        for bad in ["", "rsproxy.cn", "ftp://rsproxy.cn", "https://", "https://a b", "https://a\"b", "https://a/`x`"] {
+            assert!(validate_mirror_url(bad, "x").is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn probe_reads_checksum() {
+        let checksum = "ab".repeat(32);
+        let dist = serve_once("200 OK", &format!("{}  channel-rust-stable.toml\n", checksum));
+        let probe = probe_rustup_dist(&dist).unwrap();
+        assert_eq!(probe.status, 200);
+        assert_eq!(probe.checksum, checksum);
+        assert!(probe.url.ends_with(PROBE_FILE));
+    }
+
+    #[test]
+    fn probe_rejects_missing_file() {
+        let dist = serve_once("404 Not Found", "not found");
+        let err = probe_rustup_dist(&dist).unwrap_err();
+        assert!(err.message.contains("404"), "{}", err);
+    }
+
+    #[test]
+    fn probe_rejects_non_checksum_body() {
+        let dist = serve_once("200 OK", "<html>captive portal</html>");
+        let err = probe_rustup_dist(&dist).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert!(err.detail.unwrap_or_default().contains("captive portal"));
+    }
+
+    #[test]
+    fn check_without_probe_only_validates() {
+        let check = check_rustup_env(Some("https://rsproxy.cn/"), Some(""), false).unwrap();
+        assert_eq!(check.dist.as_deref(), Some("https://rsproxy.cn"));
+        assert_eq!(check.root, None);
+        assert!(check.probe.is_none() && check.warning.is_none());
+        assert!(check_rustup_env(Some("rsproxy.cn"), None, false).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use crate::config::{self, BackupEntry, CargoConfig, KeyChange, LoadedConfig};
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const PRESETS_DIR: &str = "presets";
+// 预设文件格式版本，高于此版本的文件拒绝导入
+const PRESET_FORMAT: u32 = 1;
+
+const BUILTIN_PRESETS: &[&str] = &[
+    r#"
+id = "fast-debug"
+name = "快速调试构建"
+description = "调试信息只保留行号表，依赖以 opt-level 1 编译，缩短增量编译和链接时间"
+version = "1.0.0"
+
+[config.profile.dev]
+debug = "line-tables-only"
+
+[config.profile.dev.package."*"]
+opt-level = 1
+"#,
+    r#"
+id = "fast-debug-linux"
+name = "Linux 快速链接 (mold)"
+description = "使用 mold 链接器，需要先安装 mold 且 gcc >= 12.1 或 clang"
+version = "1.0.0"
+platforms = ["linux"]
+
+[config.target.'cfg(target_os = "linux")']
+rustflags = ["-C", "link-arg=-fuse-ld=mold"]
+"#,
+    r#"
+id = "small-release"
+name = "最小体积发布"
+description = "按体积优化、开启 LTO、剥离符号并在 panic 时直接终止"
+version = "1.0.0"
+
+[config.profile.release]
+opt-level = "z"
+lto = true
+codegen-units = 1
+panic = "abort"
+strip = true
+"#,
+    r#"
+id = "fast-release"
+name = "最高性能发布"
+description = "完整 LTO、单代码生成单元，并针对本机 CPU 优化（产物不可分发到其他机器）"
+version = "1.0.0"
+
+[config.profile.release]
+opt-level = 3
+lto = "fat"
+codegen-units = 1
+
+[config.build]
+rustflags = ["-Ctarget-cpu=native"]
+"#,
+    r#"
+id = "static-crt-windows"
+name = "Windows 静态 CRT"
+description = "静态链接 MSVC 运行时，产物无需安装 VC++ 运行库"
+version = "1.0.0"
+platforms = ["windows"]
+
+[config.target.x86_64-pc-windows-msvc]
+rustflags = ["-Ctarget-feature=+crt-static"]
+"#,
+    r#"
+id = "sccache"
+name = "sccache 编译缓存"
+description = "通过 sccache 缓存编译结果，需要先安装 sccache"
+version = "1.0.0"
+
+[config.build]
+rustc-wrapper = "sccache"
+"#,
+];
+
+/// 界面下拉框和快捷按钮使用的常用取值；`platforms` 为空表示不限平台
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct PresetOption {
+    pub value: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub platforms: &'static [&'static str],
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct OptionLibrary {
+    pub rustflags: &'static [PresetOption],
+    pub linkers: &'static [PresetOption],
+    pub proxies: &'static [PresetOption],
+}
+
+const fn option(
+    value: &'static str,
+    label: &'static str,
+    description: &'static str,
+    platforms: &'static [&'static str],
+) -> PresetOption {
+    PresetOption { value, label, description, platforms }
+}
+
+const RUSTFLAG_OPTIONS: &[PresetOption] = &[
+    option("-Ctarget-feature=+crt-static", "Static CRT", "静态链接运行时(Windows)", &["windows"]),
+    option("-Cprefer-dynamic", "Prefer Dynamic", "优先动态链接", &[]),
+    option("-Clink-arg=-s", "Strip Symbols", "剥离符号(减小体积)", &[]),
+    option("-Ctarget-cpu=native", "Native CPU", "针对当前CPU优化", &[]),
+];
+
+const LINKER_OPTIONS: &[PresetOption] = &[
+    option("", "默认链接器", "", &[]),
+    option("lld-link", "lld-link (LLVM, 推荐Windows)", "", &["windows"]),
+    option("rust-lld", "rust-lld (Rust内置)", "", &[]),
+    option("mold", "mold (超快, Linux)", "", &["linux"]),
+    option("gold", "gold (GNU Gold, Linux)", "", &["linux"]),
+    option("link.exe", "link.exe (MSVC)", "", &["windows"]),
+];
+
+const PROXY_OPTIONS: &[PresetOption] = &[
+    option("", "无代理", "", &[]),
+    option("127.0.0.1:7890", "Clash (7890)", "", &[]),
+    option("127.0.0.1:7891", "Clash (7891)", "", &[]),
+    option("127.0.0.1:7897", "Clash Rev (7897)", "", &[]),
+    option("127.0.0.1:10808", "V2Ray (10808)", "", &[]),
+    option("127.0.0.1:1080", "V2Ray (1080)", "", &[]),
+];
+
+pub fn option_library() -> OptionLibrary {
+    OptionLibrary {
+        rustflags: RUSTFLAG_OPTIONS,
+        linkers: LINKER_OPTIONS,
+        proxies: PROXY_OPTIONS,
+    }
+}
+
+/// 预设定义：`config` 为部分配置，应用时合并到当前配置；`platforms` 为空表示不限平台
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Preset {
+    #[serde(default = "default_format")]
+    pub format: u32,
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    #[serde(default)]
+    pub unset: Vec<String>,
+    #[serde(default)]
+    pub config: CargoConfig,
+}
+
+fn default_format() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PresetInfo {
+    #[serde(flatten)]
+    pub preset: Preset,
+    pub builtin: bool,
+    pub supported: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PresetPreview {
+    pub id: String,
+    pub supported: bool,
+    pub changes: Vec<KeyChange>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApplyPresetResult {
+    pub backup: Option<BackupEntry>,
+    pub changes: Vec<KeyChange>,
+    pub loaded: LoadedConfig,
+}
+
+fn get_presets_dir() -> PathBuf {
+    config::get_app_data_dir().join(PRESETS_DIR)
+}
+
+fn parse_preset(content: &str) -> AppResult<Preset> {
+    let preset: Preset = toml::from_str(content)
+        .map_err(|e| AppError::parse("预设文件解析失败", content, &e))?;
+    if preset.format > PRESET_FORMAT {
+        return Err(AppError::invalid("预设文件格式版本过高，请升级本程序")
+            .with_detail(format!("format = {}", preset.format)));
+    }
+    validate_id(&preset.id)?;
+    Ok(preset)
+}
+
+// id 同时用作文件名，只允许小写字母、数字和连字符
+fn validate_id(id: &str) -> AppResult<()> {
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid("预设 id 只能包含小写字母、数字和连字符").with_key(id.to_string()))
+    }
+}
+
+fn is_supported(preset: &Preset) -> bool {
+    preset.platforms.is_empty()
+        || preset
+            .platforms
+            .iter()
+            .any(|platform| platform == std::env::consts::OS)
+}
+
+// 内置预设能否解析由测试 builtin_presets_parse_and_have_unique_ids 保证
+fn builtin_presets() -> Vec<Preset> {
+    BUILTIN_PRESETS
+        .iter()
+        .filter_map(|content| parse_preset(content).ok())
+        .collect()
+}
+
+// 无法解析的用户预设直接跳过，不影响其他预设
+fn user_presets() -> Vec<Preset> {
+    let Ok(entries) = fs::read_dir(get_presets_dir()) else {
+        return Vec::new();
+    };
+    let mut presets: Vec<Preset> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| parse_preset(&content).ok())
+        .collect();
+    presets.sort_by(|a, b| a.id.cmp(&b.id));
+    presets
+}
+
+pub fn list_presets() -> Vec<PresetInfo> {
+    let builtin = builtin_presets().into_iter().map(|preset| (preset, true));
+    let user = user_presets().into_iter().map(|preset| (preset, false));
+    builtin
+        .chain(user)
+        .map(|(preset, builtin)| PresetInfo {
+            supported: is_supported(&preset),
+            preset,
+            builtin,
+        })
+        .collect()
+}
+
+fn find_preset(id: &str) -> AppResult<Preset> {
+    builtin_presets()
+        .into_iter()
+        .chain(user_presets())
+        .find(|preset| preset.id == id)
+        .ok_or_else(|| AppError::not_found("预设不存在").with_key(id.to_string()))
+}
+
+// 取值写在下一个参数中的编译器选项，如 `-C opt-level=3`、`--cfg foo`
+const FLAGS_WITH_VALUE: &[&str] = &[
+    "-C", "-Z", "-L", "-l", "-A", "-W", "-D", "-F", "--cfg", "--check-cfg", "--cap-lints",
+    "--codegen", "--target", "--edition", "--emit", "--crate-type",
+];
+
+// 把数组按选项分组，带值的选项与其取值成为一组；`-C x` 与 `-Cx` 视为同一组
+fn flag_groups(items: &[toml::Value]) -> Vec<(String, Vec<toml::Value>)> {
+    let mut groups = Vec::new();
+    let mut iter = items.iter().peekable();
+    while let Some(item) = iter.next() {
+        let flag = item.as_str().filter(|flag| FLAGS_WITH_VALUE.contains(flag));
+        let value = flag.and_then(|_| iter.next_if(|next| next.is_str()));
+        match (flag, value) {
+            (Some(flag), Some(value)) => {
+                let separator = if flag.starts_with("--") { "=" } else { "" };
+                let key = format!("{}{}{}", flag, separator, value.as_str().unwrap_or_default());
+                groups.push((key, vec![item.clone(), value.clone()]));
+            }
+            _ => {
+                let key = item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string());
+                groups.push((key, vec![item.clone()]));
+            }
+        }
+    }
+    groups
+}
+
+// 预设数组中现有数组里没有的选项组整体追加到末尾，不拆开 `-C x` 这样的参数对
+fn merge_array(existing: &[toml::Value], extra: &[toml::Value]) -> Vec<toml::Value> {
+    let present: Vec<String> = flag_groups(existing).into_iter().map(|(key, _)| key).collect();
+    let mut items = existing.to_vec();
+    for (key, group) in flag_groups(extra) {
+        if !present.contains(&key) {
+            items.extend(group);
+        }
+    }
+    items
+}
+
+/// 合并预设：数组与现有数组按选项组取并集（如 rustflags），其余键直接覆盖
+fn merge_preset(base: &CargoConfig, preset: &Preset) -> AppResult<CargoConfig> {
+    let current = config::flatten_config_value(&config::config_to_value(base)?);
+    let mut overlay = toml::Table::new();
+    for (key, value) in config::flatten_config_value(&config::config_to_value(&preset.config)?) {
+        let merged = match (current.get(&key), value) {
+            (Some(toml::Value::Array(existing)), toml::Value::Array(extra)) => {
+                toml::Value::Array(merge_array(existing, &extra))
+            }
+            (_, value) => value,
+        };
+        config::insert_key_path(&mut overlay, &key, merged);
+    }
+    let overlay: CargoConfig = toml::Value::Table(overlay)
+        .try_into()
+        .map_err(|e| AppError::invalid("预设配置无效").with_detail(e))?;
+    config::overlay_config(base, &overlay, &preset.unset)
+}
+
+fn preset_changes(base: &CargoConfig, preset: &Preset) -> AppResult<(CargoConfig, Vec<KeyChange>)> {
+    let merged = merge_preset(base, preset)?;
+    let changes = config::diff_config_values(
+        &config::config_to_value(base)?,
+        &config::config_to_value(&merged)?,
+    );
+    Ok((merged, changes))
+}
+
+pub fn preview_preset(config_path: Option<&str>, id: &str) -> AppResult<PresetPreview> {
+    let preset = find_preset(id)?;
+    let current = config::load_config(config_path)?;
+    let (_, changes) = preset_changes(&current, &preset)?;
+    Ok(PresetPreview {
+        id: preset.id.clone(),
+        supported: is_supported(&preset),
+        changes,
+    })
+}
+
+/// 应用预设：不适用于当前平台时拒绝；有变化时先备份再按修订号写入
+pub fn apply_preset(config_path: Option<&str>, id: &str) -> AppResult<ApplyPresetResult> {
+    let preset = find_preset(id)?;
+    if !is_supported(&preset) {
+        return Err(AppError::invalid("该预设不适用于当前平台")
+            .with_key(preset.id.clone())
+            .with_detail(preset.platforms.join(", ")));
+    }
+    let loaded = config::load_config_with_revision(config_path)?;
+    let (merged, changes) = preset_changes(&loaded.config, &preset)?;
+
+    let mut backup = None;
+    if !changes.is_empty() {
+        let target = config::resolve_config_path(config_path);
+        if fs::metadata(&target).map(|m| m.len() > 0).unwrap_or(false) {
+            backup = Some(config::create_tagged_backup(&target, "preset")?);
+        }
+        config::save_config_checked(&merged, config_path, Some(&loaded.revision))?;
+    }
+    Ok(ApplyPresetResult {
+        backup,
+        changes,
+        loaded: config::load_config_with_revision(config_path)?,
+    })
+}
+
+/// 从文件导入预设到用户预设目录；同 id 的内置预设不可覆盖，用户预设需 `replace`
+pub fn import_preset(path: &str, replace: bool) -> AppResult<Preset> {
+    let resolved = config::expand_path(path);
+    let content = fs::read_to_string(&resolved)
+        .map_err(|e| AppError::io("读取预设文件失败", e).with_path(&resolved))?;
+    let preset = parse_preset(&content).map_err(|e| e.with_path(&resolved))?;
+
+    if builtin_presets().iter().any(|p| p.id == preset.id) {
+        return Err(AppError::new(ErrorCode::AlreadyExists, "不能覆盖内置预设")
+            .with_key(preset.id.clone()));
+    }
+    let dir = get_presets_dir();
+    let dest = dir.join(format!("{}.toml", preset.id));
+    if dest.exists() && !replace {
+        return Err(AppError::new(ErrorCode::AlreadyExists, "同 id 的预设已存在")
+            .with_key(preset.id.clone()));
+    }
+    fs::create_dir_all(&dir).map_err(|e| AppError::io("创建预设目录失败", e))?;
+    config::atomic_write(&dest, &content)?;
+    Ok(preset)
+}
+
+pub fn delete_preset(id: &str) -> AppResult<()> {
+    validate_id(id)?;
+    if builtin_presets().iter().any(|p| p.id == id) {
+        return Err(AppError::invalid("内置预设不能删除").with_key(id.to_string()));
+    }
+    let path = get_presets_dir().join(format!("{}.toml", id));
+    if !path.exists() {
+        return Err(AppError::not_found("预设不存在").with_key(id.to_string()));
+    }
+    fs::remove_file(&path).map_err(|e| AppError::io("删除预设失败", e).with_path(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<toml::Value> {
+        items.iter().map(|item| toml::Value::from(*item)).collect()
+    }
+
+    #[test]
+    fn flag_pairs_are_appended_as_a_block() {
+        let merged = merge_array(
+            &strings(&["-C", "target-cpu=native"]),
+            &strings(&["-C", "link-arg=-fuse-ld=mold"]),
+        );
+        assert_eq!(merged, strings(&["-C", "target-cpu=native", "-C", "link-arg=-fuse-ld=mold"]));
+    }
+
+    #[test]
+    fn existing_flag_groups_are_not_duplicated() {
+        let existing = strings(&["-C", "target-cpu=native", "--cfg", "tokio_unstable"]);
+        let merged = merge_array(&existing, &strings(&["-Ctarget-cpu=native", "--cfg=tokio_unstable"]));
+        assert_eq!(merged, existing);
+
+        let merged = merge_array(&strings(&["-Ctarget-cpu=native"]), &strings(&["-C", "target-cpu=native", "-Dwarnings"]));
+        assert_eq!(merged, strings(&["-Ctarget-cpu=native", "-Dwarnings"]));
+    }
+
+    #[test]
+    fn builtin_presets_parse_and_have_unique_ids() {
+        let mut ids = std::collections::HashSet::new();
+        for (index, content) in BUILTIN_PRESETS.iter().enumerate() {
+            let preset = parse_preset(content)
+                .unwrap_or_else(|e| panic!("第 {} 个内置预设无法解析: {}", index, e));
+            assert!(!preset.name.is_empty(), "{}", preset.id);
+            assert!(ids.insert(preset.id.clone()), "重复的预设 id: {}", preset.id);
+        }
+        assert_eq!(builtin_presets().len(), BUILTIN_PRESETS.len());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::config::{self, BackupEntry, CargoConfig, KeyChange, LoadedConfig, RustupEnvWriteResult};
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PROFILES_FILE: &str = "profiles.json";
+const MAX_PROFILE_NAME_LEN: usize = 64;
+// 从当前配置创建方案时捕获的表，即随办公/家庭/CI 环境变化的网络与源设置
+const CAPTURED_SECTIONS: &[&str] = &["http", "net", "source", "registry", "registries"];
+
+/// rustup 镜像环境变量，两项都为空表示清除
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct RustupProfile {
+    pub dist: Option<String>,
+    pub root: Option<String>,
+}
+
+/// 命名配置方案：`overlay` 和 `unset` 涉及的顶层表归方案所有，应用时整表替换为方案内容，
+/// 其余配置保持不变
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConfigProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub overlay: CargoConfig,
+    #[serde(default)]
+    pub unset: Vec<String>,
+    #[serde(default)]
+    pub rustup: Option<RustupProfile>,
+    #[serde(default)]
+    pub updated: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileSummary {
+    #[serde(flatten)]
+    pub profile: ConfigProfile,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileDiff {
+    pub name: String,
+    pub changes: Vec<KeyChange>,
+    pub rustup_changed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ApplyProfileResult {
+    pub backup: Option<BackupEntry>,
+    pub changes: Vec<KeyChange>,
+    pub loaded: LoadedConfig,
+    pub rustup: Option<RustupEnvWriteResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct ProfileStore {
+    profiles: Vec<ConfigProfile>,
+}
+
+fn get_profiles_path() -> PathBuf {
+    config::get_app_data_dir().join(PROFILES_FILE)
+}
+
+fn load_store() -> ProfileStore {
+    fs::read_to_string(get_profiles_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_store(store: &ProfileStore) -> AppResult<()> {
+    let path = get_profiles_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| AppError::io("创建目录失败", e))?;
+    }
+    let content = serde_json::to_string_pretty(store).map_err(AppError::serialize)?;
+    config::atomic_write(&path, &content)
+}
+
+fn validate_name(name: &str) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid("方案名称不能为空"));
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_LEN {
+        return Err(AppError::invalid("方案名称过长"));
+    }
+    Ok(name.to_string())
+}
+
+fn find_profile(store: &ProfileStore, name: &str) -> AppResult<ConfigProfile> {
+    store
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned()
+        .ok_or_else(|| AppError::not_found("配置方案不存在").with_key(name.to_string()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// 方案拥有的顶层表：overlay 中出现的表和 unset 中键所在的表
+fn owned_sections(profile: &ConfigProfile) -> AppResult<Vec<String>> {
+    let mut sections: Vec<String> = match config::config_to_value(&profile.overlay)? {
+        toml::Value::Table(table) => table.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    for key in &profile.unset {
+        let section = key.split('.').next().unwrap_or(key).to_string();
+        if !section.is_empty() && !sections.contains(&section) {
+            sections.push(section);
+        }
+    }
+    Ok(sections)
+}
+
+// 先清空方案拥有的表再覆盖，切换方案时不会残留上一个方案的代理或私有源
+fn apply_to(current: &CargoConfig, profile: &ConfigProfile) -> AppResult<CargoConfig> {
+    config::overlay_config(current, &profile.overlay, &owned_sections(profile)?)
+}
+
+fn profile_changes(current: &CargoConfig, profile: &ConfigProfile) -> AppResult<Vec<KeyChange>> {
+    let applied = apply_to(current, profile)?;
+    Ok(config::diff_config_values(
+        &config::config_to_value(current)?,
+        &config::config_to_value(&applied)?,
+    ))
+}
+
+fn rustup_matches(rustup: &Option<RustupProfile>, effective: &(Option<String>, Option<String>)) -> bool {
+    match rustup {
+        None => true,
+        Some(expected) => expected.dist == effective.0 && expected.root == effective.1,
+    }
+}
+
+/// 列出所有方案；方案拥有的表与当前配置完全一致且 rustup 变量一致时视为当前生效
+pub fn list_profiles(config_path: Option<&str>) -> AppResult<Vec<ProfileSummary>> {
+    let store = load_store();
+    let current = config::load_config(config_path)?;
+    let effective = config::get_rustup_env_effective();
+    store
+        .profiles
+        .into_iter()
+        .map(|profile| {
+            let active = profile_changes(&current, &profile)
+                .map(|changes| changes.is_empty())
+                .unwrap_or(false)
+                && rustup_matches(&profile.rustup, &effective);
+            Ok(ProfileSummary { profile, active })
+        })
+        .collect()
+}
+
+/// 新建或覆盖同名方案
+pub fn save_profile(mut profile: ConfigProfile) -> AppResult<ConfigProfile> {
+    profile.name = validate_name(&profile.name)?;
+    profile.updated = now_secs();
+    let mut store = load_store();
+    match store.profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile.clone(),
+        None => store.profiles.push(profile.clone()),
+    }
+    save_store(&store)?;
+    Ok(profile)
+}
+
+/// 以当前配置的网络与源设置（及可选的 rustup 变量）创建方案；当前没有的表也归方案所有，
+/// 应用时会被删除
+pub fn create_profile_from_config(
+    config_path: Option<&str>,
+    name: &str,
+    description: Option<String>,
+    include_rustup: bool,
+) -> AppResult<ConfigProfile> {
+    let name = validate_name(name)?;
+    if load_store().profiles.iter().any(|p| p.name == name) {
+        return Err(AppError::new(ErrorCode::AlreadyExists, "同名方案已存在").with_key(name));
+    }
+    let rustup = include_rustup.then(|| {
+        let (dist, root) = config::get_rustup_env_effective();
+        RustupProfile { dist, root }
+    });
+    let mut table = match config::config_to_value(&config::load_config(config_path)?)? {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+    table.retain(|key, _| CAPTURED_SECTIONS.contains(&key));
+    let overlay: CargoConfig = toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| AppError::invalid("当前配置无效").with_detail(e))?;
+    save_profile(ConfigProfile {
+        name,
+        description,
+        overlay,
+        unset: CAPTURED_SECTIONS.iter().map(|s| s.to_string()).collect(),
+        rustup,
+        updated: 0,
+    })
+}
+
+pub fn delete_profile(name: &str) -> AppResult<()> {
+    let mut store = load_store();
+    let before = store.profiles.len();
+    store.profiles.retain(|profile| profile.name != name);
+    if store.profiles.len() == before {
+        return Err(AppError::not_found("配置方案不存在").with_key(name.to_string()));
+    }
+    save_store(&store)
+}
+
+pub fn diff_profile(config_path: Option<&str>, name: &str) -> AppResult<ProfileDiff> {
+    let profile = find_profile(&load_store(), name)?;
+    let current = config::load_config(config_path)?;
+    Ok(ProfileDiff {
+        name: profile.name.clone(),
+        changes: profile_changes(&current, &profile)?,
+        rustup_changed: !rustup_matches(&profile.rustup, &config::get_rustup_env_effective()),
+    })
+}
+
+/// 应用方案：先备份现有配置文件，再按修订号写入，最后设置 rustup 变量
+pub fn apply_profile(config_path: Option<&str>, name: &str) -> AppResult<ApplyProfileResult> {
+    let profile = find_profile(&load_store(), name)?;
+    let loaded = config::load_config_with_revision(config_path)?;
+    let applied = apply_to(&loaded.config, &profile)?;
+    let changes = config::diff_config_values(
+        &config::config_to_value(&loaded.config)?,
+        &config::config_to_value(&applied)?,
+    );
+
+    let mut backup = None;
+    if !changes.is_empty() {
+        let target = config::resolve_config_path(config_path);
+        if fs::metadata(&target).map(|m| m.len() > 0).unwrap_or(false) {
+            backup = Some(config::create_tagged_backup(&target, "profile")?);
+        }
+        config::save_config_checked(&applied, config_path, Some(&loaded.revision))?;
+    }
+
+    let rustup = match &profile.rustup {
+        Some(rustup) if !rustup_matches(&profile.rustup, &config::get_rustup_env_effective()) => {
+            Some(config::set_rustup_env(rustup.dist.clone(), rustup.root.clone()))
+        }
+        _ => None,
+    };
+    Ok(ApplyProfileResult {
+        backup,
+        changes,
+        loaded: config::load_config_with_revision(config_path)?,
+        rustup,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> CargoConfig {
+        toml::from_str(content).unwrap()
+    }
+
+    fn profile(overlay: &str, unset: &[&str]) -> ConfigProfile {
+        ConfigProfile {
+            name: "test".to_string(),
+            description: None,
+            overlay: parse(overlay),
+            unset: unset.iter().map(|s| s.to_string()).collect(),
+            rustup: None,
+            updated: 0,
+        }
+    }
+
+    #[test]
+    fn switching_profiles_removes_previous_sections() {
+        let office = parse(
+            "[http]\nproxy = \"http://proxy.corp:8080\"\n\n[registries.corp]\nindex = \"sparse+https://crates.corp/\"\n\n[build]\njobs = 4\n",
+        );
+        let home = profile("[http]\ntimeout = 30\n", CAPTURED_SECTIONS);
+        let applied = apply_to(&office, &home).unwrap();
+        let value = config::config_to_value(&applied).unwrap();
+        let http = value.get("http").unwrap();
+        assert!(http.get("proxy").is_none());
+        assert_eq!(http.get("timeout").and_then(|v| v.as_integer()), Some(30));
+        assert!(value.get("registries").is_none());
+        assert_eq!(value.get("build").and_then(|b| b.get("jobs")).and_then(|v| v.as_integer()), Some(4));
+        assert!(profile_changes(&applied, &home).unwrap().is_empty());
+    }
+
+    #[test]
+    fn active_requires_exact_match_of_owned_sections() {
+        let proxy_only = profile("[http]\nproxy = \"http://proxy:1\"\n", &[]);
+        let exact = parse("[http]\nproxy = \"http://proxy:1\"\n\n[build]\njobs = 2\n");
+        assert!(profile_changes(&exact, &proxy_only).unwrap().is_empty());
+
+        let superset = parse("[http]\nproxy = \"http://proxy:1\"\ntimeout = 5\n");
+        assert!(!profile_changes(&superset, &proxy_only).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1230 @@
+use crate::config;
+use crate::error::{AppError, AppResult};
+use crate::{create_hidden_command, decode_command_output};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const RELEASE_CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+pub(crate) const INSTALL_PROFILES: &[&str] = &["minimal", "default", "complete"];
+const AUTO_SELF_UPDATE_MODES: &[&str] = &["enable", "disable", "check-only"];
+const AUTO_INSTALL_MODES: &[&str] = &["enable", "disable"];
+// Tier 1 目标（截至 Rust 1.91），rustc 不提供分级信息，只能内置
+const TIER1_TARGETS: &[&str] = &[
+    "aarch64-apple-darwin",
+    "aarch64-pc-windows-msvc",
+    "aarch64-unknown-linux-gnu",
+    "i686-pc-windows-msvc",
+    "i686-unknown-linux-gnu",
+    "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
+    "x86_64-unknown-linux-gnu",
+];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolchainInfo {
+    pub name: String,
+    /// `stable` / `beta` / `nightly` / 版本号；自定义（link）工具链为 None
+    pub channel: Option<String>,
+    pub date: Option<String>,
+    pub host: Option<String>,
+    pub custom: bool,
+    pub is_default: bool,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ActiveToolchain {
+    pub name: String,
+    /// rustup 给出的原因，如 `default`、`directory override for ...`、`overridden by '.../rust-toolchain.toml'`
+    pub reason: String,
+    pub is_override: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolchainList {
+    pub toolchains: Vec<ToolchainInfo>,
+    pub default: Option<String>,
+    pub active: Option<ActiveToolchain>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolchainUpdate {
+    pub toolchain: String,
+    /// `updated`、`unchanged`、`installed` 或 `failed`
+    pub status: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComponentInfo {
+    pub name: String,
+    /// 去掉目标三元组后的组件名，如 `clippy`、`rust-std`
+    pub base: String,
+    pub target: Option<String>,
+    pub installed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComponentList {
+    pub toolchain: String,
+    pub components: Vec<ComponentInfo>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ComponentRunResult {
+    pub output: String,
+    pub list: ComponentList,
+}
+
+/// 目标平台：`prebuilt` 表示 rustup 提供预编译标准库（Tier 1/2），否则只能通过 build-std 使用
+#[derive(Debug, Serialize, Clone)]
+pub struct TargetInfo {
+    pub name: String,
+    pub installed: bool,
+    pub prebuilt: bool,
+    pub tier: u8,
+    pub is_host: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TargetList {
+    pub toolchain: String,
+    pub host: String,
+    pub targets: Vec<TargetInfo>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RustupRunResult {
+    pub output: String,
+    pub updates: Vec<ToolchainUpdate>,
+    pub toolchains: ToolchainList,
+}
+
+/// rustup 的 settings.toml；未写入的项为 None，rustup 按默认值处理
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RustupSettings {
+    #[serde(default, skip_deserializing)]
+    pub path: String,
+    #[serde(default, skip_deserializing)]
+    pub exists: bool,
+    pub version: Option<String>,
+    pub default_host_triple: Option<String>,
+    pub default_toolchain: Option<String>,
+    /// `minimal` / `default` / `complete`
+    pub profile: Option<String>,
+    /// `enable` / `disable` / `check-only`
+    pub auto_self_update: Option<String>,
+    /// `enable` / `disable`，需要 rustup 1.28 及以上
+    pub auto_install: Option<String>,
+}
+
+/// 要修改的设置，None 表示保持不变
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RustupSettingsUpdate {
+    pub default_host_triple: Option<String>,
+    pub default_toolchain: Option<String>,
+    pub profile: Option<String>,
+    pub auto_self_update: Option<String>,
+    pub auto_install: Option<String>,
+}
+
+/// `rustup override set` 记录的目录覆盖；`exists` 为 false 表示目录已被删除
+#[derive(Debug, Serialize, Clone)]
+pub struct DirectoryOverride {
+    pub path: String,
+    pub toolchain: String,
+    pub exists: bool,
+}
+
+/// 项目目录实际使用的工具链及其来源
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolchainResolution {
+    pub dir: String,
+    pub toolchain: Option<String>,
+    /// `env`、`override`、`toolchain-file`、`default` 或 `none`
+    pub source: String,
+    /// 覆盖所在目录或 rust-toolchain 文件路径
+    pub origin: Option<String>,
+    pub reason: String,
+    /// 工具链未安装或无法确定时的 rustup 错误信息
+    pub error: Option<String>,
+}
+
+/// 为 rustup 子进程设置镜像环境变量，未配置时清除继承的值
+fn apply_rustup_env(cmd: &mut Command) {
+    let (dist, root) = config::get_rustup_env_effective();
+    if let Some(dist) = dist {
+        cmd.env("RUSTUP_DIST_SERVER", dist);
+    } else {
+        cmd.env_remove("RUSTUP_DIST_SERVER");
+    }
+    if let Some(root) = root {
+        cmd.env("RUSTUP_UPDATE_ROOT", root);
+    } else {
+        cmd.env_remove("RUSTUP_UPDATE_ROOT");
+    }
+}
+
+pub(crate) fn rustup_command() -> Command {
+    let mut cmd = create_hidden_command("rustup");
+    apply_rustup_env(&mut cmd);
+    cmd
+}
+
+/// 运行 rustup 并返回 (stdout, stderr)；rustup 的进度和结果信息大多输出在 stderr
+pub(crate) fn run_rustup(args: &[&str], cwd: Option<&str>, failure: &str) -> AppResult<(String, String)> {
+    let mut cmd = rustup_command();
+    cmd.args(args);
+    if let Some(dir) = cwd {
+        cmd.current_dir(config::expand_path(dir));
+    }
+    let output = cmd.output().map_err(|e| AppError::io("无法运行 rustup", e))?;
+    let stdout = decode_command_output(&output.stdout);
+    let stderr = decode_command_output(&output.stderr);
+    if output.status.success() {
+        Ok((stdout, stderr))
+    } else {
+        Err(AppError::command(failure.to_string(), stderr))
+    }
+}
+
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn is_version(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('.').collect();
+    (2..=3).contains(&parts.len())
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+// 工具链名称形如 <channel>[-<date>][-<host>]，不符合时视为自定义工具链
+fn parse_toolchain_name(name: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let (channel, rest) = name.split_once('-').unwrap_or((name, ""));
+    if !RELEASE_CHANNELS.contains(&channel) && !is_version(channel) {
+        return (None, None, None);
+    }
+    let (date, host) = if rest.len() >= 10 && is_date(&rest[..10]) {
+        (Some(rest[..10].to_string()), rest[10..].trim_start_matches('-'))
+    } else {
+        (None, rest)
+    };
+    let host = (!host.is_empty()).then(|| host.to_string());
+    (Some(channel.to_string()), date, host)
+}
+
+// 拆分 `name (flag, flag)` 形式的行
+fn split_flags(line: &str) -> (&str, Vec<&str>) {
+    match line.split_once(" (") {
+        Some((name, flags)) => (
+            name.trim(),
+            flags.trim_end_matches(')').split(", ").map(str::trim).collect(),
+        ),
+        None => (line.trim(), Vec::new()),
+    }
+}
+
+fn parse_active(stdout: &str) -> Option<ActiveToolchain> {
+    let line = stdout.lines().next()?.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let (name, reason) = match line.split_once(" (") {
+        Some((name, reason)) => (name.trim(), reason.trim_end_matches(')').to_string()),
+        None => (line, String::new()),
+    };
+    Some(ActiveToolchain {
+        name: name.to_string(),
+        is_override: reason.contains("overrid"),
+        reason,
+    })
+}
+
+/// 列出已安装的工具链；`cwd` 用于判断目录覆盖（rust-toolchain.toml 或 `rustup override`）
+pub fn list_toolchains(cwd: Option<&str>) -> AppResult<ToolchainList> {
+    let (stdout, _) = run_rustup(&["toolchain", "list"], cwd, "获取工具链列表失败")?;
+    // 未设置默认工具链或当前目录的覆盖未安装时，该命令会失败，不影响列表
+    let active = run_rustup(&["show", "active-toolchain"], cwd, "获取当前工具链失败")
+        .ok()
+        .and_then(|(stdout, _)| parse_active(&stdout));
+    Ok(parse_toolchain_list(&stdout, active))
+}
+
+// `rustup toolchain list` 的输出，1.28 起默认工具链标记为 `(active, default)`
+fn parse_toolchain_list(stdout: &str, active: Option<ActiveToolchain>) -> ToolchainList {
+    let mut default = None;
+    let toolchains = stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("no installed toolchains"))
+        .map(|line| {
+            let (name, flags) = split_flags(line);
+            let is_default = flags.contains(&"default");
+            if is_default {
+                default = Some(name.to_string());
+            }
+            let (channel, date, host) = parse_toolchain_name(name);
+            ToolchainInfo {
+                name: name.to_string(),
+                custom: channel.is_none(),
+                channel,
+                date,
+                host,
+                is_default,
+                is_active: active.as_ref().is_some_and(|a| a.name == name),
+            }
+        })
+        .collect();
+    ToolchainList {
+        toolchains,
+        default,
+        active,
+    }
+}
+
+// 工具链和组件名称会作为 rustup 参数传入，只允许常见字符，避免被解析为选项
+fn validate_name<'a>(name: &'a str, message: &str) -> AppResult<&'a str> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(name)
+    } else {
+        Err(AppError::invalid(message.to_string()).with_key(name.to_string()))
+    }
+}
+
+pub(crate) fn validate_toolchain(name: &str) -> AppResult<&str> {
+    validate_name(name, "工具链名称无效")
+}
+
+fn parse_updates(output: &str) -> Vec<ToolchainUpdate> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            ["updated", "unchanged", "installed", "update failed"]
+                .iter()
+                .find_map(|status| {
+                    // 更新失败时没有版本号，行尾只剩 ` -`
+                    let (toolchain, version) = line.split_once(&format!(" {} -", status))?;
+                    let status = if *status == "update failed" { "failed" } else { status };
+                    Some(ToolchainUpdate {
+                        toolchain: toolchain.trim().to_string(),
+                        status: status.to_string(),
+                        version: version.trim().to_string(),
+                    })
+                })
+        })
+        .collect()
+}
+
+fn join_output(stdout: String, stderr: String) -> String {
+    [stdout, stderr]
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn finish(stdout: String, stderr: String) -> AppResult<RustupRunResult> {
+    let output = join_output(stdout, stderr);
+    Ok(RustupRunResult {
+        updates: parse_updates(&output),
+        output,
+        toolchains: list_toolchains(None)?,
+    })
+}
+
+/// 安装工具链，如 `stable`、`nightly-2024-05-01`、`1.79.0`；`profile` 为 minimal/default/complete
+pub fn install_toolchain(name: &str, profile: Option<&str>) -> AppResult<RustupRunResult> {
+    let name = validate_toolchain(name)?;
+    let mut args = vec!["toolchain", "install", name, "--no-self-update"];
+    if let Some(profile) = profile {
+        if !INSTALL_PROFILES.contains(&profile) {
+            return Err(AppError::invalid("安装配置只能是 minimal、default 或 complete")
+                .with_key(profile.to_string()));
+        }
+        args.extend(["--profile", profile]);
+    }
+    let (stdout, stderr) = run_rustup(&args, None, "安装工具链失败")?;
+    finish(stdout, stderr)
+}
+
+pub fn uninstall_toolchain(name: &str) -> AppResult<RustupRunResult> {
+    let name = validate_toolchain(name)?;
+    let (stdout, stderr) = run_rustup(&["toolchain", "uninstall", name], None, "卸载工具链失败")?;
+    finish(stdout, stderr)
+}
+
+/// 以本地目录注册自定义工具链（`rustup toolchain link`）
+pub fn link_toolchain(name: &str, path: &str) -> AppResult<RustupRunResult> {
+    let name = validate_toolchain(name)?;
+    let path = config::expand_path(path);
+    if !path.join("bin").is_dir() {
+        return Err(AppError::not_found("目录下没有 bin 子目录，不是有效的工具链").with_path(&path));
+    }
+    let path = path.to_string_lossy().to_string();
+    let (stdout, stderr) = run_rustup(&["toolchain", "link", name, &path], None, "注册自定义工具链失败")?;
+    finish(stdout, stderr)
+}
+
+pub fn set_default_toolchain(name: &str) -> AppResult<RustupRunResult> {
+    let name = validate_toolchain(name)?;
+    let (stdout, stderr) = run_rustup(&["default", name], None, "设置默认工具链失败")?;
+    finish(stdout, stderr)
+}
+
+/// 运行 `rustup update`，`name` 为空时更新全部工具链；使用已配置的 rustup 镜像
+pub fn update_toolchains(name: Option<&str>) -> AppResult<RustupRunResult> {
+    let mut args = vec!["update", "--no-self-update"];
+    if let Some(name) = name {
+        args.push(validate_toolchain(name)?);
+    }
+    let (stdout, stderr) = run_rustup(&args, None, "更新工具链失败")?;
+    finish(stdout, stderr)
+}
+
+// 未指定工具链时使用当前生效的工具链
+fn resolve_toolchain(toolchain: Option<&str>) -> AppResult<String> {
+    if let Some(name) = toolchain {
+        return validate_toolchain(name).map(str::to_string);
+    }
+    let (stdout, _) = run_rustup(&["show", "active-toolchain"], None, "获取当前工具链失败")?;
+    parse_active(&stdout)
+        .map(|active| active.name)
+        .ok_or_else(|| AppError::not_found("未设置默认工具链"))
+}
+
+// 组件名带目标三元组后缀，如 `clippy-x86_64-unknown-linux-gnu`、`rust-std-wasm32-unknown-unknown`
+fn split_component(name: &str, host: &str) -> (String, Option<String>) {
+    if let Some(base) = name.strip_suffix(&format!("-{}", host)) {
+        return (base.to_string(), Some(host.to_string()));
+    }
+    if let Some(target) = name.strip_prefix("rust-std-") {
+        return ("rust-std".to_string(), Some(target.to_string()));
+    }
+    (name.to_string(), None)
+}
+
+/// 列出工具链的全部组件及安装状态
+pub fn list_components(toolchain: Option<&str>) -> AppResult<ComponentList> {
+    let toolchain = resolve_toolchain(toolchain)?;
+    let (stdout, _) = run_rustup(
+        &["component", "list", "--toolchain", &toolchain],
+        None,
+        "获取组件列表失败",
+    )?;
+    let components = parse_components(&stdout, &toolchain_host(&toolchain));
+    Ok(ComponentList {
+        toolchain,
+        components,
+    })
+}
+
+fn parse_components(stdout: &str, host: &str) -> Vec<ComponentInfo> {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, flags) = split_flags(line);
+            let (base, target) = split_component(name, host);
+            ComponentInfo {
+                name: name.to_string(),
+                base,
+                target,
+                installed: flags.contains(&"installed"),
+            }
+        })
+        .collect()
+}
+
+fn change_components(
+    toolchain: Option<&str>,
+    components: &[String],
+    action: &str,
+    failure: &str,
+) -> AppResult<ComponentRunResult> {
+    if components.is_empty() {
+        return Err(AppError::invalid("未指定组件"));
+    }
+    let toolchain = resolve_toolchain(toolchain)?;
+    let mut args = vec!["component", action, "--toolchain", toolchain.as_str()];
+    for component in components {
+        args.push(validate_name(component, "组件名称无效")?);
+    }
+    let (stdout, stderr) = run_rustup(&args, None, failure)?;
+    Ok(ComponentRunResult {
+        output: join_output(stdout, stderr),
+        list: list_components(Some(&toolchain))?,
+    })
+}
+
+/// 安装组件，可使用短名称（如 `rust-src`、`llvm-tools`），rustup 会补全目标后缀
+pub fn add_components(toolchain: Option<&str>, components: &[String]) -> AppResult<ComponentRunResult> {
+    change_components(toolchain, components, "add", "安装组件失败")
+}
+
+pub fn remove_components(toolchain: Option<&str>, components: &[String]) -> AppResult<ComponentRunResult> {
+    change_components(toolchain, components, "remove", "移除组件失败")
+}
+
+fn toolchain_host(toolchain: &str) -> String {
+    parse_toolchain_name(toolchain)
+        .2
+        .unwrap_or_else(config::get_current_target)
+}
+
+/// 已安装的目标平台名称
+pub fn installed_targets(toolchain: Option<&str>) -> AppResult<Vec<String>> {
+    let mut args = vec!["target", "list", "--installed"];
+    if let Some(name) = toolchain {
+        args.extend(["--toolchain", validate_toolchain(name)?]);
+    }
+    let (stdout, _) = run_rustup(&args, None, "获取已安装目标失败")?;
+    Ok(stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// 完整目标列表：合并 `rustup target list` 与 `rustc --print target-list`，
+/// 仅出现在后者中的目标没有预编译标准库，视为 Tier 3
+pub fn list_targets(toolchain: Option<&str>) -> AppResult<TargetList> {
+    let toolchain = resolve_toolchain(toolchain)?;
+    let host = toolchain_host(&toolchain);
+    let (stdout, _) = run_rustup(
+        &["target", "list", "--toolchain", &toolchain],
+        None,
+        "获取目标列表失败",
+    )?;
+    // 自定义工具链可能没有 rustc 的 target-list，失败时只返回 rustup 的结果
+    let rustc_targets = run_rustup(
+        &["run", &toolchain, "rustc", "--print", "target-list"],
+        None,
+        "获取 rustc 目标列表失败",
+    )
+    .ok()
+    .map(|(stdout, _)| stdout);
+    let targets = parse_targets(&stdout, rustc_targets.as_deref(), &host);
+    Ok(TargetList {
+        toolchain,
+        host,
+        targets,
+    })
+}
+
+// rustup 列出的目标都有预编译标准库；只出现在 rustc 列表中的补为 Tier 3
+fn parse_targets(rustup_list: &str, rustc_list: Option<&str>, host: &str) -> Vec<TargetInfo> {
+    let mut targets: Vec<TargetInfo> = rustup_list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (name, flags) = split_flags(line);
+            TargetInfo {
+                name: name.to_string(),
+                installed: flags.contains(&"installed"),
+                prebuilt: true,
+                tier: if TIER1_TARGETS.contains(&name) { 1 } else { 2 },
+                is_host: name == host,
+            }
+        })
+        .collect();
+    let extra = rustc_list.unwrap_or_default();
+    for name in extra.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if !targets.iter().any(|target| target.name == name) {
+            targets.push(TargetInfo {
+                name: name.to_string(),
+                installed: false,
+                prebuilt: false,
+                tier: 3,
+                is_host: name == host,
+            });
+        }
+    }
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    targets
+}
+
+fn change_target(toolchain: Option<&str>, target: &str, action: &str, failure: &str) -> AppResult<()> {
+    let target = validate_name(target, "目标平台名称无效")?;
+    let mut args = vec!["target", action, target];
+    if let Some(name) = toolchain {
+        args.extend(["--toolchain", validate_toolchain(name)?]);
+    }
+    run_rustup(&args, None, failure).map(|_| ())
+}
+
+pub fn install_target(toolchain: Option<&str>, target: &str) -> AppResult<()> {
+    change_target(toolchain, target, "add", "安装目标失败")
+}
+
+/// 移除目标平台；rustup 不允许移除宿主平台的标准库，这里提前拒绝
+pub fn remove_target(toolchain: Option<&str>, target: &str) -> AppResult<()> {
+    let toolchain = resolve_toolchain(toolchain)?;
+    if target == toolchain_host(&toolchain) {
+        return Err(AppError::invalid("不能移除宿主平台的目标").with_key(target.to_string()));
+    }
+    change_target(Some(&toolchain), target, "remove", "移除目标失败")
+}
+
+/// 列出 rustup settings.toml 中的目录覆盖
+pub fn list_overrides() -> AppResult<Vec<DirectoryOverride>> {
+    let (stdout, _) = run_rustup(&["override", "list"], None, "获取目录覆盖失败")?;
+    Ok(parse_overrides(&stdout))
+}
+
+fn parse_overrides(stdout: &str) -> Vec<DirectoryOverride> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            // 格式为 `<path>[ (not a directory)]<空白>\t<toolchain>`
+            let (path, toolchain) = line.split_once('\t')?;
+            let path = path.trim();
+            let (path, exists) = match path.strip_suffix("(not a directory)") {
+                Some(path) => (path.trim_end(), false),
+                None => (path, true),
+            };
+            Some(DirectoryOverride {
+                path: path.to_string(),
+                toolchain: toolchain.trim().to_string(),
+                exists,
+            })
+        })
+        .collect()
+}
+
+/// 为目录设置覆盖工具链
+pub fn set_override(dir: &str, toolchain: &str) -> AppResult<Vec<DirectoryOverride>> {
+    let toolchain = validate_toolchain(toolchain)?;
+    let path = config::expand_path(dir);
+    if !path.is_dir() {
+        return Err(AppError::not_found("目录不存在").with_path(&path));
+    }
+    let path = path.to_string_lossy();
+    run_rustup(&["override", "set", toolchain, "--path", &path], None, "设置目录覆盖失败")?;
+    list_overrides()
+}
+
+/// 移除目录覆盖；`dir` 为 None 时移除所有指向已删除目录的覆盖
+pub fn unset_override(dir: Option<&str>) -> AppResult<Vec<DirectoryOverride>> {
+    let Some(dir) = dir else {
+        run_rustup(&["override", "unset", "--nonexistent"], None, "移除目录覆盖失败")?;
+        return list_overrides();
+    };
+    // 目录可能已被删除，不检查是否存在；路径的规范化（末尾斜杠、`..`、符号链接、大小写）
+    // 交给 rustup。目录没有覆盖时 rustup 只提示不报错，通过覆盖数量是否减少来判断
+    let path = config::expand_path(dir).to_string_lossy().to_string();
+    let before = list_overrides()?.len();
+    run_rustup(&["override", "unset", "--path", &path], None, "移除目录覆盖失败")?;
+    let after = list_overrides()?;
+    if after.len() == before {
+        return Err(AppError::not_found("该目录没有覆盖").with_key(path));
+    }
+    Ok(after)
+}
+
+// 从 rustup 给出的原因中取出单引号内的路径
+fn quoted_path(reason: &str) -> Option<String> {
+    let start = reason.find('\'')? + 1;
+    let end = reason[start..].find('\'')? + start;
+    Some(reason[start..end].to_string())
+}
+
+// 按 `rustup show active-toolchain` 给出的原因判断来源，兼容 1.27 与 1.28 起的措辞
+fn resolution_source(reason: &str) -> (&'static str, Option<String>) {
+    if reason.contains("environment") {
+        ("env", None)
+    } else if reason.contains("directory override") {
+        ("override", quoted_path(reason))
+    } else if reason.contains("overrid") {
+        ("toolchain-file", quoted_path(reason))
+    } else {
+        ("default", None)
+    }
+}
+
+/// 判断在 `dir` 中运行 cargo 时使用的工具链：环境变量 > 目录覆盖 > rust-toolchain 文件 > 默认工具链
+pub fn resolve_toolchain_for(dir: &str) -> AppResult<ToolchainResolution> {
+    let path = config::expand_path(dir);
+    if !path.is_dir() {
+        return Err(AppError::not_found("目录不存在").with_path(&path));
+    }
+    let mut cmd = rustup_command();
+    // 新版 rustup 会在工具链缺失时自动安装，这里只做查询
+    cmd.args(["show", "active-toolchain"])
+        .env("RUSTUP_AUTO_INSTALL", "0")
+        .current_dir(&path);
+    let output = cmd.output().map_err(|e| AppError::io("无法运行 rustup", e))?;
+    let dir = path.to_string_lossy().to_string();
+
+    if !output.status.success() {
+        let stderr = decode_command_output(&output.stderr);
+        let error = stderr
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches("error: ")
+            .to_string();
+        // 错误信息（或其 Caused by）中会带出 rust-toolchain 文件路径
+        let file_line = stderr.lines().find(|line| line.contains("rust-toolchain"));
+        let (source, origin) = if let Some(line) = file_line {
+            ("toolchain-file", quoted_path(line))
+        } else if stderr.contains("RUSTUP_TOOLCHAIN") {
+            ("env", None)
+        } else if let Some(found) = list_overrides()?
+            .into_iter()
+            .filter(|o| path.starts_with(&o.path))
+            .max_by_key(|o| o.path.len())
+        {
+            ("override", Some(found.path))
+        } else {
+            ("none", None)
+        };
+        return Ok(ToolchainResolution {
+            dir,
+            toolchain: None,
+            source: source.to_string(),
+            origin,
+            reason: String::new(),
+            error: Some(error),
+        });
+    }
+
+    let active = parse_active(&decode_command_output(&output.stdout));
+    let reason = active.as_ref().map(|a| a.reason.clone()).unwrap_or_default();
+    let (source, origin) = match active {
+        Some(_) => resolution_source(&reason),
+        None => ("none", None),
+    };
+    Ok(ToolchainResolution {
+        dir,
+        toolchain: active.map(|a| a.name),
+        source: source.to_string(),
+        origin,
+        reason,
+        error: None,
+    })
+}
+
+fn rustup_home() -> PathBuf {
+    match std::env::var("RUSTUP_HOME") {
+        Ok(home) if !home.trim().is_empty() => config::expand_path(&home),
+        _ => Path::new(&config::get_home_dir()).join(".rustup"),
+    }
+}
+
+/// 读取 `$RUSTUP_HOME/settings.toml`，文件不存在时返回空设置
+pub fn read_rustup_settings() -> AppResult<RustupSettings> {
+    let path = rustup_home().join("settings.toml");
+    let mut settings = match std::fs::read_to_string(&path) {
+        Ok(content) => toml::from_str::<RustupSettings>(&content)
+            .map_err(|e| AppError::parse("rustup settings.toml 解析失败", &content, &e).with_path(&path))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => RustupSettings::default(),
+        Err(e) => return Err(AppError::io("读取 rustup settings.toml 失败", e).with_path(&path)),
+    };
+    settings.exists = path.is_file();
+    settings.path = path.to_string_lossy().to_string();
+    Ok(settings)
+}
+
+fn check_mode<'a>(value: &'a str, allowed: &[&str], message: &str) -> AppResult<&'a str> {
+    if allowed.contains(&value) {
+        Ok(value)
+    } else {
+        Err(AppError::invalid(format!("{}只能是 {}", message, allowed.join(" / "))).with_key(value.to_string()))
+    }
+}
+
+/// 通过 `rustup set` / `rustup default` 修改设置，全部校验通过后再依次执行；
+/// 设置默认工具链时若未安装，rustup 会先安装它
+pub fn update_rustup_settings(update: &RustupSettingsUpdate) -> AppResult<RustupSettings> {
+    let mut commands: Vec<Vec<&str>> = Vec::new();
+    if let Some(host) = update.default_host_triple.as_deref() {
+        commands.push(vec!["set", "default-host", validate_name(host, "宿主平台名称无效")?]);
+    }
+    if let Some(profile) = update.profile.as_deref() {
+        commands.push(vec!["set", "profile", check_mode(profile, INSTALL_PROFILES, "安装配置")?]);
+    }
+    if let Some(mode) = update.auto_self_update.as_deref() {
+        let mode = check_mode(mode, AUTO_SELF_UPDATE_MODES, "自动更新模式")?;
+        commands.push(vec!["set", "auto-self-update", mode]);
+    }
+    if let Some(mode) = update.auto_install.as_deref() {
+        let mode = check_mode(mode, AUTO_INSTALL_MODES, "自动安装模式")?;
+        commands.push(vec!["set", "auto-install", mode]);
+    }
+    // 放在最后，使新的 default-host 和 profile 对安装生效
+    if let Some(name) = update.default_toolchain.as_deref() {
+        commands.push(vec!["default", validate_toolchain(name)?]);
+    }
+    for args in &commands {
+        run_rustup(args, None, "修改 rustup 设置失败")?;
+    }
+    read_rustup_settings()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_support::TestDir;
+    use std::ffi::OsString;
+
+    fn parts(name: &str) -> (Option<String>, Option<String>, Option<String>) {
+        parse_toolchain_name(name)
+    }
+
+    fn expect(
+        channel: Option<&str>,
+        date: Option<&str>,
+        host: Option<&str>,
+    ) -> (Option<String>, Option<String>, Option<String>) {
+        let owned = |v: Option<&str>| v.map(str::to_string);
+        (owned(channel), owned(date), owned(host))
+    }
+
+    #[test]
+    fn toolchain_names_are_split_into_channel_date_and_host() {
+        let host = Some("x86_64-unknown-linux-gnu");
+        assert_eq!(parts("stable-x86_64-unknown-linux-gnu"), expect(Some("stable"), None, host));
+        assert_eq!(
+            parts("nightly-2024-05-01-x86_64-unknown-linux-gnu"),
+            expect(Some("nightly"), Some("2024-05-01"), host)
+        );
+        assert_eq!(parts("nightly-2024-05-01"), expect(Some("nightly"), Some("2024-05-01"), None));
+        assert_eq!(
+            parts("1.79.0-x86_64-pc-windows-msvc"),
+            expect(Some("1.79.0"), None, Some("x86_64-pc-windows-msvc"))
+        );
+        assert_eq!(parts("1.79"), expect(Some("1.79"), None, None));
+        assert_eq!(parts("beta"), expect(Some("beta"), None, None));
+        assert_eq!(parts("my-toolchain"), expect(None, None, None));
+        assert_eq!(parts("1.79.x-dev"), expect(None, None, None));
+    }
+
+    #[test]
+    fn toolchain_list_from_rustup_1_27() {
+        let stdout = "stable-x86_64-unknown-linux-gnu (default)\n\
+                      nightly-2024-05-01-x86_64-unknown-linux-gnu\n\
+                      1.79.0-x86_64-unknown-linux-gnu\n\
+                      my-toolchain\n";
+        let active = parse_active("stable-x86_64-unknown-linux-gnu (default)\n");
+        let list = parse_toolchain_list(stdout, active);
+
+        assert_eq!(list.default.as_deref(), Some("stable-x86_64-unknown-linux-gnu"));
+        let names: Vec<&str> = list.toolchains.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "stable-x86_64-unknown-linux-gnu",
+                "nightly-2024-05-01-x86_64-unknown-linux-gnu",
+                "1.79.0-x86_64-unknown-linux-gnu",
+                "my-toolchain",
+            ]
+        );
+        let stable = &list.toolchains[0];
+        assert!(stable.is_default && stable.is_active && !stable.custom);
+        assert_eq!(list.toolchains[2].channel.as_deref(), Some("1.79.0"));
+        assert!(list.toolchains[3].custom);
+        assert!(!list.toolchains[3].is_active);
+    }
+
+    #[test]
+    fn toolchain_list_from_rustup_1_28() {
+        let stdout = "stable-x86_64-unknown-linux-gnu (default)\n\
+                      nightly-x86_64-unknown-linux-gnu (active)\n";
+        let active = parse_active(
+            "nightly-x86_64-unknown-linux-gnu (overridden by '/home/u/proj/rust-toolchain.toml')\n",
+        );
+        let list = parse_toolchain_list(stdout, active);
+        assert_eq!(list.default.as_deref(), Some("stable-x86_64-unknown-linux-gnu"));
+        assert!(!list.toolchains[0].is_active);
+        assert!(list.toolchains[1].is_active && !list.toolchains[1].is_default);
+        assert!(list.active.unwrap().is_override);
+
+        let list = parse_toolchain_list(
+            "stable-x86_64-unknown-linux-gnu (active, default)\n",
+            parse_active("stable-x86_64-unknown-linux-gnu (default)\n"),
+        );
+        assert!(list.toolchains[0].is_default && list.toolchains[0].is_active);
+
+        let list = parse_toolchain_list("no installed toolchains\n", None);
+        assert!(list.toolchains.is_empty());
+        assert!(list.default.is_none());
+    }
+
+    #[test]
+    fn active_toolchain_reasons_from_rustup_1_27_and_1_28() {
+        let cases = [
+            ("stable-x86_64-unknown-linux-gnu (default)", "default", false),
+            (
+                "nightly-x86_64-unknown-linux-gnu (directory override for '/home/u/proj')",
+                "directory override for '/home/u/proj'",
+                true,
+            ),
+            (
+                "1.79.0-x86_64-unknown-linux-gnu (overridden by '/home/u/proj/rust-toolchain.toml')",
+                "overridden by '/home/u/proj/rust-toolchain.toml'",
+                true,
+            ),
+            // 1.27
+            (
+                "beta-x86_64-unknown-linux-gnu (environment override by RUSTUP_TOOLCHAIN)",
+                "environment override by RUSTUP_TOOLCHAIN",
+                true,
+            ),
+            // 1.28
+            (
+                "beta-x86_64-unknown-linux-gnu (overridden by environment variable RUSTUP_TOOLCHAIN)",
+                "overridden by environment variable RUSTUP_TOOLCHAIN",
+                true,
+            ),
+        ];
+        for (line, reason, is_override) in cases {
+            let active = parse_active(&format!("{}\n", line)).unwrap();
+            assert_eq!(active.name, line.split(" (").next().unwrap());
+            assert_eq!(active.reason, reason);
+            assert_eq!(active.is_override, is_override, "{}", line);
+        }
+        assert!(parse_active("").is_none());
+        assert!(parse_active("\n").is_none());
+        let bare = parse_active("stable-x86_64-unknown-linux-gnu\n").unwrap();
+        assert_eq!(bare.reason, "");
+    }
+
+    #[test]
+    fn update_summary_lines_are_parsed() {
+        let output = "\
+info: syncing channel updates for 'stable-x86_64-unknown-linux-gnu'
+info: checking for self-update
+
+  stable-x86_64-unknown-linux-gnu unchanged - rustc 1.79.0 (129f3b996 2024-06-10)
+  nightly-x86_64-unknown-linux-gnu updated - rustc 1.81.0-nightly (5315cbe15 2024-07-11) (from rustc 1.80.0-nightly (bdbbb6c6a 2024-06-01))
+  1.79.0-x86_64-unknown-linux-gnu installed - rustc 1.79.0 (129f3b996 2024-06-10)
+  beta-x86_64-unknown-linux-gnu update failed -
+info: cleaning up downloads & tmp directories
+";
+        let updates = parse_updates(output);
+        let summary: Vec<(&str, &str)> = updates
+            .iter()
+            .map(|u| (u.toolchain.as_str(), u.status.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("stable-x86_64-unknown-linux-gnu", "unchanged"),
+                ("nightly-x86_64-unknown-linux-gnu", "updated"),
+                ("1.79.0-x86_64-unknown-linux-gnu", "installed"),
+                ("beta-x86_64-unknown-linux-gnu", "failed"),
+            ]
+        );
+        assert_eq!(updates[0].version, "rustc 1.79.0 (129f3b996 2024-06-10)");
+        assert!(updates[1].version.ends_with("(from rustc 1.80.0-nightly (bdbbb6c6a 2024-06-01))"));
+        assert_eq!(updates[3].version, "");
+    }
+
+    #[test]
+    fn component_names_are_split_from_their_target() {
+        let host = "x86_64-unknown-linux-gnu";
+        let split = |name| split_component(name, host);
+        assert_eq!(
+            split("clippy-x86_64-unknown-linux-gnu"),
+            ("clippy".to_string(), Some(host.to_string()))
+        );
+        assert_eq!(
+            split("llvm-tools-x86_64-unknown-linux-gnu"),
+            ("llvm-tools".to_string(), Some(host.to_string()))
+        );
+        assert_eq!(
+            split("rust-std-wasm32-unknown-unknown"),
+            ("rust-std".to_string(), Some("wasm32-unknown-unknown".to_string()))
+        );
+        assert_eq!(
+            split("rust-std-x86_64-unknown-linux-gnu"),
+            ("rust-std".to_string(), Some(host.to_string()))
+        );
+        assert_eq!(split("rust-src"), ("rust-src".to_string(), None));
+        // 非宿主平台的其他组件无法可靠拆分，保留完整名称
+        assert_eq!(
+            split("rustc-dev-aarch64-apple-darwin"),
+            ("rustc-dev-aarch64-apple-darwin".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn component_list_from_rustup_1_27_and_1_28() {
+        // 两个版本的 `rustup component list` 格式相同
+        let stdout = "\
+cargo-x86_64-unknown-linux-gnu (installed)
+clippy-x86_64-unknown-linux-gnu (installed)
+llvm-tools-x86_64-unknown-linux-gnu
+rust-docs-x86_64-unknown-linux-gnu
+rust-src (installed)
+rust-std-aarch64-apple-darwin
+rust-std-x86_64-unknown-linux-gnu (installed)
+";
+        let components = parse_components(stdout, "x86_64-unknown-linux-gnu");
+        let summary: Vec<(&str, Option<&str>, bool)> = components
+            .iter()
+            .map(|c| (c.base.as_str(), c.target.as_deref(), c.installed))
+            .collect();
+        let host = Some("x86_64-unknown-linux-gnu");
+        assert_eq!(
+            summary,
+            vec![
+                ("cargo", host, true),
+                ("clippy", host, true),
+                ("llvm-tools", host, false),
+                ("rust-docs", host, false),
+                ("rust-src", None, true),
+                ("rust-std", Some("aarch64-apple-darwin"), false),
+                ("rust-std", host, true),
+            ]
+        );
+        assert_eq!(components[0].name, "cargo-x86_64-unknown-linux-gnu");
+    }
+
+    #[test]
+    fn target_catalog_merges_rustup_and_rustc_lists() {
+        // `rustup target list` 在 1.27 和 1.28 中格式相同
+        let rustup_list = "\
+aarch64-apple-darwin
+wasm32-unknown-unknown (installed)
+x86_64-unknown-linux-gnu (installed)
+";
+        let rustc_list = "aarch64-apple-darwin\nx86_64-unknown-linux-gnu\navr-unknown-gnu-atmega328\n";
+        let host = "x86_64-unknown-linux-gnu";
+
+        let targets = parse_targets(rustup_list, Some(rustc_list), host);
+        let summary: Vec<(&str, u8, bool, bool, bool)> = targets
+            .iter()
+            .map(|t| (t.name.as_str(), t.tier, t.installed, t.prebuilt, t.is_host))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("aarch64-apple-darwin", 1, false, true, false),
+                ("avr-unknown-gnu-atmega328", 3, false, false, false),
+                ("wasm32-unknown-unknown", 2, true, true, false),
+                ("x86_64-unknown-linux-gnu", 1, true, true, true),
+            ]
+        );
+
+        // 自定义工具链拿不到 rustc 列表时只保留 rustup 的结果
+        assert_eq!(parse_targets(rustup_list, None, host).len(), 3);
+    }
+
+    #[test]
+    fn override_list_marks_missing_directories() {
+        // 1.27 与 1.28 均按 `{:<40}\t{:<20}` 对齐输出，已删除的目录带 `(not a directory)`
+        let stdout = "\
+/home/u/gone (not a directory)          \tnightly-2024-05-01-x86_64-unknown-linux-gnu
+/home/u/my project                      \tstable-x86_64-unknown-linux-gnu
+C:\\Users\\u\\proj                        \t1.79.0
+";
+        let overrides = parse_overrides(stdout);
+        let summary: Vec<(&str, &str, bool)> = overrides
+            .iter()
+            .map(|o| (o.path.as_str(), o.toolchain.as_str(), o.exists))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/home/u/gone", "nightly-2024-05-01-x86_64-unknown-linux-gnu", false),
+                ("/home/u/my project", "stable-x86_64-unknown-linux-gnu", true),
+                ("C:\\Users\\u\\proj", "1.79.0", true),
+            ]
+        );
+        assert!(parse_overrides("no overrides\n").is_empty());
+    }
+
+    #[test]
+    fn resolution_source_follows_the_active_reason() {
+        let cases = [
+            ("default", "default", None),
+            ("environment override by RUSTUP_TOOLCHAIN", "env", None),
+            ("overridden by environment variable RUSTUP_TOOLCHAIN", "env", None),
+            ("directory override for '/home/u/proj'", "override", Some("/home/u/proj")),
+            (
+                "overridden by '/home/u/proj/rust-toolchain.toml'",
+                "toolchain-file",
+                Some("/home/u/proj/rust-toolchain.toml"),
+            ),
+        ];
+        for (reason, source, origin) in cases {
+            let (found, path) = resolution_source(reason);
+            assert_eq!(found, source, "{}", reason);
+            assert_eq!(path.as_deref(), origin, "{}", reason);
+        }
+    }
+
+    // 把 RUSTUP_HOME 指向临时目录，结束时恢复
+    struct IsolatedRustupHome {
+        home: PathBuf,
+        previous: Option<OsString>,
+        _dir: TestDir,
+    }
+
+    impl IsolatedRustupHome {
+        fn new(name: &str) -> Self {
+            let dir = TestDir::new(name);
+            let home = dir.path().join("rustup");
+            std::fs::create_dir_all(&home).unwrap();
+            let previous = std::env::var_os("RUSTUP_HOME");
+            std::env::set_var("RUSTUP_HOME", &home);
+            IsolatedRustupHome {
+                home,
+                previous,
+                _dir: dir,
+            }
+        }
+    }
+
+    impl Drop for IsolatedRustupHome {
+        fn drop(&mut self) {
+            match self.previous.take() {
+                Some(previous) => std::env::set_var("RUSTUP_HOME", previous),
+                None => std::env::remove_var("RUSTUP_HOME"),
+            }
+        }
+    }
+
+    fn rustup_available() -> bool {
+        rustup_command().arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn unsetting_an_override_lets_rustup_normalize_the_path() {
+        if !rustup_available() {
+            return;
+        }
+        let rustup = IsolatedRustupHome::new("override");
+        let work = rustup.home.parent().unwrap();
+        let project = work.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let project = std::fs::canonicalize(&project).unwrap();
+        let gone = project.parent().unwrap().join("gone");
+
+        let mut overrides = toml::Table::new();
+        for (path, toolchain) in [(&project, "stable"), (&gone, "nightly-2024-05-01")] {
+            overrides.insert(path.to_string_lossy().to_string(), toolchain.into());
+        }
+        let mut settings = toml::Table::new();
+        settings.insert("version".into(), "12".into());
+        settings.insert("overrides".into(), toml::Value::Table(overrides));
+        std::fs::write(rustup.home.join("settings.toml"), settings.to_string()).unwrap();
+
+        let listed = list_overrides().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().any(|o| Path::new(&o.path) == gone && !o.exists));
+
+        // 末尾带分隔符的路径由 rustup 规范化后仍能匹配
+        let with_slash = format!("{}{}", project.to_string_lossy(), std::path::MAIN_SEPARATOR);
+        let remaining = unset_override(Some(&with_slash)).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(Path::new(&remaining[0].path), gone);
+
+        let err = unset_override(Some(&project.to_string_lossy())).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::NotFound);
+
+        assert!(unset_override(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn settings_files_from_rustup_1_27_and_1_28_are_read() {
+        let rustup = IsolatedRustupHome::new("settings-read");
+        let path = rustup.home.join("settings.toml");
+
+        let missing = read_rustup_settings().unwrap();
+        assert!(!missing.exists);
+        assert_eq!(Path::new(&missing.path), path);
+        assert!(missing.profile.is_none());
+
+        std::fs::write(
+            &path,
+            "version = \"12\"\n\
+             default_host_triple = \"x86_64-unknown-linux-gnu\"\n\
+             default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n\
+             profile = \"minimal\"\n\n\
+             [overrides]\n\
+             \"/home/u/proj\" = \"nightly-x86_64-unknown-linux-gnu\"\n",
+        )
+        .unwrap();
+        let v127 = read_rustup_settings().unwrap();
+        assert!(v127.exists);
+        assert_eq!(v127.default_toolchain.as_deref(), Some("stable-x86_64-unknown-linux-gnu"));
+        assert_eq!(v127.profile.as_deref(), Some("minimal"));
+        assert!(v127.auto_install.is_none());
+
+        std::fs::write(
+            &path,
+            "version = \"12\"\n\
+             default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n\
+             profile = \"default\"\n\
+             auto_self_update = \"check-only\"\n\
+             auto_install = \"disable\"\n\n\
+             [overrides]\n",
+        )
+        .unwrap();
+        let v128 = read_rustup_settings().unwrap();
+        assert_eq!(v128.auto_self_update.as_deref(), Some("check-only"));
+        assert_eq!(v128.auto_install.as_deref(), Some("disable"));
+
+        std::fs::write(&path, "profile = [\n").unwrap();
+        let err = read_rustup_settings().unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::Parse);
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected_before_running_rustup() {
+        let rustup = IsolatedRustupHome::new("settings-invalid");
+        let update = RustupSettingsUpdate {
+            default_toolchain: Some("stable".to_string()),
+            profile: Some("huge".to_string()),
+            ..Default::default()
+        };
+        let err = update_rustup_settings(&update).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::InvalidInput);
+        assert_eq!(err.key.as_deref(), Some("huge"));
+        assert!(!rustup.home.join("settings.toml").exists());
+
+        let update = RustupSettingsUpdate {
+            default_host_triple: Some("--help".to_string()),
+            ..Default::default()
+        };
+        assert!(update_rustup_settings(&update).is_err());
+    }
+
+    #[test]
+    fn settings_are_written_through_rustup_set() {
+        if !rustup_available() {
+            return;
+        }
+        let _rustup = IsolatedRustupHome::new("settings-write");
+        let update = RustupSettingsUpdate {
+            profile: Some("minimal".to_string()),
+            auto_self_update: Some("disable".to_string()),
+            ..Default::default()
+        };
+        let settings = update_rustup_settings(&update).unwrap();
+        assert!(settings.exists);
+        assert_eq!(settings.profile.as_deref(), Some("minimal"));
+        assert_eq!(settings.auto_self_update.as_deref(), Some("disable"));
+    }
+}
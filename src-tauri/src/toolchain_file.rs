@@ -0,0 +1,316 @@
+use crate::config;
+use crate::error::{AppError, AppResult};
+use crate::rustup;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TOOLCHAIN_FILE: &str = "rust-toolchain.toml";
+// 旧版文件名，内容可以是单行渠道名或 TOML
+const LEGACY_TOOLCHAIN_FILE: &str = "rust-toolchain";
+
+/// `rust-toolchain.toml` 中 `[toolchain]` 表的内容，未识别的键原样保留
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ToolchainSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    #[serde(flatten)]
+    pub other: HashMap<String, toml::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct ToolchainDocument {
+    #[serde(default)]
+    toolchain: ToolchainSpec,
+    #[serde(flatten)]
+    other: HashMap<String, toml::Value>,
+}
+
+/// 文件要求与本机安装情况的对照；`issues` 为文件内容本身的问题
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ToolchainFileStatus {
+    pub toolchain_installed: bool,
+    pub missing_components: Vec<String>,
+    pub missing_targets: Vec<String>,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolchainFileInfo {
+    pub path: String,
+    pub exists: bool,
+    pub legacy: bool,
+    pub spec: ToolchainSpec,
+    pub status: ToolchainFileStatus,
+}
+
+// 已存在的文件优先（新文件名优先于旧文件名），都不存在时使用 rust-toolchain.toml
+fn locate(dir: &Path) -> (PathBuf, bool) {
+    let legacy = dir.join(LEGACY_TOOLCHAIN_FILE);
+    if !dir.join(TOOLCHAIN_FILE).is_file() && legacy.is_file() {
+        (legacy, true)
+    } else {
+        (dir.join(TOOLCHAIN_FILE), false)
+    }
+}
+
+fn project_dir(dir: &str) -> AppResult<PathBuf> {
+    let path = config::expand_path(dir);
+    if path.is_dir() {
+        Ok(path)
+    } else {
+        Err(AppError::not_found("项目目录不存在").with_path(&path))
+    }
+}
+
+fn parse_document(content: &str, legacy: bool) -> AppResult<ToolchainDocument> {
+    let trimmed = content.trim();
+    // 旧版单行格式：整个文件就是渠道名
+    if legacy && !trimmed.is_empty() && !trimmed.contains('=') && !trimmed.contains('[') {
+        return Ok(ToolchainDocument {
+            toolchain: ToolchainSpec {
+                channel: Some(trimmed.to_string()),
+                ..Default::default()
+            },
+            other: HashMap::new(),
+        });
+    }
+    toml::from_str(content).map_err(|e| AppError::parse("rust-toolchain 文件解析失败", content, &e))
+}
+
+// 组件和目标名称会作为 rustup 参数传入，规则与 rustup::validate_name 相同
+fn is_valid_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn validate_spec(spec: &ToolchainSpec) -> Vec<String> {
+    let mut issues = Vec::new();
+    match (&spec.channel, &spec.path) {
+        (Some(_), Some(_)) => issues.push("channel 与 path 不能同时设置".to_string()),
+        (None, None) => issues.push("缺少 channel".to_string()),
+        _ => {}
+    }
+    if let Some(channel) = &spec.channel {
+        if rustup::validate_toolchain(channel).is_err() {
+            issues.push(format!("channel 无效: {}", channel));
+        }
+    }
+    if let Some(profile) = &spec.profile {
+        if !rustup::INSTALL_PROFILES.contains(&profile.as_str()) {
+            issues.push(format!("profile 只能是 minimal、default 或 complete: {}", profile));
+        }
+    }
+    for name in spec.components.iter().chain(&spec.targets) {
+        if !is_valid_name(name) {
+            issues.push(format!("名称无效: {}", name));
+        }
+    }
+    if spec.path.is_some() && (!spec.components.is_empty() || !spec.targets.is_empty()) {
+        issues.push("使用 path 时 components 和 targets 不会生效".to_string());
+    }
+    issues
+}
+
+// 按本机已安装的工具链解析 channel，如 `stable` -> `stable-x86_64-unknown-linux-gnu`
+fn find_installed(channel: &str) -> AppResult<Option<String>> {
+    let host = config::get_current_target();
+    let list = rustup::list_toolchains(None)?;
+    Ok(list
+        .toolchains
+        .into_iter()
+        .map(|t| t.name)
+        .find(|name| name == channel || *name == format!("{}-{}", channel, host)))
+}
+
+fn check_installed(spec: &ToolchainSpec, issues: Vec<String>) -> AppResult<ToolchainFileStatus> {
+    let mut status = ToolchainFileStatus {
+        issues,
+        ..Default::default()
+    };
+    if let Some(path) = &spec.path {
+        status.toolchain_installed = config::expand_path(path).join("bin").is_dir();
+        return Ok(status);
+    }
+    let Some(channel) = spec.channel.as_deref().filter(|_| status.issues.is_empty()) else {
+        return Ok(status);
+    };
+    let Some(installed) = find_installed(channel)? else {
+        status.missing_components = spec.components.clone();
+        status.missing_targets = spec.targets.clone();
+        return Ok(status);
+    };
+    status.toolchain_installed = true;
+
+    let components = rustup::list_components(Some(&installed))?.components;
+    status.missing_components = spec
+        .components
+        .iter()
+        .filter(|wanted| {
+            !components
+                .iter()
+                .any(|c| c.installed && (&c.base == *wanted || &c.name == *wanted))
+        })
+        .cloned()
+        .collect();
+    let targets = rustup::installed_targets(Some(&installed))?;
+    status.missing_targets = spec
+        .targets
+        .iter()
+        .filter(|wanted| !targets.contains(wanted))
+        .cloned()
+        .collect();
+    Ok(status)
+}
+
+/// 读取项目目录下的 rust-toolchain 文件并检查本机是否满足其要求；文件不存在时返回空内容
+pub fn read_toolchain_file(dir: &str) -> AppResult<ToolchainFileInfo> {
+    let dir = project_dir(dir)?;
+    let (path, legacy) = locate(&dir);
+    let exists = path.is_file();
+    let spec = if exists {
+        let content =
+            fs::read_to_string(&path).map_err(|e| AppError::io("读取 rust-toolchain 文件失败", e))?;
+        parse_document(&content, legacy)
+            .map_err(|e| e.with_path(&path))?
+            .toolchain
+    } else {
+        ToolchainSpec::default()
+    };
+    let status = if exists {
+        check_installed(&spec, validate_spec(&spec))?
+    } else {
+        ToolchainFileStatus::default()
+    };
+    Ok(ToolchainFileInfo {
+        path: path.to_string_lossy().to_string(),
+        exists,
+        legacy,
+        spec,
+        status,
+    })
+}
+
+/// 写入 `[toolchain]` 表，文件中的其他表保持不变（注释不保留）；内容无效时拒绝写入
+pub fn save_toolchain_file(dir: &str, spec: ToolchainSpec) -> AppResult<ToolchainFileInfo> {
+    let project = project_dir(dir)?;
+    let issues = validate_spec(&spec);
+    if !issues.is_empty() {
+        return Err(AppError::invalid("rust-toolchain 文件内容无效").with_detail(issues.join("; ")));
+    }
+    let (path, legacy) = locate(&project);
+    let mut document = match fs::read_to_string(&path) {
+        Ok(content) => parse_document(&content, legacy).map_err(|e| e.with_path(&path))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ToolchainDocument::default(),
+        Err(e) => return Err(AppError::io("读取 rust-toolchain 文件失败", e).with_path(&path)),
+    };
+    document.toolchain = spec;
+    let content = toml::to_string_pretty(&document).map_err(AppError::serialize)?;
+    config::atomic_write(&path, &content)?;
+    read_toolchain_file(dir)
+}
+
+/// 一次性安装文件要求的工具链、组件和目标平台
+pub fn install_toolchain_file(dir: &str) -> AppResult<ToolchainFileInfo> {
+    let info = read_toolchain_file(dir)?;
+    if !info.exists {
+        return Err(AppError::not_found("项目中没有 rust-toolchain 文件"));
+    }
+    if let Some(first) = info.status.issues.first() {
+        return Err(AppError::invalid(first.clone()).with_path(Path::new(&info.path)));
+    }
+    let Some(channel) = info.spec.channel.as_deref() else {
+        return Err(AppError::invalid("使用 path 的自定义工具链无法自动安装"));
+    };
+
+    let mut args = vec!["toolchain", "install", channel, "--no-self-update"];
+    if let Some(profile) = info.spec.profile.as_deref() {
+        args.extend(["--profile", profile]);
+    }
+    for component in &info.status.missing_components {
+        args.extend(["--component", component.as_str()]);
+    }
+    for target in &info.status.missing_targets {
+        args.extend(["--target", target.as_str()]);
+    }
+    rustup::run_rustup(&args, None, "安装工具链失败")?;
+    read_toolchain_file(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_support::TestDir;
+
+    fn spec(content: &str) -> ToolchainSpec {
+        parse_document(content, false).unwrap().toolchain
+    }
+
+    #[test]
+    fn legacy_file_may_be_a_bare_channel() {
+        let document = parse_document("nightly-2024-05-01\n", true).unwrap();
+        assert_eq!(document.toolchain.channel.as_deref(), Some("nightly-2024-05-01"));
+
+        let document = parse_document("[toolchain]\nchannel = \"1.80.0\"\n", true).unwrap();
+        assert_eq!(document.toolchain.channel.as_deref(), Some("1.80.0"));
+
+        // 新文件名必须是 TOML
+        assert!(parse_document("stable\n", false).is_err());
+    }
+
+    #[test]
+    fn toml_keeps_unknown_keys_and_tables() {
+        let document = parse_document(
+            "[toolchain]\nchannel = \"stable\"\ncomponents = [\"clippy\"]\nfuture = 1\n\n[other]\nx = 1\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(document.toolchain.components, vec!["clippy".to_string()]);
+        assert!(document.toolchain.other.contains_key("future"));
+        assert!(document.other.contains_key("other"));
+    }
+
+    #[test]
+    fn spec_validation_reports_conflicts() {
+        assert!(validate_spec(&spec("[toolchain]\nchannel = \"stable\"\nprofile = \"minimal\"\n")).is_empty());
+
+        let issues = validate_spec(&spec("[toolchain]\nchannel = \"stable\"\npath = \"/opt/rust\"\n"));
+        assert!(issues.iter().any(|issue| issue.contains("channel 与 path")), "{:?}", issues);
+
+        let issues = validate_spec(&spec("[toolchain]\nchannel = \"stable\"\nprofile = \"huge\"\n"));
+        assert!(issues.iter().any(|issue| issue.contains("profile")), "{:?}", issues);
+
+        let issues = validate_spec(&spec("[toolchain]\nprofile = \"minimal\"\n"));
+        assert!(issues.iter().any(|issue| issue.contains("缺少 channel")), "{:?}", issues);
+
+        let issues = validate_spec(&spec(
+            "[toolchain]\nchannel = \"stable\"\ncomponents = [\"clippy\", \"--force\"]\ntargets = [\"wasm32 wasi\"]\n",
+        ));
+        assert_eq!(issues, vec!["名称无效: --force", "名称无效: wasm32 wasi"]);
+    }
+
+    #[test]
+    fn unreadable_file_is_not_overwritten() {
+        let test_dir = TestDir::new("toolchain");
+        let dir = test_dir.path();
+        // 非 UTF-8 内容读取失败但不是 NotFound，不能当作空文件覆盖
+        let bytes = b"[toolchain]\nchannel = \"\xff\"\n";
+        fs::write(dir.join(TOOLCHAIN_FILE), bytes).unwrap();
+        let spec = spec("[toolchain]\nchannel = \"stable\"\n");
+        assert!(save_toolchain_file(&dir.to_string_lossy(), spec).is_err());
+        assert_eq!(fs::read(dir.join(TOOLCHAIN_FILE)).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::config::{self, CargoConfig, KeyChange, LoadedConfig};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::history;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const UNDO_FILE: &str = "undo.json";
+const MAX_UNDO_ENTRIES: usize = 100;
+
+// 同一进程内串行化对撤销记录文件的读改写
+static UNDO_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub source: String,
+    pub summary: String,
+    pub changes: Vec<KeyChange>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UndoStatus {
+    pub undo: Vec<UndoEntry>,
+    pub redo: Vec<UndoEntry>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UndoOutcome {
+    pub entry: UndoEntry,
+    pub loaded: LoadedConfig,
+    pub status: UndoStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct UndoStack {
+    next_id: u64,
+    undo: Vec<UndoEntry>,
+    redo: Vec<UndoEntry>,
+}
+
+fn get_undo_path(config_path: Option<&str>) -> PathBuf {
+    config::get_backup_dir(config_path).join(UNDO_FILE)
+}
+
+fn load_stack(config_path: Option<&str>) -> UndoStack {
+    fs::read_to_string(get_undo_path(config_path))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_stack(config_path: Option<&str>, stack: &UndoStack) -> AppResult<()> {
+    let path = get_undo_path(config_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| AppError::io("创建备份目录失败", e))?;
+    }
+    let content = serde_json::to_string_pretty(stack).map_err(AppError::serialize)?;
+    config::atomic_write(&path, &content)
+}
+
+fn status_of(stack: &UndoStack) -> UndoStatus {
+    UndoStatus {
+        undo: stack.undo.iter().rev().cloned().collect(),
+        redo: stack.redo.iter().rev().cloned().collect(),
+    }
+}
+
+// 经 CargoConfig 往返一次再展开，保证记录时与撤销时比较的是同一种表示
+fn normalized_value(content: &str) -> Option<toml::Value> {
+    if content.trim().is_empty() {
+        return Some(toml::Value::Table(toml::Table::new()));
+    }
+    let config: CargoConfig = toml::from_str(content).ok()?;
+    config::config_to_value(&config).ok()
+}
+
+/// 读取保存前的配置原文，供 `record_change` 计算差异
+pub fn read_before(config_path: Option<&str>) -> String {
+    fs::read_to_string(config::resolve_config_path(config_path)).unwrap_or_default()
+}
+
+/// 把一次写入记录为可逆补丁并清空重做栈；内容无变化或任一侧无法解析时不记录
+pub fn record_change(
+    config_path: Option<&str>,
+    before: &str,
+    source: &str,
+) -> AppResult<Option<UndoEntry>> {
+    let after = read_before(config_path);
+    let (Some(old_value), Some(new_value)) = (normalized_value(before), normalized_value(&after))
+    else {
+        return Ok(None);
+    };
+    let changes = config::diff_config_values(&old_value, &new_value);
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut stack = load_stack(config_path);
+    stack.next_id += 1;
+    let entry = UndoEntry {
+        id: stack.next_id,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        source: source.to_string(),
+        summary: history::build_commit_message(&changes, false).0,
+        changes,
+    };
+    stack.undo.push(entry.clone());
+    if stack.undo.len() > MAX_UNDO_ENTRIES {
+        let overflow = stack.undo.len() - MAX_UNDO_ENTRIES;
+        stack.undo.drain(..overflow);
+    }
+    stack.redo.clear();
+    save_stack(config_path, &stack)?;
+    Ok(Some(entry))
+}
+
+pub fn get_undo_status(config_path: Option<&str>) -> UndoStatus {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    status_of(&load_stack(config_path))
+}
+
+// 撤销时把键从 new 改回 old，重做时反之；当前值与预期不符说明配置已被其他修改覆盖
+fn apply_changes(
+    config_path: Option<&str>,
+    changes: &[KeyChange],
+    undo: bool,
+) -> AppResult<LoadedConfig> {
+    let loaded = config::load_config_with_revision(config_path)?;
+    let current = config::config_to_value(&loaded.config)?;
+    let flat = config::flatten_config_value(&current);
+
+    let pick = |change: &KeyChange, new: bool| {
+        if new {
+            change.new.clone()
+        } else {
+            change.old.clone()
+        }
+    };
+    let stale: Vec<&str> = changes
+        .iter()
+        .filter(|change| flat.get(&change.key).cloned() != pick(change, undo))
+        .map(|change| change.key.as_str())
+        .collect();
+    if let Some(first) = stale.first() {
+        return Err(AppError::new(ErrorCode::Conflict, "配置已被其他修改覆盖，无法撤销或重做此记录")
+            .with_key(*first)
+            .with_detail(stale.join(", ")));
+    }
+
+    let mut table = match current {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+    // 先删除再写入，避免父表与子键互相覆盖
+    for change in changes {
+        if pick(change, !undo).is_none() {
+            config::remove_key_path(&mut table, &change.key);
+        }
+    }
+    for change in changes {
+        if let Some(value) = pick(change, !undo) {
+            if !config::insert_key_path(&mut table, &change.key, value) {
+                return Err(AppError::new(ErrorCode::Conflict, "配置结构已变化，无法应用此记录")
+                    .with_key(change.key.clone()));
+            }
+        }
+    }
+
+    let config: CargoConfig = toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| AppError::internal("撤销结果无法转换为配置").with_detail(e))?;
+    config::save_config_checked(&config, config_path, Some(&loaded.revision))?;
+    config::load_config_with_revision(config_path)
+}
+
+fn step(config_path: Option<&str>, undo: bool) -> AppResult<UndoOutcome> {
+    let _guard = UNDO_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut stack = load_stack(config_path);
+    let source = if undo { &mut stack.undo } else { &mut stack.redo };
+    let entry = source.pop().ok_or_else(|| {
+        AppError::not_found(if undo { "没有可撤销的修改" } else { "没有可重做的修改" })
+    })?;
+
+    let loaded = apply_changes(config_path, &entry.changes, undo)?;
+    if undo {
+        stack.redo.push(entry.clone());
+    } else {
+        stack.undo.push(entry.clone());
+    }
+    save_stack(config_path, &stack)?;
+    Ok(UndoOutcome {
+        entry,
+        loaded,
+        status: status_of(&stack),
+    })
+}
+
+pub fn undo_config_change(config_path: Option<&str>) -> AppResult<UndoOutcome> {
+    step(config_path, true)
+}
+
+pub fn redo_config_change(config_path: Option<&str>) -> AppResult<UndoOutcome> {
+    step(config_path, false)
+}
@@ -0,0 +1,266 @@
+use crate::config::{self, BackupEntry};
+use crate::error::AppResult;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, Once};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// 只监视一个小文件，用轮询 mtime/大小代替系统文件通知，省去额外依赖且各平台行为一致；
+// 每秒检查一次，变化后文件需保持 2 秒不变才处理：编辑器保存时常常分多次写入（截断、写入、
+// 重命名），防抖窗口至少覆盖两个轮询周期，避免对写到一半的文件做快照
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+const DEBOUNCE: Duration = Duration::from_millis(2000);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigChangedEvent {
+    pub path: String,
+    pub revision: String,
+    pub snapshot: Option<BackupEntry>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WatchStatus {
+    pub enabled: bool,
+    pub path: Option<String>,
+    pub interval_minutes: Option<u64>,
+    pub last_snapshot: Option<BackupEntry>,
+    pub last_error: Option<String>,
+}
+
+type Fingerprint = Option<(Option<SystemTime>, u64)>;
+
+#[derive(Default)]
+struct WatchState {
+    path: Option<PathBuf>,
+    snapshots: bool,
+    interval: Option<Duration>,
+    fingerprint: Fingerprint,
+    known_hash: Option<u64>,
+    pending_since: Option<Instant>,
+    last_scheduled: Option<Instant>,
+    scheduled_hash: Option<u64>,
+    last_snapshot: Option<BackupEntry>,
+    last_error: Option<String>,
+}
+
+type ChangeListener = Box<dyn Fn(ConfigChangedEvent) + Send>;
+
+static STATE: Mutex<Option<WatchState>> = Mutex::new(None);
+static LISTENER: Mutex<Option<ChangeListener>> = Mutex::new(None);
+static START: Once = Once::new();
+
+fn lock_state() -> MutexGuard<'static, Option<WatchState>> {
+    STATE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn fingerprint(path: &Path) -> Fingerprint {
+    fs::metadata(path)
+        .ok()
+        .map(|metadata| (metadata.modified().ok(), metadata.len()))
+}
+
+fn content_hash(path: &Path) -> Option<u64> {
+    fs::read(path).ok().map(|bytes| config::fnv1a64(&bytes))
+}
+
+// 一次轮询中需要在锁外完成的工作
+#[derive(Default)]
+struct TickPlan {
+    path: PathBuf,
+    changed: bool,
+    external_snapshot: bool,
+    scheduled_snapshot: bool,
+}
+
+fn tick(state: &mut WatchState) -> Option<TickPlan> {
+    let path = state.path.clone()?;
+
+    let current = fingerprint(&path);
+    if current != state.fingerprint {
+        state.fingerprint = current;
+        state.pending_since = Some(Instant::now());
+        return None;
+    }
+
+    let mut plan = TickPlan {
+        path: path.clone(),
+        ..Default::default()
+    };
+    if let Some(since) = state.pending_since {
+        if since.elapsed() >= DEBOUNCE {
+            state.pending_since = None;
+            let hash = content_hash(&path);
+            if hash != state.known_hash {
+                state.known_hash = hash;
+                plan.changed = true;
+                if state.snapshots && hash.is_some() {
+                    state.scheduled_hash = hash;
+                    plan.external_snapshot = true;
+                }
+            }
+        }
+    }
+
+    if let Some(interval) = state.interval.filter(|_| state.snapshots) {
+        let due = state
+            .last_scheduled
+            .map(|at| at.elapsed() >= interval)
+            .unwrap_or(true);
+        if due {
+            state.last_scheduled = Some(Instant::now());
+            let hash = content_hash(&path);
+            if hash.is_some() && hash != state.scheduled_hash {
+                state.scheduled_hash = hash;
+                plan.scheduled_snapshot = true;
+            }
+        }
+    }
+    (plan.changed || plan.scheduled_snapshot).then_some(plan)
+}
+
+// 写备份文件较慢，在锁外进行，完成后再记录结果
+fn snapshot(path: &Path, kind: &str) -> Option<BackupEntry> {
+    let result = config::create_tagged_backup(path, kind);
+    let mut guard = lock_state();
+    let state = guard.as_mut()?;
+    match result {
+        Ok(entry) => {
+            state.last_snapshot = Some(entry.clone());
+            state.last_error = None;
+            Some(entry)
+        }
+        Err(err) => {
+            state.last_error = Some(err.to_string());
+            None
+        }
+    }
+}
+
+fn run_plan(plan: TickPlan) -> Option<ConfigChangedEvent> {
+    let snapshot_entry = if plan.external_snapshot {
+        snapshot(&plan.path, "external")
+    } else {
+        None
+    };
+    if plan.scheduled_snapshot {
+        let _ = snapshot(&plan.path, "scheduled");
+    }
+    plan.changed.then(|| ConfigChangedEvent {
+        path: plan.path.to_string_lossy().to_string(),
+        revision: config::get_config_revision(&plan.path),
+        snapshot: snapshot_entry,
+    })
+}
+
+fn ensure_thread() {
+    START.call_once(|| {
+        thread::spawn(|| loop {
+            thread::sleep(POLL_INTERVAL);
+            let plan = lock_state().as_mut().and_then(tick);
+            if let Some(event) = plan.and_then(run_plan) {
+                let listener = LISTENER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if let Some(listener) = listener.as_ref() {
+                    listener(event);
+                }
+            }
+        });
+    });
+}
+
+fn apply(path: PathBuf, snapshots: bool, interval_minutes: Option<u64>) {
+    let mut guard = lock_state();
+    let state = guard.get_or_insert_with(WatchState::default);
+    if state.path.as_deref() != Some(path.as_path()) {
+        let hash = content_hash(&path);
+        state.fingerprint = fingerprint(&path);
+        state.known_hash = hash;
+        state.scheduled_hash = hash;
+        state.pending_since = None;
+        state.last_scheduled = None;
+        state.path = Some(path);
+    }
+    state.snapshots = snapshots;
+    state.last_scheduled.get_or_insert_with(Instant::now);
+    state.interval = interval_minutes
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes * 60));
+    drop(guard);
+    ensure_thread();
+}
+
+/// 注册外部修改回调，由 Tauri 层转发为前端事件
+pub fn set_listener<F>(listener: F)
+where
+    F: Fn(ConfigChangedEvent) + Send + 'static,
+{
+    let mut guard = LISTENER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(Box::new(listener));
+}
+
+pub fn start_from_settings() {
+    let settings = config::load_app_settings();
+    let path = config::resolve_config_path(settings.watch_path.as_deref());
+    apply(path, settings.watch_enabled, settings.backup_interval_minutes);
+}
+
+/// 前端加载配置时调用：未设置监视路径时跟随当前加载的文件，设置了则以设置为准
+pub fn track(path: Option<&str>) {
+    let settings = config::load_app_settings();
+    if settings.watch_path.is_some() {
+        return;
+    }
+    apply(
+        config::resolve_config_path(path),
+        settings.watch_enabled,
+        settings.backup_interval_minutes,
+    );
+}
+
+pub fn get_watch_status() -> WatchStatus {
+    let settings = config::load_app_settings();
+    let guard = lock_state();
+    let state = guard.as_ref();
+    WatchStatus {
+        enabled: settings.watch_enabled,
+        path: state
+            .and_then(|s| s.path.as_ref())
+            .map(|p| p.to_string_lossy().to_string()),
+        interval_minutes: settings.backup_interval_minutes,
+        last_snapshot: state.and_then(|s| s.last_snapshot.clone()),
+        last_error: state.and_then(|s| s.last_error.clone()),
+    }
+}
+
+pub fn set_config_watch(
+    path: Option<String>,
+    enabled: bool,
+    interval_minutes: Option<u64>,
+) -> AppResult<WatchStatus> {
+    let mut settings = config::load_app_settings();
+    settings.watch_enabled = enabled;
+    settings.watch_path = path.filter(|p| !p.trim().is_empty());
+    settings.backup_interval_minutes = interval_minutes.filter(|minutes| *minutes > 0);
+    config::save_app_settings(&settings)?;
+
+    apply(
+        config::resolve_config_path(settings.watch_path.as_deref()),
+        enabled,
+        settings.backup_interval_minutes,
+    );
+    Ok(get_watch_status())
+}
+
+/// 应用自身写入配置后调用，避免把自己的保存当成外部修改
+pub fn mark_saved(path: Option<&str>) {
+    let path = config::resolve_config_path(path);
+    let mut guard = lock_state();
+    if let Some(state) = guard.as_mut() {
+        if state.path.as_deref() == Some(path.as_path()) {
+            state.fingerprint = fingerprint(&path);
+            state.known_hash = content_hash(&path);
+            state.pending_since = None;
+        }
+    }
+}
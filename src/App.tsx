@@ -1,11 +1,13 @@
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { AdminStatus, CargoConfig } from "./types";
-import { MIRRORS } from "@/lib/mirrors";
+import { AdminStatus, CargoConfig, ConfigChangedEvent, LoadedConfig, SkippedSection } from "./types";
+import { useMirrors } from "@/lib/mirrors";
 import { store } from "@/lib/store";
 import { cleanEmptyValues } from "@/lib/config";
 import { ConfirmAction, ConfirmOptions, ConfirmTone } from "@/lib/confirm";
+import { formatError, isAppError } from "@/lib/utils";
 
 // Tabs
 import { RegistryTab } from "@/components/tabs/RegistryTab";
@@ -30,6 +32,7 @@ type ConfirmState = {
 
 function App() {
   const [config, setConfig] = useState<CargoConfig>({});
+  const { crates: cratesMirrors } = useMirrors();
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
   const [activeTab, setActiveTab] = useState<TabType>((store.get("lastActiveTab", "registry") as TabType) || "registry");
@@ -66,6 +69,8 @@ function App() {
   const confirmResolver = useRef<((value: boolean) => void) | null>(null);
   const dirtyRef = useRef(false);
   const allowCloseRef = useRef(false);
+  const revisionRef = useRef<string | undefined>(undefined);
+  const skippedRef = useRef<SkippedSection[]>([]);
 
   const showToast = (message: string, type: "success" | "error" = "success") => {
     const title = type === "success" ? "操作成功" : "操作失败";
@@ -140,7 +145,7 @@ function App() {
       setCustomCratesSource(null);
       return;
     }
-    const mirror = MIRRORS.find(m => m.replaceWith === replaceWith);
+    const mirror = cratesMirrors.find(m => m.replaceWith === replaceWith);
     if (mirror) {
       setSelectedMirror(mirror.id);
       setCustomCratesSource(null);
@@ -151,7 +156,7 @@ function App() {
         registry: config.source?.[replaceWith]?.registry
       });
     }
-  }, [config]);
+  }, [config, cratesMirrors]);
 
   async function loadConfigPath() {
     try {
@@ -304,17 +309,71 @@ function App() {
     };
   }, [confirmAction, forceCloseWindow]);
 
-  async function loadConfig(pathOverride?: string) {
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    const setup = async () => {
+      unlisten = await listen<ConfigChangedEvent>("config-changed", async (event) => {
+        if (event.payload.revision === revisionRef.current) return;
+        if (dirtyRef.current) {
+          showToast("配置文件已在外部被修改，保存前请先重新加载", "error");
+          return;
+        }
+        await loadConfig();
+      });
+    };
+    setup();
+    return () => {
+      if (unlisten) unlisten();
+    };
+  }, [configPath]);
+
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    const setup = async () => {
+      // 配置已保存，只是 git 历史没有提交成功
+      unlisten = await listen<unknown>("history-error", (event) => {
+        showToast("历史记录提交失败: " + formatError(event.payload), "error");
+      });
+    };
+    setup();
+    return () => {
+      if (unlisten) unlisten();
+    };
+  }, []);
+
+  async function loadConfig(pathOverride?: string, recover = false) {
     setLoading(true);
     try {
       const resolvedPath = pathOverride || configPath || undefined;
-      const c = await invoke<CargoConfig>("get_config", resolvedPath ? { path: resolvedPath } : undefined);
+      const loaded = await invoke<LoadedConfig>("get_config", { path: resolvedPath, recover });
+      const c = loaded.config;
+      revisionRef.current = loaded.revision;
+      skippedRef.current = loaded.skipped;
       setConfig(c);
       setSavedSnapshot(stableStringify(c));
       setHasSnapshot(true);
-      showToast("配置已加载", "success");
+      if (loaded.skipped.length > 0) {
+        const headers = loaded.skipped.map(s => `${s.header || "(根)"} 第 ${s.line} 行`).join("、");
+        showToast(`已跳过无法解析的部分：${headers}`, "error");
+      } else {
+        showToast("配置已加载", "success");
+      }
     } catch (e) {
-      showToast("加载失败: " + e, "error");
+      // 解析失败时提供恢复模式，先加载能解析的部分，方便在应用内修复
+      if (!recover && isAppError(e) && e.code === "parse") {
+        const confirmed = await confirmAction({
+          title: "配置文件解析失败",
+          message: `${formatError(e)}\n\n${e.snippet ?? ""}\n是否以恢复模式打开？无法解析的表将被跳过。`,
+          okLabel: "恢复模式打开",
+          cancelLabel: "取消",
+          tone: "warning"
+        });
+        if (confirmed) {
+          await loadConfig(pathOverride, true);
+          return;
+        }
+      }
+      showToast("加载失败: " + formatError(e), "error");
     } finally {
       setLoading(false);
     }
@@ -339,7 +398,23 @@ function App() {
     try {
     const cleanConfig = buildConfigForExport();
     const resolvedPath = configPath || defaultConfigPath;
-    if (resolvedPath) {
+    const skipped = skippedRef.current;
+    if (resolvedPath && skipped.length > 0) {
+      const confirmed = await confirmAction({
+        title: "覆盖无法解析的内容？",
+        message: `当前配置以恢复模式加载，保存会移除以下无法解析的部分：\n${skipped.map(s => s.header || "(根)").join("、")}\n将先自动备份原文件，是否继续？`,
+        okLabel: "备份并保存",
+        cancelLabel: "取消",
+        tone: "warning"
+      });
+      if (!confirmed) return;
+      try {
+        await invoke("create_backup", { path: resolvedPath, label: "recovery" });
+      } catch (e) {
+        showToast("备份失败，已取消保存: " + formatError(e), "error");
+        return;
+      }
+    } else if (resolvedPath) {
       const hasConfig = await invoke<boolean>("check_file_exists", { path: resolvedPath });
       if (hasConfig) {
         const shouldBackup = await confirmAction({
@@ -353,19 +428,24 @@ function App() {
           try {
             await invoke("create_backup", { path: resolvedPath });
           } catch (e) {
-            showToast("备份失败，已取消保存: " + e, "error");
+            showToast("备份失败，已取消保存: " + formatError(e), "error");
             return;
           }
         }
       }
     }
-    await invoke("save_config", { config: cleanConfig, path: resolvedPath || undefined });
+    revisionRef.current = await invoke<string>("save_config", {
+      config: cleanConfig,
+      path: resolvedPath || undefined,
+      revision: revisionRef.current
+    });
+    skippedRef.current = [];
     setConfig(cleanConfig);
     setSavedSnapshot(stableStringify(cleanConfig));
     setHasSnapshot(true);
     showToast("配置已保存", "success");
     } catch (e) {
-      showToast("保存失败: " + e, "error");
+      showToast("保存失败: " + formatError(e), "error");
     } finally {
       setSaving(false);
     }
@@ -373,8 +453,8 @@ function App() {
 
   const buildConfigForExport = () => {
     let newSource = { ...config.source };
-    const managedMirrorKeys = MIRRORS.filter(m => m.id !== "official").map(m => m.replaceWith);
-    const mirror = MIRRORS.find(m => m.id === selectedMirror);
+    const managedMirrorKeys = cratesMirrors.filter(m => m.id !== "official").map(m => m.replaceWith);
+    const mirror = cratesMirrors.find(m => m.id === selectedMirror);
     if (mirror && mirror.id !== "official") {
       newSource["crates-io"] = { "replace-with": mirror.replaceWith };
       newSource[mirror.replaceWith] = { registry: mirror.registry };
@@ -400,7 +480,7 @@ function App() {
       const folder = getConfigFolderFromPath(resolvedPath);
       await invoke("open_folder", { path: folder || resolvedPath || undefined });
     } catch (e) {
-      showToast("打开失败: " + e, "error");
+      showToast("打开失败: " + formatError(e), "error");
     }
   }
 
@@ -1,11 +1,12 @@
 import { useEffect, useState, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { open, save } from "@tauri-apps/plugin-dialog";
-import { BackupEntry, CargoConfig } from "@/types";
+import { BackupEntry, CargoConfig, ExportResult, ImportMergeResult, ImportResult, ImportStrategy } from "@/types";
 import { Prism as SyntaxHighlighter } from 'react-syntax-highlighter';
 import { vscDarkPlus } from 'react-syntax-highlighter/dist/esm/styles/prism';
 import { GlassOverlay } from "@/components/GlassOverlay";
 import { ConfirmAction } from "@/lib/confirm";
+import { formatError } from "@/lib/utils";
 
 
 interface Props {
@@ -36,6 +37,7 @@ export function BackupTab({
   const [customName, setCustomName] = useState("");
   const [loadingBackups, setLoadingBackups] = useState(false);
   const [working, setWorking] = useState(false);
+  const [importMode, setImportMode] = useState<"replace" | ImportStrategy>("replace");
   
   // Preview Hover State
   const [hoverPreview, setHoverPreview] = useState<string | null>(null);
@@ -89,7 +91,7 @@ export function BackupTab({
         if (token !== preloadTokenRef.current) return;
         setPreviewCache(prev => new Map(prev).set(entry.path, text));
       } catch (e) {
-        setPreviewCache(prev => new Map(prev).set(entry.path, "// 加载失败\n" + e));
+        setPreviewCache(prev => new Map(prev).set(entry.path, "// 加载失败\n" + formatError(e)));
       } finally {
         previewLoadingRef.current.delete(entry.path);
       }
@@ -124,7 +126,7 @@ export function BackupTab({
       // 预取部分备份预览，避免阻塞 UI
       preloadBackupPreviews(items);
     } catch (e) {
-      showToast("读取备份失败: " + e, "error");
+      showToast("读取备份失败: " + formatError(e), "error");
     } finally {
       setLoadingBackups(false);
     }
@@ -139,7 +141,7 @@ export function BackupTab({
       showToast("备份已创建", "success");
       await refreshBackups();
     } catch (e) {
-      showToast("备份失败: " + e, "error");
+      showToast("备份失败: " + formatError(e), "error");
     } finally {
       setWorking(false);
     }
@@ -160,7 +162,7 @@ export function BackupTab({
       showToast(`已清除 ${count} 个备份文件`, "success");
       await refreshBackups();
     } catch (e) {
-      showToast("清除失败: " + e, "error");
+      showToast("清除失败: " + formatError(e), "error");
     } finally {
       setWorking(false);
     }
@@ -181,7 +183,7 @@ export function BackupTab({
       await reloadConfig();
       showToast("已恢复备份", "success");
     } catch (e) {
-      showToast("恢复失败: " + e, "error");
+      showToast("恢复失败: " + formatError(e), "error");
     } finally {
       setWorking(false);
     }
@@ -208,7 +210,7 @@ export function BackupTab({
       });
       await refreshBackups();
     } catch (e) {
-      showToast("删除失败: " + e, "error");
+      showToast("删除失败: " + formatError(e), "error");
     } finally {
       setWorking(false);
     }
@@ -239,7 +241,7 @@ export function BackupTab({
       // 刷新列表会重新加载缓存
       await refreshBackups();
     } catch (e) {
-      showToast("重命名失败: " + e, "error");
+      showToast("重命名失败: " + formatError(e), "error");
     } finally {
       setWorking(false);
     }
@@ -255,23 +257,51 @@ export function BackupTab({
     try {
       const selected = await open({
         title: "导入配置文件",
-        filters: [{ name: "TOML", extensions: ["toml"] }],
+        filters: [
+          { name: "TOML", extensions: ["toml"] },
+          { name: "JSON", extensions: ["json"] },
+          { name: "YAML", extensions: ["yml", "yaml"] },
+          { name: "dotenv", extensions: ["env"] }
+        ],
         multiple: false,
         directory: false
       });
       if (typeof selected === "string" && selected) {
-        setWorking(true);
-        try {
-          const imported = await invoke<CargoConfig>("import_config", { path: selected });
-          setConfig(imported);
-          showToast("配置已导入，请保存后写入磁盘", "success");
-        } finally {
-          setWorking(false);
-        }
+        await importFrom(selected);
       }
     } catch (e) {
+      showToast("导入失败: " + formatError(e), "error");
+    }
+  }
+
+  // path 为空时从当前进程的 CARGO_* 环境变量导入
+  async function importFrom(path: string | null) {
+    setWorking(true);
+    try {
+      if (importMode !== "replace") {
+        const merged = await invoke<ImportMergeResult>("merge_import", {
+          config: buildExportConfig(),
+          path,
+          strategy: importMode,
+        });
+        setConfig(merged.merged);
+        showToast(
+          `已合并：新增 ${merged.added.length} 项，覆盖 ${merged.overridden.length} 项，跳过 ${merged.skipped.length} 项，请保存后写入磁盘`,
+          "success"
+        );
+        return;
+      }
+      const result = await invoke<ImportResult>("import_config", { path });
+      setConfig(result.config);
+      if (result.skipped.length > 0) {
+        showToast(`配置已导入，${result.skipped.length} 个变量无法识别已跳过，请保存后写入磁盘`, "success");
+      } else {
+        showToast("配置已导入，请保存后写入磁盘", "success");
+      }
+    } catch (e) {
+      showToast("导入失败: " + formatError(e), "error");
+    } finally {
       setWorking(false);
-      showToast("导入失败: " + e, "error");
     }
   }
 
@@ -284,20 +314,31 @@ export function BackupTab({
       const path = await save({
         title: "导出配置文件",
         defaultPath: defaultName,
-        filters: [{ name: "TOML", extensions: ["toml"] }]
+        // 非 TOML 格式由后端按扩展名推断，导出为 CARGO_* 环境变量
+        filters: [
+          { name: "TOML", extensions: ["toml"] },
+          { name: "dotenv", extensions: ["env"] },
+          { name: "Shell", extensions: ["sh"] },
+          { name: "GitHub Actions env", extensions: ["yml", "yaml"] },
+          { name: "Dockerfile", extensions: ["dockerfile"] }
+        ]
       });
       if (typeof path === "string" && path) {
         setWorking(true);
         try {
-          await invoke("export_config", { path, config: buildExportConfig() });
-          showToast("配置已导出", "success");
+          const result = await invoke<ExportResult>("export_config", { path, config: buildExportConfig() });
+          if (result.skipped.length > 0) {
+            showToast(`配置已导出，${result.skipped.length} 项无法以环境变量表示或为敏感信息，已跳过`, "success");
+          } else {
+            showToast("配置已导出", "success");
+          }
         } finally {
           setWorking(false);
         }
       }
     } catch (e) {
       setWorking(false);
-      showToast("导出失败: " + e, "error");
+      showToast("导出失败: " + formatError(e), "error");
     }
   }
 
@@ -313,7 +354,7 @@ export function BackupTab({
         await updateConfigPath(`${normalized}/config.toml`);
       }
     } catch (e) {
-      showToast("选择目录失败: " + e, "error");
+      showToast("选择目录失败: " + formatError(e), "error");
     }
   }
 
@@ -330,7 +371,7 @@ export function BackupTab({
         await updateConfigPath(normalized);
       }
     } catch (e) {
-      showToast("选择文件失败: " + e, "error");
+      showToast("选择文件失败: " + formatError(e), "error");
     }
   }
 
@@ -345,7 +386,7 @@ export function BackupTab({
         : "";
       await invoke("open_folder", { path: folder || normalized || undefined });
     } catch (e) {
-      showToast("打开目录失败: " + e, "error");
+      showToast("打开目录失败: " + formatError(e), "error");
     }
   }
 
@@ -353,7 +394,7 @@ export function BackupTab({
     try {
       await invoke("open_config_file", { path: configPath || undefined });
     } catch (e) {
-      showToast("打开文件失败: " + e, "error");
+      showToast("打开文件失败: " + formatError(e), "error");
     }
   }
 
@@ -362,7 +403,7 @@ export function BackupTab({
       if (!backupDir) return;
       await invoke("open_folder", { path: backupDir });
     } catch (e) {
-      showToast("打开目录失败: " + e, "error");
+      showToast("打开目录失败: " + formatError(e), "error");
     }
   }
 
@@ -370,7 +411,7 @@ export function BackupTab({
     try {
       await invoke("open_config_file", { path: entry.path });
     } catch (e) {
-      showToast("打开文件失败: " + e, "error");
+      showToast("打开文件失败: " + formatError(e), "error");
     }
   }
 
@@ -424,7 +465,7 @@ export function BackupTab({
        }, 5000);
        
     } catch (e) {
-       const errorMsg = "// 读取失败或文件损坏\n" + e;
+       const errorMsg = "// 读取失败或文件损坏\n" + formatError(e);
        if (hoveringRef.current !== path) {
          setHoverLoading(false);
          return;
@@ -684,11 +725,26 @@ export function BackupTab({
           <div className="form-row">
             <div>
               <div className="form-label">导入配置</div>
-              <div className="form-hint">导入后需点击保存配置生效</div>
+              <div className="form-hint">支持 TOML / JSON / YAML / dotenv，导入后需点击保存配置生效</div>
+            </div>
+            <div style={{ display: "flex", gap: 8 }}>
+              <select
+                className="select"
+                value={importMode}
+                onChange={(e) => setImportMode(e.target.value as "replace" | ImportStrategy)}
+                disabled={working}
+              >
+                <option value="replace">替换全部</option>
+                <option value="keep_existing">合并，保留现有值</option>
+                <option value="prefer_imported">合并，优先导入值</option>
+              </select>
+              <button className="btn btn-secondary" onClick={handleImport} disabled={working}>
+                选择文件导入
+              </button>
+              <button className="btn btn-secondary" onClick={() => importFrom(null)} disabled={working}>
+                从环境变量导入
+              </button>
             </div>
-            <button className="btn btn-secondary" onClick={handleImport} disabled={working}>
-              选择文件导入
-            </button>
           </div>
           <div className="form-row">
             <div>
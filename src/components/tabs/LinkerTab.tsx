@@ -1,7 +1,8 @@
 import { useState, useEffect } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
 import { invoke } from "@tauri-apps/api/core";
-import { CargoConfig, TargetConfig, LINKER_OPTIONS, COMMON_RUSTFLAGS } from "@/types";
+import { CargoConfig, TargetConfig } from "@/types";
+import { useOptionLibrary } from "@/lib/presets";
 
 interface Props {
   config: CargoConfig;
@@ -12,13 +13,14 @@ interface Props {
 export function LinkerTab({ config, setConfig, currentTarget }: Props) {
   const [linkerStatus, setLinkerStatus] = useState<{ ok: boolean; mode: "file" | "command" } | null>(null);
   const [isManualCustom, setIsManualCustom] = useState(false);
+  const { linkers: linkerOptions, rustflags: commonRustflags } = useOptionLibrary();
 
   const getTargetLinker = () => {
     return config.target?.[currentTarget]?.linker || "";
   };
 
-  // Check if current linker is custom (not in presets) and not empty
-  const isCustomLinker = !LINKER_OPTIONS.some(l => l.value === getTargetLinker()) && getTargetLinker() !== "";
+  // Check if current linker is custom (not in presets) and not empty; wait until the presets have loaded
+  const isCustomLinker = linkerOptions.length > 0 && !linkerOptions.some(l => l.value === getTargetLinker()) && getTargetLinker() !== "";
   
   // Sync manual state with prop changes
   useEffect(() => {
@@ -150,7 +152,7 @@ export function LinkerTab({ config, setConfig, currentTarget }: Props) {
               value={showCustomInput ? "custom" : getTargetLinker()} 
               onChange={(e) => handleLinkerChange(e.target.value)}
             >
-              {LINKER_OPTIONS.map(l => (<option key={l.value} value={l.value}>{l.label}</option>))}
+              {linkerOptions.map(l => (<option key={l.value} value={l.value}>{l.label}</option>))}
               <option value="custom">自定义路径...</option>
             </select>
           </div>
@@ -211,7 +213,7 @@ export function LinkerTab({ config, setConfig, currentTarget }: Props) {
         <div className="card-content">
           {/* 常用 Flags 列表 - 紧凑网格布局 */}
           <div style={{ display: "grid", gridTemplateColumns: "1fr 1fr", gap: 8, marginBottom: 16 }}>
-             {COMMON_RUSTFLAGS.map(flag => {
+             {commonRustflags.map(flag => {
                const active = hasRustflag(flag.value);
                return (
                  <div 
@@ -225,7 +227,7 @@ export function LinkerTab({ config, setConfig, currentTarget }: Props) {
                    <div style={{ flex: 1, minWidth: 0 }}>
                      <div style={{ fontWeight: 500, fontSize: 12 }}>{flag.label}</div>
                      <div style={{ fontSize: 10, color: "var(--text-secondary)", marginTop: 1, whiteSpace: "nowrap", overflow: "hidden", textOverflow: "ellipsis" }}>
-                       {flag.desc}
+                       {flag.description}
                      </div>
                    </div>
                    <div 
@@ -243,10 +245,10 @@ export function LinkerTab({ config, setConfig, currentTarget }: Props) {
             className="input" 
             style={{ width: "100%", height: 70, resize: "vertical", fontFamily: "monospace", fontSize: 12 }}
             placeholder="每行一个参数，例如:&#10;-C link-arg=-s&#10;-C target-cpu=native"
-            value={(config.target?.[currentTarget]?.rustflags || []).filter(f => !COMMON_RUSTFLAGS.some(cf => cf.value === f)).join("\n")}
+            value={(config.target?.[currentTarget]?.rustflags || []).filter(f => !commonRustflags.some(cf => cf.value === f)).join("\n")}
             onChange={(e) => {
               const customFlags = e.target.value.split("\n").filter(f => f.trim());
-              const commonActiveFlags = COMMON_RUSTFLAGS.filter(cf => hasRustflag(cf.value)).map(cf => cf.value);
+              const commonActiveFlags = commonRustflags.filter(cf => hasRustflag(cf.value)).map(cf => cf.value);
               const allFlags = [...commonActiveFlags, ...customFlags];
               updateTarget(currentTarget, "rustflags", allFlags.length > 0 ? allFlags : undefined);
             }}
@@ -1,7 +1,9 @@
 
 import { useEffect, useState } from "react";
 import { open as openDialog } from "@tauri-apps/plugin-dialog";
-import { CargoConfig, NetConfig, HttpConfig, HttpsConfig, PROXY_PRESETS } from "@/types";
+import { CargoConfig, NetConfig, HttpConfig, HttpsConfig } from "@/types";
+import { useOptionLibrary } from "@/lib/presets";
+import { formatError } from "@/lib/utils";
 
 interface Props {
   config: CargoConfig;
@@ -14,10 +16,12 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
   const [httpsProxyMode, setHttpsProxyMode] = useState<"preset" | "custom">("preset");
   const [customHttpProxy, setCustomHttpProxy] = useState("");
   const [customHttpsProxy, setCustomHttpsProxy] = useState("");
+  const { proxies } = useOptionLibrary();
+  const proxyOptions = [...proxies, { value: "custom", label: "自定义..." }];
 
   useEffect(() => {
     const httpProxy = config.http?.proxy || "";
-    const httpIsPreset = PROXY_PRESETS.some(p => p.value === httpProxy);
+    const httpIsPreset = proxyOptions.some(p => p.value === httpProxy);
     if (httpProxy && !httpIsPreset) {
       setHttpProxyMode("custom");
       setCustomHttpProxy(httpProxy);
@@ -25,11 +29,11 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
       setHttpProxyMode("preset");
       setCustomHttpProxy("");
     }
-  }, [config.http?.proxy]);
+  }, [config.http?.proxy, proxies]);
 
   useEffect(() => {
     const httpsProxy = config.https?.proxy || "";
-    const httpsIsPreset = PROXY_PRESETS.some(p => p.value === httpsProxy);
+    const httpsIsPreset = proxyOptions.some(p => p.value === httpsProxy);
     if (httpsProxy && !httpsIsPreset) {
       setHttpsProxyMode("custom");
       setCustomHttpsProxy(httpsProxy);
@@ -37,7 +41,7 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
       setHttpsProxyMode("preset");
       setCustomHttpsProxy("");
     }
-  }, [config.https?.proxy]);
+  }, [config.https?.proxy, proxies]);
 
   const updateNet = (key: string, value: boolean) => {
     const newNet: NetConfig = { ...config.net };
@@ -116,7 +120,7 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
               updateHttp("cainfo", selected);
           }
       } catch (e) {
-          showToast("选择文件失败: " + e, "error");
+          showToast("选择文件失败: " + formatError(e), "error");
       }
   };
 
@@ -179,7 +183,7 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
                     value={httpProxyMode === "custom" ? "custom" : (config.http?.proxy || "")} 
                     onChange={(e) => updateHttpProxy(e.target.value)}
                   >
-                    {PROXY_PRESETS.map(p => (<option key={p.value} value={p.value}>{p.label}</option>))}
+                    {proxyOptions.map(p => (<option key={p.value} value={p.value}>{p.label}</option>))}
                   </select>
                   {httpProxyMode === "custom" && (
                     <div className="flex gap-2 flex-1">
@@ -204,7 +208,7 @@ export function NetworkTab({ config, setConfig, showToast }: Props) {
                     value={httpsProxyMode === "custom" ? "custom" : (config.https?.proxy || "")} 
                     onChange={(e) => updateHttpsProxy(e.target.value)}
                   >
-                    {PROXY_PRESETS.map(p => (<option key={p.value} value={p.value}>{p.label}</option>))}
+                    {proxyOptions.map(p => (<option key={p.value} value={p.value}>{p.label}</option>))}
                   </select>
                   {httpsProxyMode === "custom" && (
                     <div className="flex gap-2 flex-1">
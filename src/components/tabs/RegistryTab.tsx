@@ -2,9 +2,10 @@
 import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { AdminStatus, CargoConfig, RegistryEntry, RustupEnvStatus, RustupEnvWriteResult } from "@/types";
-import { MIRRORS } from "@/lib/mirrors";
+import { useMirrors } from "@/lib/mirrors";
 import { GlassOverlay } from "@/components/GlassOverlay";
 import { ConfirmAction } from "@/lib/confirm";
+import { formatError } from "@/lib/utils";
 
 interface Props {
   config: CargoConfig;
@@ -17,14 +18,6 @@ interface Props {
   confirmAction: ConfirmAction;
 }
 
-const RUSTUP_MIRRORS = [
-  { id: "official", name: "Official (官方)", dist: "", root: "" },
-  { id: "ustc", name: "USTC (中科大)", dist: "https://mirrors.ustc.edu.cn/rustup", root: "https://mirrors.ustc.edu.cn/rustup" },
-  { id: "tuna", name: "TUNA (清华)", dist: "https://mirrors.tuna.tsinghua.edu.cn/rustup", root: "https://mirrors.tuna.tsinghua.edu.cn/rustup" },
-  { id: "sjtu", name: "SJTU (上交)", dist: "https://mirrors.sjtug.sjtu.edu.cn/rust-static", root: "https://mirrors.sjtug.sjtu.edu.cn/rust-static/rustup" },
-  { id: "rsproxy", name: "Rsproxy (字节)", dist: "https://rsproxy.cn/rustup", root: "https://rsproxy.cn/rustup" },
-];
-
 export function RegistryTab({
   config,
   setConfig,
@@ -36,6 +29,7 @@ export function RegistryTab({
   confirmAction
 }: Props) {
   const registries = config.registries || {};
+  const { crates: cratesMirrors, rustup: rustupMirrors } = useMirrors();
   const [newKey, setNewKey] = useState("");
   const [newIndex, setNewIndex] = useState("");
   const [newToken, setNewToken] = useState("");
@@ -79,7 +73,7 @@ export function RegistryTab({
     const newSource = { ...(config.source || {}) };
     if (mirrorId === "official") {
       delete newSource["crates-io"];
-      for (const m of MIRRORS) {
+      for (const m of cratesMirrors) {
         if (m.id !== "official") {
           delete newSource[m.replaceWith];
         }
@@ -88,11 +82,11 @@ export function RegistryTab({
       if (showToast) showToast("已重置为官方源", "success");
       return;
     }
-    const mirror = MIRRORS.find(m => m.id === mirrorId);
+    const mirror = cratesMirrors.find(m => m.id === mirrorId);
     if (!mirror) return;
     newSource["crates-io"] = { "replace-with": mirror.replaceWith };
     newSource[mirror.replaceWith] = { registry: mirror.registry };
-    for (const m of MIRRORS) {
+    for (const m of cratesMirrors) {
       if (m.id !== "official" && m.replaceWith !== mirror.replaceWith) {
         delete newSource[m.replaceWith];
       }
@@ -107,7 +101,7 @@ export function RegistryTab({
       const status = await invoke<RustupEnvStatus>("get_rustup_env_status");
       setRustupStatus(status);
     } catch (e) {
-      if (showToast) showToast("读取 Rustup 镜像状态失败: " + e, "error");
+      if (showToast) showToast("读取 Rustup 镜像状态失败: " + formatError(e), "error");
     } finally {
       setRustupLoading(false);
     }
@@ -131,7 +125,7 @@ export function RegistryTab({
 
   const activeRustup = (() => {
     if (!distEffective && !rootEffective) return "official";
-    const found = RUSTUP_MIRRORS.find(m => m.dist === distEffective && m.root === rootEffective);
+    const found = rustupMirrors.find(m => m.dist === distEffective && m.root === rootEffective);
     return found ? found.id : "custom";
   })();
 
@@ -141,6 +135,7 @@ export function RegistryTab({
       const result = await invoke<RustupEnvWriteResult>("set_rustup_env", { dist, root });
       const systemSkipped = !!result.system.skipped;
       setRustupLastWrite({ dist, root });
+      if (result.warning && showToast) showToast(result.warning, "error");
       if (!result.user.ok) {
         if (showToast) showToast(`用户级写入失败: ${result.user.error || "未知错误"}`, "error");
       }
@@ -163,14 +158,14 @@ export function RegistryTab({
       }
       await loadRustupStatus();
     } catch (e) {
-      if (showToast) showToast("写入 Rustup 配置失败: " + e, "error");
+      if (showToast) showToast("写入 Rustup 配置失败: " + formatError(e), "error");
     } finally {
       setRustupWriting(false);
     }
   };
 
   const applyRustupMirror = async (mirrorId: string) => {
-    const mirror = RUSTUP_MIRRORS.find(m => m.id === mirrorId);
+    const mirror = rustupMirrors.find(m => m.id === mirrorId);
     if (!mirror) return;
     const dist = mirrorId === "official" ? null : mirror.dist;
     const root = mirrorId === "official" ? null : mirror.root;
@@ -228,9 +223,11 @@ export function RegistryTab({
     if (!confirmed) return;
     setRustupWriting(true);
     try {
+      // 首次写入时已探测过镜像
       const result = await invoke<RustupEnvWriteResult>("set_rustup_env", {
         dist: rustupLastWrite.dist,
-        root: rustupLastWrite.root
+        root: rustupLastWrite.root,
+        probe: false
       });
       if (!result.system.ok) {
         const err = sanitizeError(result.system.error) || "系统级未生效";
@@ -242,7 +239,7 @@ export function RegistryTab({
       }
       await loadRustupStatus();
     } catch (e) {
-      if (showToast) showToast("系统级重试失败: " + e, "error");
+      if (showToast) showToast("系统级重试失败: " + formatError(e), "error");
     } finally {
       setRustupWriting(false);
     }
@@ -264,7 +261,7 @@ export function RegistryTab({
                 <span style={{ fontSize: 12, color: "var(--text-secondary)" }}>替换 crates.io 默认源，加速依赖下载</span>
              </div>
              <div style={{ display: "flex", flexWrap: "wrap", gap: 8 }}>
-                {MIRRORS.map(m => {
+                {cratesMirrors.map(m => {
                   const isActive = selectedMirror === m.id;
                   return (
                     <button 
@@ -301,7 +298,7 @@ export function RegistryTab({
                 <span style={{ fontSize: 12, color: "var(--text-secondary)" }}>加速 rustup update 及 Toolchain 下载</span>
              </div>
              <div style={{ display: "flex", flexWrap: "wrap", gap: 8 }}>
-               {RUSTUP_MIRRORS.map(m => {
+               {rustupMirrors.map(m => {
                   const isActive = activeRustup === m.id;
                   return (
                     <button 
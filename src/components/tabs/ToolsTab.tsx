@@ -4,6 +4,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { CargoConfig, TARGET_PLATFORMS, WRAPPER_OPTIONS } from "@/types";
 import { GlassOverlay } from "@/components/GlassOverlay";
 import { ConfirmAction } from "@/lib/confirm";
+import { formatError } from "@/lib/utils";
 
 interface Props {
   config: CargoConfig;
@@ -52,7 +53,7 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
       const stats = await invoke<CacheStats>("get_cargo_cache_stats");
       setCacheStats(stats);
     } catch (e) {
-      showToast("获取缓存统计失败: " + e, "error");
+      showToast("获取缓存统计失败: " + formatError(e), "error");
     } finally {
       setLoadingCache(false);
     }
@@ -75,7 +76,7 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
       showToast("清理完成", "success");
       await loadCacheStats(); // Refresh
     } catch (e) {
-      showToast("清理失败: " + e, "error");
+      showToast("清理失败: " + formatError(e), "error");
     } finally {
       setCleaning(false);
     }
@@ -88,12 +89,33 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
       setSccacheInstalled(true);
       showToast("sccache 安装成功", "success");
     } catch (e) {
-      showToast("sccache 安装失败: " + e, "error");
+      showToast("sccache 安装失败: " + formatError(e), "error");
     } finally {
       setInstallingSccache(false);
     }
   }
 
+  async function handleRemoveTarget(target: string) {
+    const confirmed = await confirmAction({
+      title: "移除 Target",
+      message: `确定要移除 ${target} 的标准库吗？`,
+      okLabel: "确认移除",
+      cancelLabel: "取消",
+      tone: "warning"
+    });
+    if (!confirmed) return;
+    setInstallingTarget(target);
+    try {
+      await invoke("remove_target", { target });
+      await checkTools();
+      showToast(`Target ${target} 已移除`, "success");
+    } catch (e) {
+      showToast(`Target 移除失败: ${formatError(e)}`, "error");
+    } finally {
+      setInstallingTarget("");
+    }
+  }
+
   async function handleInstallTarget(target: string) {
     if (!target) return;
     setInstallingTarget(target);
@@ -102,7 +124,7 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
       await checkTools();
       showToast(`Target ${target} 安装成功`, "success");
     } catch (e) {
-      showToast(`Target 安装失败: ${e}`, "error");
+      showToast(`Target 安装失败: ${formatError(e)}`, "error");
     } finally {
       setInstallingTarget("");
     }
@@ -211,7 +233,13 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
                 value={config.build?.target || ""} 
                 onChange={(e) => updateBuild("target", e.target.value || undefined)}
               >
-                {TARGET_PLATFORMS.map(t => {
+                {[
+                  ...TARGET_PLATFORMS,
+                  // 通过命令行等方式安装、不在内置列表中的目标
+                  ...installedTargets
+                    .filter(name => !TARGET_PLATFORMS.some(t => t.value === name))
+                    .map(name => ({ value: name, label: name })),
+                ].map(t => {
                    const isInstalled = installedTargets.includes(t.value);
                    return (
                     <option key={t.value} value={t.value}>
@@ -229,6 +257,15 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
                   {installingTarget === config.build.target ? "⏳..." : "📥 安装"}
                 </button>
               )}
+              {config.build?.target && installedTargets.includes(config.build.target) && (
+                <button
+                  className="btn btn-secondary btn-sm"
+                  onClick={() => handleRemoveTarget(config.build!.target!)}
+                  disabled={!!installingTarget}
+                >
+                  {installingTarget === config.build.target ? "⏳..." : "🗑 移除"}
+                </button>
+              )}
             </div>
           </div>
           <div className="form-row">
@@ -242,7 +279,7 @@ export function ToolsTab({ config, setConfig, showToast, confirmAction }: Props)
           <div className="glass-panel">
             <div className="glass-spinner" />
             <div>
-              <div className="glass-title">正在安装 Target</div>
+              <div className="glass-title">正在处理 Target</div>
               <div className="glass-desc">{installingTarget || "请稍候…"}</div>
             </div>
           </div>
@@ -1,3 +1,7 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { MirrorCatalog, RustupMirrorInfo } from "@/types";
+
 export type Mirror = {
     id: string;
     name: string;
@@ -5,41 +9,48 @@ export type Mirror = {
     replaceWith: string; // usually 'mirror' or 'ustc' etc
 };
 
-export const MIRRORS: Mirror[] = [
-    {
-        id: "official",
-        name: "Official (crates.io)",
-        registry: "https://github.com/rust-lang/crates.io-index",
-        replaceWith: "crates-io", // Effectively reset
-    },
-    {
-        id: "ustc",
-        name: "USTC (中科大)",
-        registry: "sparse+https://mirrors.ustc.edu.cn/crates.io-index/",
-        replaceWith: "ustc",
-    },
-    {
-        id: "tuna",
-        name: "TUNA (清华大学)",
-        registry: "https://mirrors.tuna.tsinghua.edu.cn/git/crates.io-index.git",
-        replaceWith: "tuna",
-    },
-    {
-        id: "sjtu",
-        name: "SJTU (上海交大)",
-        registry: "https://mirrors.sjtug.sjtu.edu.cn/git/crates.io-index",
-        replaceWith: "sjtu",
-    },
-    {
-        id: "rsproxy",
-        name: "Rsproxy (字节跳动)",
-        registry: "sparse+https://rsproxy.cn/crates.io-index",
-        replaceWith: "rsproxy",
-    },
-    {
-        id: "aliyun",
-        name: "Aliyun (阿里云)",
-        registry: "https://code.aliyun.com/rustcc/crates.io-index.git",
-        replaceWith: "aliyun",
-    },
-];
+export type Mirrors = {
+    crates: Mirror[];
+    rustup: RustupMirrorInfo[];
+};
+
+const EMPTY_MIRRORS: Mirrors = { crates: [], rustup: [] };
+
+let pending: Promise<Mirrors> | null = null;
+
+// 镜像列表由后端 get_mirror_catalog 提供，与命令行模式共用同一份；失败时下次挂载再重试
+function loadMirrors(): Promise<Mirrors> {
+    if (!pending) {
+        pending = invoke<MirrorCatalog>("get_mirror_catalog")
+            .then(catalog => ({
+                crates: catalog.crates.map(m => ({
+                    id: m.id,
+                    name: m.name,
+                    registry: m.registry,
+                    replaceWith: m.replace_with,
+                })),
+                rustup: catalog.rustup,
+            }))
+            .catch(() => {
+                pending = null;
+                return EMPTY_MIRRORS;
+            });
+    }
+    return pending;
+}
+
+export function useMirrors(): Mirrors {
+    const [mirrors, setMirrors] = useState<Mirrors>(EMPTY_MIRRORS);
+
+    useEffect(() => {
+        let active = true;
+        loadMirrors().then(result => {
+            if (active) setMirrors(result);
+        });
+        return () => {
+            active = false;
+        };
+    }, []);
+
+    return mirrors;
+}
@@ -0,0 +1,34 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { OptionLibrary } from "@/types";
+
+const EMPTY_LIBRARY: OptionLibrary = { rustflags: [], linkers: [], proxies: [] };
+
+let pending: Promise<OptionLibrary> | null = null;
+
+// 选项列表在会话内不会变化，多个页面共用一次请求；失败时下次挂载再重试
+function loadOptionLibrary(): Promise<OptionLibrary> {
+  if (!pending) {
+    pending = invoke<OptionLibrary>("get_option_library").catch(() => {
+      pending = null;
+      return EMPTY_LIBRARY;
+    });
+  }
+  return pending;
+}
+
+export function useOptionLibrary(): OptionLibrary {
+  const [library, setLibrary] = useState<OptionLibrary>(EMPTY_LIBRARY);
+
+  useEffect(() => {
+    let active = true;
+    loadOptionLibrary().then(result => {
+      if (active) setLibrary(result);
+    });
+    return () => {
+      active = false;
+    };
+  }, []);
+
+  return library;
+}
@@ -1,6 +1,28 @@
 import { type ClassValue, clsx } from "clsx";
 import { twMerge } from "tailwind-merge";
+import type { AppError, ErrorCode } from "@/types";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
 }
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === "object" && e !== null && "code" in e && "message" in e;
+}
+
+export function isErrorCode(e: unknown, code: ErrorCode) {
+  return isAppError(e) && e.code === code;
+}
+
+// 后端命令返回 AppError，旧接口或插件可能仍返回字符串
+export function formatError(e: unknown): string {
+  if (!isAppError(e)) return String(e);
+  let text = e.message;
+  if (e.line != null) {
+    text += ` (第 ${e.line} 行${e.column != null ? `第 ${e.column} 列` : ""}${e.key ? `，${e.key}` : ""})`;
+  } else if (e.key) {
+    text += ` (${e.key})`;
+  }
+  if (e.detail) text += `: ${e.detail}`;
+  return text;
+}
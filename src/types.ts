@@ -85,6 +85,26 @@ export interface CargoConfig {
     [key: string]: any;
 }
 
+export interface LoadedConfig {
+    config: CargoConfig;
+    revision: string;
+    path: string;
+    skipped: SkippedSection[];
+}
+
+export interface SkippedSection {
+    header: string;
+    line: number;
+    content: string;
+    error: AppError;
+}
+
+export interface ConfigChangedEvent {
+    path: string;
+    revision: string;
+    snapshot?: BackupEntry;
+}
+
 export interface RustupEnvScopeStatus {
     value?: string;
     source?: string;
@@ -110,6 +130,34 @@ export interface RustupEnvWriteOutcome {
 export interface RustupEnvWriteResult {
     user: RustupEnvWriteOutcome;
     system: RustupEnvWriteOutcome;
+    warning?: string | null;
+}
+
+export interface CratesMirrorInfo {
+    id: string;
+    name: string;
+    registry: string;
+    replace_with: string;
+}
+
+export interface RustupMirrorInfo {
+    id: string;
+    name: string;
+    dist: string;
+    root: string;
+    crates: string;
+}
+
+export interface MirrorCatalog {
+    crates: CratesMirrorInfo[];
+    rustup: RustupMirrorInfo[];
+}
+
+export interface RustupProbe {
+    url: string;
+    status: number;
+    elapsed_ms: number;
+    checksum: string;
 }
 
 export interface AdminStatus {
@@ -122,6 +170,388 @@ export interface BackupEntry {
     path: string;
     modified: number;
     size: number;
+    kind: "auto" | "manual" | "external" | "scheduled" | "profile" | "preset" | "custom";
+}
+
+export interface WatchStatus {
+    enabled: boolean;
+    path?: string;
+    interval_minutes?: number;
+    last_snapshot?: BackupEntry;
+    last_error?: string;
+}
+
+export interface BackupSettings {
+    root: string;
+    custom: boolean;
+    dir: string;
+    migrated: number;
+    conflicts: string[];
+}
+
+export interface BackupIssue {
+    name: string;
+    kind: "unreadable" | "truncated" | "hash_mismatch" | "parse_error";
+    message: string;
+}
+
+export interface BackupVerifyReport {
+    checked: number;
+    ok: number;
+    unverified: number;
+    issues: BackupIssue[];
+    quarantined: string[];
+}
+
+export interface KeyChange {
+    key: string;
+    kind: "added" | "removed" | "changed";
+    old?: any;
+    new?: any;
+}
+
+export type MergeChoice = "base" | "local" | "remote";
+
+export interface MergedKey {
+    key: string;
+    source: MergeChoice;
+    value?: any;
+}
+
+export interface MergeConflict {
+    key: string;
+    base?: any;
+    local?: any;
+    remote?: any;
+}
+
+export interface DiskMergeResult {
+    merged: CargoConfig;
+    auto_merged: MergedKey[];
+    conflicts: MergeConflict[];
+    clean: boolean;
+    remote: CargoConfig;
+    revision: string;
+}
+
+export interface HistoryStatus {
+    enabled: boolean;
+    initialized: boolean;
+    repo: string;
+}
+
+export interface HistoryEntry {
+    id: string;
+    short_id: string;
+    subject: string;
+    body: string;
+    timestamp: number;
+}
+
+export interface HistoryDiff {
+    entry: HistoryEntry;
+    patch: string;
+    changes: KeyChange[];
+}
+
+export interface UndoEntry {
+    id: number;
+    timestamp: number;
+    source: string;
+    summary: string;
+    changes: KeyChange[];
+}
+
+export interface UndoStatus {
+    undo: UndoEntry[];
+    redo: UndoEntry[];
+}
+
+export interface UndoOutcome {
+    entry: UndoEntry;
+    loaded: LoadedConfig;
+    status: UndoStatus;
+}
+
+export interface RustupProfile {
+    dist?: string | null;
+    root?: string | null;
+}
+
+export interface ConfigProfile {
+    name: string;
+    description?: string | null;
+    overlay: CargoConfig;
+    unset: string[];
+    rustup?: RustupProfile | null;
+    updated: number;
+}
+
+export interface ProfileSummary extends ConfigProfile {
+    active: boolean;
+}
+
+export interface ProfileDiff {
+    name: string;
+    changes: KeyChange[];
+    rustup_changed: boolean;
+}
+
+export type ExportFormat = "toml" | "dotenv" | "shell" | "github_actions" | "dockerfile";
+
+export interface SkippedKey {
+    key: string;
+    reason: string;
+}
+
+export interface ExportResult {
+    path: string;
+    format: ExportFormat;
+    exported: number;
+    skipped: SkippedKey[];
+}
+
+export type ImportFormat = "toml" | "json" | "yaml" | "dotenv" | "env";
+
+export interface ImportResult {
+    config: CargoConfig;
+    format: ImportFormat;
+    skipped: SkippedKey[];
+}
+
+export type ImportStrategy = "keep_existing" | "prefer_imported" | "skip";
+
+export interface ImportedKey {
+    key: string;
+    existing?: unknown;
+    imported: unknown;
+}
+
+export interface ImportMergeResult {
+    merged: CargoConfig;
+    added: ImportedKey[];
+    overridden: ImportedKey[];
+    skipped: ImportedKey[];
+    import_skipped: SkippedKey[];
+}
+
+export interface ToolchainInfo {
+    name: string;
+    channel?: string | null;
+    date?: string | null;
+    host?: string | null;
+    custom: boolean;
+    is_default: boolean;
+    is_active: boolean;
+}
+
+export interface ActiveToolchain {
+    name: string;
+    reason: string;
+    is_override: boolean;
+}
+
+export interface ToolchainList {
+    toolchains: ToolchainInfo[];
+    default?: string | null;
+    active?: ActiveToolchain | null;
+}
+
+export interface ToolchainUpdate {
+    toolchain: string;
+    status: "updated" | "unchanged" | "installed" | "failed";
+    version: string;
+}
+
+export interface ComponentInfo {
+    name: string;
+    base: string;
+    target?: string | null;
+    installed: boolean;
+}
+
+export interface ComponentList {
+    toolchain: string;
+    components: ComponentInfo[];
+}
+
+export interface ComponentRunResult {
+    output: string;
+    list: ComponentList;
+}
+
+export interface TargetInfo {
+    name: string;
+    installed: boolean;
+    prebuilt: boolean;
+    tier: 1 | 2 | 3;
+    is_host: boolean;
+}
+
+export interface TargetList {
+    toolchain: string;
+    host: string;
+    targets: TargetInfo[];
+}
+
+export interface RustupRunResult {
+    output: string;
+    updates: ToolchainUpdate[];
+    toolchains: ToolchainList;
+}
+
+export interface RustupSettings {
+    path: string;
+    exists: boolean;
+    version?: string | null;
+    default_host_triple?: string | null;
+    default_toolchain?: string | null;
+    profile?: "minimal" | "default" | "complete" | null;
+    auto_self_update?: "enable" | "disable" | "check-only" | null;
+    auto_install?: "enable" | "disable" | null;
+}
+
+export type RustupSettingsUpdate = Partial<
+    Pick<RustupSettings, "default_host_triple" | "default_toolchain" | "profile" | "auto_self_update" | "auto_install">
+>;
+
+export interface DirectoryOverride {
+    path: string;
+    toolchain: string;
+    exists: boolean;
+}
+
+export interface ToolchainResolution {
+    dir: string;
+    toolchain?: string | null;
+    source: "env" | "override" | "toolchain-file" | "default" | "none";
+    origin?: string | null;
+    reason: string;
+    error?: string | null;
+}
+
+export interface ToolchainSpec {
+    channel?: string;
+    path?: string;
+    profile?: "minimal" | "default" | "complete";
+    components?: string[];
+    targets?: string[];
+    [key: string]: unknown;
+}
+
+export interface ToolchainFileStatus {
+    toolchain_installed: boolean;
+    missing_components: string[];
+    missing_targets: string[];
+    issues: string[];
+}
+
+export interface ToolchainFileInfo {
+    path: string;
+    exists: boolean;
+    legacy: boolean;
+    spec: ToolchainSpec;
+    status: ToolchainFileStatus;
+}
+
+export interface SettingSource {
+    kind: "file" | "env" | "env-fallback";
+    source: string;
+    value: unknown;
+}
+
+export interface ExplainedSetting {
+    key: string;
+    value: unknown;
+    origin: SettingSource;
+    sources: SettingSource[];
+    merged: boolean;
+    overrides_app_config: boolean;
+}
+
+export interface ConfigFileInfo {
+    path: string;
+    error?: AppError | null;
+}
+
+export interface ExplainReport {
+    config_path: string;
+    app_config_loaded: boolean;
+    files: ConfigFileInfo[];
+    settings: ExplainedSetting[];
+}
+
+export interface Preset {
+    format: number;
+    id: string;
+    name: string;
+    description: string;
+    version: string;
+    platforms: string[];
+    unset: string[];
+    config: CargoConfig;
+}
+
+export interface PresetInfo extends Preset {
+    builtin: boolean;
+    supported: boolean;
+}
+
+export interface PresetPreview {
+    id: string;
+    supported: boolean;
+    changes: KeyChange[];
+}
+
+// 常用 rustflags、链接器和代理取值，由后端 get_option_library 提供
+export interface PresetOption {
+    value: string;
+    label: string;
+    description: string;
+    platforms: string[];
+}
+
+export interface OptionLibrary {
+    rustflags: PresetOption[];
+    linkers: PresetOption[];
+    proxies: PresetOption[];
+}
+
+export interface ApplyPresetResult {
+    backup?: BackupEntry | null;
+    changes: KeyChange[];
+    loaded: LoadedConfig;
+}
+
+export interface ApplyProfileResult {
+    backup?: BackupEntry | null;
+    changes: KeyChange[];
+    loaded: LoadedConfig;
+    rustup?: RustupEnvWriteResult | null;
+}
+
+export type ErrorCode =
+    | "not_found"
+    | "permission_denied"
+    | "already_exists"
+    | "invalid_input"
+    | "parse"
+    | "serialize"
+    | "conflict"
+    | "locked"
+    | "io"
+    | "command"
+    | "internal";
+
+export interface AppError {
+    code: ErrorCode;
+    message: string;
+    detail?: string;
+    path?: string;
+    key?: string;
+    line?: number;
+    column?: number;
+    snippet?: string;
 }
 
 // 常用目标平台
@@ -138,16 +568,6 @@ export const TARGET_PLATFORMS = [
     { value: "wasm32-unknown-unknown", label: "WebAssembly" },
 ];
 
-// 链接器选项
-export const LINKER_OPTIONS = [
-    { value: "", label: "默认链接器" },
-    { value: "lld-link", label: "lld-link (LLVM, 推荐Windows)" },
-    { value: "rust-lld", label: "rust-lld (Rust内置)" },
-    { value: "mold", label: "mold (超快, Linux)" },
-    { value: "gold", label: "gold (GNU Gold, Linux)" },
-    { value: "link.exe", label: "link.exe (MSVC)" },
-];
-
 // 编译缓存选项
 export const WRAPPER_OPTIONS = [
     { value: "", label: "无 (不使用缓存)" },
@@ -155,21 +575,3 @@ export const WRAPPER_OPTIONS = [
     { value: "ccache", label: "ccache" },
 ];
 
-// 常用 Rustflags
-export const COMMON_RUSTFLAGS = [
-    { value: "-Ctarget-feature=+crt-static", label: "Static CRT", desc: "静态链接运行时(Windows)" },
-    { value: "-Cprefer-dynamic", label: "Prefer Dynamic", desc: "优先动态链接" },
-    { value: "-Clink-arg=-s", label: "Strip Symbols", desc: "剥离符号(减小体积)" },
-    { value: "-Ctarget-cpu=native", label: "Native CPU", desc: "针对当前CPU优化" },
-];
-
-// 代理预设
-export const PROXY_PRESETS = [
-  { label: "无代理", value: "" },
-  { label: "Clash (7890)", value: "127.0.0.1:7890" },
-  { label: "Clash (7891)", value: "127.0.0.1:7891" },
-  { label: "Clash Rev (7897)", value: "127.0.0.1:7897" },
-  { label: "V2Ray (10808)", value: "127.0.0.1:10808" },
-  { label: "V2Ray (1080)", value: "127.0.0.1:1080" },
-  { label: "自定义...", value: "custom" },
-];